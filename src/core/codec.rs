@@ -52,6 +52,12 @@ pub const PROP_NAMES: &[(&[u8], u64)] = &[
     (b"where",      fixed_bits::PROP_WHERE),
     (b"url",        fixed_bits::PROP_URL),
     (b"headers",    fixed_bits::PROP_HEADERS),
+    (b"glob",       fixed_bits::PROP_GLOB),
+    (b"multi",      fixed_bits::PROP_MULTI),
+    (b"transform",  fixed_bits::PROP_TRANSFORM),
+    (b"method",     fixed_bits::PROP_METHOD),
+    (b"body",       fixed_bits::PROP_BODY),
+    (b"fallback",   fixed_bits::PROP_FALLBACK),
 ];
 
 pub fn prop_encode(s: &[u8]) -> u64 {
@@ -99,6 +99,8 @@ pub const CLIENT_KVS:       u64 = 0b0100;
 pub const CLIENT_DB:        u64 = 0b0101;
 pub const CLIENT_HTTP:       u64 = 0b0110;
 pub const CLIENT_FILE:      u64 = 0b0111;
+/// Any other client name; the name is kept as the client record's value.
+pub const CLIENT_CUSTOM:    u64 = 0b1000;
 
 pub const PROP_NULL:       u64 = 0b0000;
 pub const PROP_TYPE:       u64 = 0b0001;
@@ -110,6 +112,12 @@ pub const PROP_TABLE:      u64 = 0b0110;
 pub const PROP_WHERE:      u64 = 0b0111;
 pub const PROP_URL:        u64 = 0b1000;
 pub const PROP_HEADERS:    u64 = 0b1001;
+pub const PROP_GLOB:       u64 = 0b1010;
+pub const PROP_MULTI:      u64 = 0b1011;
+pub const PROP_TRANSFORM:  u64 = 0b1100;
+pub const PROP_METHOD:     u64 = 0b1101;
+pub const PROP_BODY:       u64 = 0b1110;
+pub const PROP_FALLBACK:   u64 = 0b1111;
 
 pub const TYPE_NULL:     u64 = 0b00000;
 pub const TYPE_I32:      u64 = 0b00100;
@@ -2,11 +2,12 @@ extern crate alloc;
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::collections::BTreeMap;
+use std::sync::Mutex;
 
 use super::fixed_bits;
 use super::codec;
 use super::pool::DynamicPool;
-use super::parser::ParsedManifest;
+use super::parser::{ParsedManifest, ParseOptions, qualify_path};
 
 /// A resolved or unresolved config value produced by `build_config`.
 /// State layer is responsible for resolving `Placeholder` variants via `State::get()`.
@@ -21,6 +22,31 @@ pub enum ConfigValue {
     Map(Vec<(String, String)>),
     /// Numeric client id.
     Client(u64),
+    /// Structured `where`: (column → value) pairs, each value resolved like a prop value.
+    Params(Vec<(String, ConfigValue)>),
+}
+
+/// How key segments are compared when walking a manifest path.
+/// Applied to both the looked-up segment and the stored key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalizeMode {
+    /// Byte-for-byte match.
+    #[default]
+    Exact,
+    /// Ignores leading/trailing ASCII whitespace.
+    Trim,
+    /// Ignores leading/trailing ASCII whitespace and ASCII case.
+    TrimLowercase,
+}
+
+impl NormalizeMode {
+    fn matches(self, stored: &[u8], target: &[u8]) -> bool {
+        match self {
+            NormalizeMode::Exact => stored == target,
+            NormalizeMode::Trim => stored.trim_ascii() == target.trim_ascii(),
+            NormalizeMode::TrimLowercase => stored.trim_ascii().eq_ignore_ascii_case(target.trim_ascii()),
+        }
+    }
 }
 
 /// Owns all parsed manifest data and provides decode queries.
@@ -32,6 +58,12 @@ pub struct Manifest {
     pub values: Vec<[u64; 2]>,
     pub path_map: Vec<Vec<u16>>,
     pub children_map: Vec<Vec<u16>>,
+    pub normalize: NormalizeMode,
+    pub options: ParseOptions,
+    /// `get_meta` results keyed by (file, path); cleared whenever a file is added.
+    meta_cache: Mutex<BTreeMap<(String, String), MetaIndices>>,
+    /// Runtime replacements for static leaf values, keyed by key index. Not written by `to_bytes`.
+    static_overrides: BTreeMap<u16, String>,
 }
 
 impl Manifest {
@@ -43,15 +75,117 @@ impl Manifest {
             values: alloc::vec![[0, 0]],
             path_map: alloc::vec![alloc::vec![]],
             children_map: alloc::vec![alloc::vec![]],
+            normalize: NormalizeMode::Exact,
+            options: ParseOptions::default(),
+            meta_cache: Mutex::new(BTreeMap::new()),
+            static_overrides: BTreeMap::new(),
         }
     }
 
+    pub fn with_key_normalization(mut self, mode: NormalizeMode) -> Self {
+        self.normalize = mode;
+        self.meta_cache.get_mut().unwrap().clear();
+        self
+    }
+
+    /// Sets the parser options used for files parsed into this Manifest; placeholders
+    /// are rendered back with the same delimiters.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Makes files parsed afterwards fail on `_`-prefixed keys other than
+    /// `_load`/`_store`/`_state`.
+    pub fn with_strict_meta(mut self, strict: bool) -> Self {
+        self.options.strict_meta = strict;
+        self
+    }
+
+    /// Sets the deepest nesting accepted in files parsed afterwards (see `ParseOptions::max_depth`).
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.options.max_depth = depth;
+        self
+    }
+
     pub fn is_loaded(&self, file: &str) -> bool {
         self.files.contains_key(file)
     }
 
     pub fn insert(&mut self, file: String, pm: ParsedManifest) {
         self.files.insert(file, pm);
+        self.meta_cache.get_mut().unwrap().clear();
+    }
+
+    /// Forgets `file` so it can be inserted again, and rebuilds the pools from the
+    /// remaining files so its records do not linger. Returns the old → new key index
+    /// map (0 for dropped records), or `None` when `file` was not loaded.
+    pub fn remove(&mut self, file: &str) -> Option<Vec<u16>> {
+        self.meta_cache.get_mut().unwrap().clear();
+        self.files.remove(file)?;
+        Some(self.compact())
+    }
+
+    /// Copies every record reachable from a loaded file into fresh pools, keeping
+    /// their order. Returns the old → new key index map.
+    fn compact(&mut self) -> Vec<u16> {
+        let mut reachable = alloc::vec![false; self.keys.len()];
+        let mut stack: Vec<u16> = self.files.values().map(|pm| pm.file_key_idx).collect();
+        while let Some(idx) = stack.pop() {
+            let Some(&record) = self.keys.get(idx as usize) else { continue };
+            if idx == 0 || reachable[idx as usize] {
+                continue;
+            }
+            reachable[idx as usize] = true;
+            if fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 0 {
+                stack.extend(self.children_of(record));
+            }
+        }
+        let mut key_map = alloc::vec![0u16; self.keys.len()];
+        let kept = reachable.iter().enumerate().filter(|(_, r)| **r);
+        for (new, (old, _)) in (1u16..).zip(kept) {
+            key_map[old] = new;
+        }
+
+        let mut fresh = Compaction::default();
+        let mut keys = alloc::vec![0u64];
+        for (&record, _) in self.keys.iter().zip(&reachable).filter(|(_, r)| **r) {
+            let is_path = fixed_bits::get(record, fixed_bits::K_OFFSET_IS_PATH, fixed_bits::K_MASK_IS_PATH) == 1;
+            let is_leaf = fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 1;
+            let has_children = fixed_bits::get(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN) == 1;
+
+            let dyn_idx = fixed_bits::get(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
+            let dyn_idx = if is_path { fresh.path(self, dyn_idx) } else { fresh.dynamic(self, dyn_idx) };
+
+            let child = fixed_bits::get(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD) as u16;
+            let child = if child == 0 {
+                0
+            } else if is_leaf {
+                fresh.value(self, child)
+            } else if has_children {
+                let list = self.children_map.get(child as usize).map(|c| c.iter().map(|&k| key_map[k as usize]).collect()).unwrap_or_default();
+                fresh.children_map.push(list);
+                (fresh.children_map.len() - 1) as u16
+            } else {
+                key_map[child as usize]
+            };
+
+            let record = fixed_bits::set(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC, dyn_idx as u64);
+            keys.push(fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, child as u64));
+        }
+
+        self.keys = keys;
+        self.values = fresh.values;
+        self.path_map = fresh.path_map;
+        self.children_map = fresh.children_map;
+        self.dynamic = fresh.pool;
+        for pm in self.files.values_mut() {
+            pm.file_key_idx = key_map[pm.file_key_idx as usize];
+        }
+        self.static_overrides = core::mem::take(&mut self.static_overrides).into_iter()
+            .filter_map(|(idx, v)| key_map.get(idx as usize).filter(|&&n| n != 0).map(|&n| (n, v)))
+            .collect();
+        key_map
     }
 
     /// Returns the direct field-key and meta-key children indices of a record.
@@ -94,7 +228,7 @@ impl Manifest {
                 continue;
             }
             let dyn_idx = fixed_bits::get(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
-            if self.dynamic.get(dyn_idx)? != target {
+            if !self.normalize.matches(self.dynamic.get(dyn_idx)?, target) {
                 continue;
             }
             if rest.is_empty() {
@@ -109,9 +243,263 @@ impl Manifest {
         None
     }
 
+    /// Returns the path a placeholder written in the value or meta of `context_key`
+    /// (`file.a.b`) is qualified to at parse time: a path without `.` is prefixed with
+    /// the key's file and ancestors (`${org_id}` in `cache.user.tenant_id` →
+    /// `cache.user.org_id`); a path with `.` is already absolute. The placeholder may be
+    /// given bare or with its delimiters. Whether the target exists is not checked.
+    pub fn resolve_key(&self, context_key: &str, placeholder: &str) -> String {
+        let path = placeholder.as_bytes();
+        let path = path.strip_prefix(self.options.placeholder_open.as_slice())
+            .and_then(|p| p.strip_suffix(self.options.placeholder_close.as_slice()))
+            .unwrap_or(path);
+        let mut segments: Vec<&[u8]> = context_key.split('.').map(str::as_bytes).collect();
+        segments.pop();
+        let (file, ancestors) = match segments.split_first() {
+            Some((file, ancestors)) => (String::from_utf8_lossy(file).into_owned(), ancestors),
+            None => (String::from(context_key), &[][..]),
+        };
+        String::from_utf8_lossy(&qualify_path(path, &file, ancestors)).into_owned()
+    }
+
+    /// Returns the qualified dot-path (`file.a.b`) of a field key record, or `None`
+    /// if `key_idx` is not a field key reachable from a loaded file.
+    pub fn key_path(&self, key_idx: u16) -> Option<String> {
+        for (file, pm) in &self.files {
+            if pm.file_key_idx == key_idx {
+                return Some(file.clone());
+            }
+            let record = self.keys.get(pm.file_key_idx as usize).copied()?;
+            let mut segments: Vec<&[u8]> = Vec::new();
+            if self.path_to(key_idx, &self.children_of(record), &mut segments) {
+                let mut path = file.clone();
+                for seg in segments {
+                    path.push('.');
+                    path.push_str(&String::from_utf8_lossy(seg));
+                }
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    fn path_to<'a>(&'a self, target: u16, candidates: &[u16], segments: &mut Vec<&'a [u8]>) -> bool {
+        for &idx in candidates {
+            let record = match self.keys.get(idx as usize).copied() {
+                Some(r) => r,
+                None => continue,
+            };
+            if fixed_bits::get(record, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT) != fixed_bits::ROOT_NULL
+                || fixed_bits::get(record, fixed_bits::K_OFFSET_IS_PATH, fixed_bits::K_MASK_IS_PATH) == 1 {
+                continue;
+            }
+            let dyn_idx = fixed_bits::get(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
+            let name = match self.dynamic.get(dyn_idx) {
+                Some(n) => n,
+                None => continue,
+            };
+            segments.push(name);
+            if idx == target {
+                return true;
+            }
+            let is_leaf = fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 1;
+            if !is_leaf && self.path_to(target, &self.children_of(record), segments) {
+                return true;
+            }
+            segments.pop();
+        }
+        false
+    }
+
+    /// Advisory checks for meta combinations `State::get` can never use, one message
+    /// per finding, prefixed with the qualified key:
+    /// - a `_load` without `client` never runs
+    /// - a `_store` declared on a node whose `_load.client` is State is never read back:
+    ///   `get` follows the referenced key and skips `_store`
+    pub fn lint(&self, file: &str) -> Vec<String> {
+        let mut findings = alloc::vec![];
+        if let Some(record) = self.files.get(file).and_then(|pm| self.keys.get(pm.file_key_idx as usize).copied()) {
+            self.lint_node(file, "", record, &mut findings);
+        }
+        findings
+    }
+
+    fn lint_node(&self, file: &str, path: &str, record: u64, findings: &mut Vec<String>) {
+        let mut own = MetaIndices::default();
+        self.collect_meta(record, &mut own);
+        if own.load.is_some() || own.store.is_some() {
+            let qualified = if path.is_empty() { String::from(file) } else { alloc::format!("{}.{}", file, path) };
+            let meta = self.get_meta(file, path);
+            if let Some(load_idx) = meta.load {
+                let client = self.get_client(load_idx);
+                if own.load.is_some() && client == fixed_bits::CLIENT_NULL {
+                    findings.push(alloc::format!("{}: _load has no client and never runs", qualified));
+                }
+                if client == fixed_bits::CLIENT_STATE && own.store.is_some() {
+                    findings.push(alloc::format!("{}: _store is never read because _load.client is State", qualified));
+                }
+            }
+        }
+
+        if fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 1 {
+            return;
+        }
+        for idx in self.children_of(record) {
+            let Some(child) = self.keys.get(idx as usize).copied() else { continue };
+            if fixed_bits::get(child, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT) != fixed_bits::ROOT_NULL {
+                continue;
+            }
+            let dyn_idx = fixed_bits::get(child, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
+            let Some(name) = self.dynamic.get(dyn_idx) else { continue };
+            let name = String::from_utf8_lossy(name);
+            let child_path = if path.is_empty() { name.into_owned() } else { alloc::format!("{}.{}", path, name) };
+            self.lint_node(file, &child_path, child, findings);
+        }
+    }
+
+    /// Returns the `_state.type` in effect for a field key (e.g. `b"integer"`): its own,
+    /// else the nearest ancestor's. `None` when no `_state` on the way declares a known type.
+    pub fn get_type(&self, key_idx: u16) -> Option<&'static [u8]> {
+        let qualified = self.key_path(key_idx)?;
+        let (file, path) = split_key(&qualified);
+        self.get_meta(file, path).state.and_then(|idx| self.state_type(idx))
+    }
+
+    /// Returns the `_state.cache_ttl` declared directly on a field key, in seconds
+    /// (fractions allowed). `None` when absent, negative or not a number.
+    pub fn cache_ttl(&self, key_idx: u16) -> Option<f64> {
+        let record = self.keys.get(key_idx as usize).copied()?;
+        if fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 1 {
+            return None;
+        }
+        let state_idx = self.children_of(record).into_iter().find(|&idx| {
+            self.keys.get(idx as usize).is_some_and(|&r| {
+                fixed_bits::get(r, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT) == fixed_bits::ROOT_STATE
+            })
+        })?;
+        let ttl = self.children_of(self.keys[state_idx as usize]).into_iter()
+            .filter_map(|idx| self.keys.get(idx as usize).copied())
+            .find(|&r| fixed_bits::get(r, fixed_bits::K_OFFSET_PROP, fixed_bits::K_MASK_PROP) == fixed_bits::PROP_TTL)?;
+        let value_idx = fixed_bits::get(ttl, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD) as u16;
+        match self.decode_value(value_idx)? {
+            ConfigValue::Str(s) => s.trim().parse::<f64>().ok().filter(|t| t.is_finite() && *t >= 0.0),
+            _ => None,
+        }
+    }
+
+    /// Decodes the `type` of the `_state` record at `state_idx`.
+    pub fn state_type(&self, state_idx: u16) -> Option<&'static [u8]> {
+        let record = self.keys.get(state_idx as usize).copied()?;
+        self.children_of(record).into_iter()
+            .filter_map(|idx| self.keys.get(idx as usize).copied())
+            .find_map(|r| codec::type_decode(fixed_bits::get(r, fixed_bits::K_OFFSET_TYPE, fixed_bits::K_MASK_TYPE)))
+    }
+
+    /// Returns the (name, key_idx) of each field key directly under `key_idx`, in
+    /// declaration order. Meta keys are skipped.
+    pub fn field_children(&self, key_idx: u16) -> Vec<(String, u16)> {
+        let Some(record) = self.keys.get(key_idx as usize).copied() else { return alloc::vec![] };
+        if fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 1 {
+            return alloc::vec![];
+        }
+        self.children_of(record).into_iter()
+            .filter_map(|idx| {
+                let child = self.keys.get(idx as usize).copied()?;
+                if fixed_bits::get(child, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT) != fixed_bits::ROOT_NULL {
+                    return None;
+                }
+                let dyn_idx = fixed_bits::get(child, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
+                let name = self.dynamic.get(dyn_idx)?;
+                Some((String::from_utf8_lossy(name).into_owned(), idx))
+            })
+            .collect()
+    }
+
+    /// Returns the static scalar fields declared directly under a field key, as
+    /// (field_name, value) pairs. Fields without a value and meta keys are skipped;
+    /// placeholders are embedded as `${path}`.
+    pub fn static_fields(&self, key_idx: u16) -> Vec<(String, String)> {
+        let record = match self.keys.get(key_idx as usize).copied() {
+            Some(r) => r,
+            None => return alloc::vec![],
+        };
+        if fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 1 {
+            return alloc::vec![];
+        }
+        let mut fields = alloc::vec![];
+        for child_idx in self.children_of(record) {
+            let child = match self.keys.get(child_idx as usize).copied() {
+                Some(c) => c,
+                None => continue,
+            };
+            if fixed_bits::get(child, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT) != fixed_bits::ROOT_NULL
+                || fixed_bits::get(child, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 0 {
+                continue;
+            }
+            let dyn_idx = fixed_bits::get(child, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
+            let name = match self.dynamic.get(dyn_idx) {
+                Some(n) => String::from_utf8_lossy(n).into_owned(),
+                None => continue,
+            };
+            if let Some(value) = self.static_value(child_idx) {
+                fields.push((name, value));
+            }
+        }
+        fields
+    }
+
+    /// Returns the static value of the leaf field key at `key_idx` (a `set_static` override
+    /// first), with placeholders embedded as `${path}`. None for non-leaves and null leaves.
+    pub fn static_value(&self, key_idx: u16) -> Option<String> {
+        let record = self.keys.get(key_idx as usize).copied()?;
+        if key_idx == 0
+            || fixed_bits::get(record, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT) != fixed_bits::ROOT_NULL
+            || fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 0 {
+            return None;
+        }
+        if let Some(value) = self.static_overrides.get(&key_idx) {
+            return Some(value.clone());
+        }
+        let value_idx = fixed_bits::get(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD) as usize;
+        if value_idx == 0 {
+            return None;
+        }
+        self.values.get(value_idx).copied().and_then(|vo| self.decode_value_tokens(vo))
+    }
+
+    /// Replaces the static value of the leaf field key at `key_idx` in memory; `static_fields`
+    /// reports `value` until `clear_static`. Returns false when `key_idx` is not a leaf field key.
+    pub fn set_static(&mut self, key_idx: u16, value: &str) -> bool {
+        let Some(record) = self.keys.get(key_idx as usize).copied() else { return false };
+        if key_idx == 0
+            || fixed_bits::get(record, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT) != fixed_bits::ROOT_NULL
+            || fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 0 {
+            return false;
+        }
+        self.static_overrides.insert(key_idx, value.to_string());
+        true
+    }
+
+    /// Drops a `set_static` override, restoring the manifest's value. Returns whether one existed.
+    pub fn clear_static(&mut self, key_idx: u16) -> bool {
+        self.static_overrides.remove(&key_idx).is_some()
+    }
+
     /// Returns meta record indices (_load/_store/_state) for a dot-path node.
-    /// Collects from root to node; child overrides parent.
+    /// Collects from root to node; child overrides parent, except that a `_state`
+    /// without a `type` keeps the ancestor's typed `_state`. Results are memoized until
+    /// the next file is added.
     pub fn get_meta(&self, file: &str, path: &str) -> MetaIndices {
+        let cache_key = (file.to_string(), path.to_string());
+        if let Some(meta) = self.meta_cache.lock().unwrap().get(&cache_key) {
+            return *meta;
+        }
+        let meta = self.walk_meta(file, path);
+        self.meta_cache.lock().unwrap().insert(cache_key, meta);
+        meta
+    }
+
+    fn walk_meta(&self, file: &str, path: &str) -> MetaIndices {
         let file_idx = match self.files.get(file) {
             Some(pm) => pm.file_key_idx,
             None => return MetaIndices::default(),
@@ -137,7 +525,7 @@ impl Manifest {
                     continue;
                 }
                 let dyn_idx = fixed_bits::get(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
-                if self.dynamic.get(dyn_idx) == Some(segment.as_bytes()) {
+                if self.dynamic.get(dyn_idx).is_some_and(|k| self.normalize.matches(k, segment.as_bytes())) {
                     self.collect_meta(record, &mut meta);
                     found_idx = Some(idx);
                     break;
@@ -164,7 +552,7 @@ impl Manifest {
             match root {
                 fixed_bits::ROOT_LOAD  => meta.load  = Some(idx),
                 fixed_bits::ROOT_STORE => meta.store = Some(idx),
-                fixed_bits::ROOT_STATE => meta.state = Some(idx),
+                fixed_bits::ROOT_STATE if meta.state.is_none() || self.state_type(idx).is_some() => meta.state = Some(idx),
                 _ => {}
             }
         }
@@ -190,6 +578,18 @@ impl Manifest {
         fixed_bits::CLIENT_NULL
     }
 
+    /// Returns the nested `fallback` block of a `_load` record, usable with
+    /// `build_config`/`load_fallback` like the record itself.
+    pub fn load_fallback(&self, meta_idx: u16) -> Option<u16> {
+        let record = self.keys.get(meta_idx as usize).copied()?;
+        self.children_of(record).into_iter().find(|&idx| {
+            self.keys.get(idx as usize).is_some_and(|&r| {
+                fixed_bits::get(r, fixed_bits::K_OFFSET_PROP, fixed_bits::K_MASK_PROP) == fixed_bits::PROP_FALLBACK
+                    && fixed_bits::get(r, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 0
+            })
+        })
+    }
+
     /// Decodes a meta record into a list of (prop_name, ConfigValue) pairs.
     /// The caller (State) is responsible for resolving any `ConfigValue::Placeholder` entries.
     pub fn build_config(&self, meta_idx: u16) -> Option<Vec<(String, ConfigValue)>> {
@@ -220,6 +620,9 @@ impl Manifest {
 
             if client != fixed_bits::CLIENT_NULL {
                 entries.push(("client".into(), ConfigValue::Client(client)));
+                if client == fixed_bits::CLIENT_CUSTOM && let Some(cv) = self.decode_value(value_idx) {
+                    entries.push(("client_name".into(), cv));
+                }
                 continue;
             }
 
@@ -232,6 +635,11 @@ impl Manifest {
                 if let Some(pairs) = self.decode_map(child_idx) {
                     entries.push(("map".into(), ConfigValue::Map(pairs)));
                 }
+            } else if (prop_name == b"where" || prop_name == b"body") && !is_leaf {
+                if let Some(params) = self.decode_params(child_idx) {
+                    let name = String::from_utf8_lossy(prop_name).into_owned();
+                    entries.push((name, ConfigValue::Params(params)));
+                }
             } else if prop_name == b"connection" {
                 if value_idx != 0 {
                     if let Some(cv) = self.decode_value(value_idx) {
@@ -290,6 +698,35 @@ impl Manifest {
         Some(pairs)
     }
 
+    /// Decodes a structured `where` prop's children into (column, value) pairs.
+    pub fn decode_params(&self, where_idx: u16) -> Option<Vec<(String, ConfigValue)>> {
+        let record = self.keys.get(where_idx as usize).copied()?;
+        let child_idx = fixed_bits::get(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD) as usize;
+        if child_idx == 0 {
+            return Some(alloc::vec![]);
+        }
+        let children = if fixed_bits::get(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN) == 1 {
+            self.children_map.get(child_idx)?.to_vec()
+        } else {
+            alloc::vec![child_idx as u16]
+        };
+
+        let mut params = alloc::vec![];
+        for &c in &children {
+            let child = self.keys.get(c as usize).copied()?;
+            let dyn_idx   = fixed_bits::get(child, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC) as u16;
+            let value_idx = fixed_bits::get(child, fixed_bits::K_OFFSET_CHILD,   fixed_bits::K_MASK_CHILD) as u16;
+            let column = String::from_utf8_lossy(self.dynamic.get(dyn_idx)?).into_owned();
+            let value = if value_idx == 0 {
+                ConfigValue::Str(String::new())
+            } else {
+                self.decode_value(value_idx)?
+            };
+            params.push((column, value));
+        }
+        Some(params)
+    }
+
     /// Decodes a value record into a ConfigValue.
     /// If the value is a placeholder path, returns Placeholder(path_string).
     /// If it's a template or static string, returns Str(string) or Placeholder for single-path tokens.
@@ -310,19 +747,12 @@ impl Manifest {
     }
 
     /// Decodes all tokens of a value record into a raw string,
-    /// embedding placeholder paths as `${path}` so the caller can resolve them.
+    /// embedding placeholder paths as `${path}` (or the configured delimiters)
+    /// so the caller can resolve them.
     pub fn decode_value_tokens(&self, vo: [u64; 2]) -> Option<String> {
-        const TOKEN_OFFSETS: [(u32, u32); 6] = [
-            (fixed_bits::V_OFFSET_T0_IS_PATH, fixed_bits::V_OFFSET_T0_DYNAMIC),
-            (fixed_bits::V_OFFSET_T1_IS_PATH, fixed_bits::V_OFFSET_T1_DYNAMIC),
-            (fixed_bits::V_OFFSET_T2_IS_PATH, fixed_bits::V_OFFSET_T2_DYNAMIC),
-            (fixed_bits::V_OFFSET_T3_IS_PATH, fixed_bits::V_OFFSET_T3_DYNAMIC),
-            (fixed_bits::V_OFFSET_T4_IS_PATH, fixed_bits::V_OFFSET_T4_DYNAMIC),
-            (fixed_bits::V_OFFSET_T5_IS_PATH, fixed_bits::V_OFFSET_T5_DYNAMIC),
-        ];
 
         let mut result = String::new();
-        for (i, (off_is_path, off_dynamic)) in TOKEN_OFFSETS.iter().enumerate() {
+        for (i, (off_is_path, off_dynamic)) in VALUE_TOKEN_OFFSETS.iter().enumerate() {
             let word = if i < 3 { 0 } else { 1 };
             let is_path = fixed_bits::get(vo[word], *off_is_path, fixed_bits::V_MASK_IS_PATH) == 1;
             let dyn_idx = fixed_bits::get(vo[word], *off_dynamic, fixed_bits::V_MASK_DYNAMIC) as u16;
@@ -330,9 +760,9 @@ impl Manifest {
 
             if is_path {
                 let path = self.resolve_path(dyn_idx)?;
-                result.push_str("${");
+                result.push_str(&String::from_utf8_lossy(&self.options.placeholder_open));
                 result.push_str(&path);
-                result.push('}');
+                result.push_str(&String::from_utf8_lossy(&self.options.placeholder_close));
             } else {
                 let b = self.dynamic.get(dyn_idx)?;
                 result.push_str(&String::from_utf8_lossy(b));
@@ -349,6 +779,316 @@ impl Manifest {
             .collect();
         Some(parts.join("."))
     }
+
+    /// Serializes all pools and the file table into a compact little-endian binary.
+    ///
+    /// Layout: magic `SEM1`, then files, dynamic, keys, values, path_map, children_map,
+    /// each prefixed by a u32 entry count. Index 0 (null) entries are included as-is.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+
+        put_u32(&mut out, self.files.len() as u32);
+        for (name, pm) in &self.files {
+            put_bytes(&mut out, name.as_bytes());
+            out.extend_from_slice(&pm.file_key_idx.to_le_bytes());
+        }
+
+        put_u32(&mut out, self.dynamic.len() as u32);
+        for i in 0..self.dynamic.len() {
+            put_bytes(&mut out, self.dynamic.get(i as u16).unwrap_or_default());
+        }
+
+        put_u32(&mut out, self.keys.len() as u32);
+        for k in &self.keys {
+            out.extend_from_slice(&k.to_le_bytes());
+        }
+
+        put_u32(&mut out, self.values.len() as u32);
+        for v in &self.values {
+            out.extend_from_slice(&v[0].to_le_bytes());
+            out.extend_from_slice(&v[1].to_le_bytes());
+        }
+
+        for list in [&self.path_map, &self.children_map] {
+            put_u32(&mut out, list.len() as u32);
+            for entry in list {
+                put_u32(&mut out, entry.len() as u32);
+                for idx in entry {
+                    out.extend_from_slice(&idx.to_le_bytes());
+                }
+            }
+        }
+        out
+    }
+
+    /// Restores a Manifest written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = Reader { buf: bytes, pos: 0 };
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err("not a precompiled manifest (bad magic)".into());
+        }
+
+        let mut files = BTreeMap::new();
+        for _ in 0..r.u32()? {
+            let name = String::from_utf8(r.bytes()?.to_vec())
+                .map_err(|_| String::from("file name is not UTF-8"))?;
+            files.insert(name, ParsedManifest { file_key_idx: r.u16()? });
+        }
+
+        let mut dynamic = DynamicPool::new();
+        let dyn_len = r.u32()?;
+        for i in 0..dyn_len {
+            let b = r.bytes()?;
+            if i > 0 && dynamic.intern(b) as u32 != i {
+                return Err(alloc::format!("duplicate dynamic pool entry at {}", i));
+            }
+        }
+
+        let mut keys = Vec::new();
+        for _ in 0..r.u32()? {
+            keys.push(r.u64()?);
+        }
+
+        let mut values = Vec::new();
+        for _ in 0..r.u32()? {
+            values.push([r.u64()?, r.u64()?]);
+        }
+
+        let mut lists: [Vec<Vec<u16>>; 2] = [Vec::new(), Vec::new()];
+        for list in lists.iter_mut() {
+            for _ in 0..r.u32()? {
+                let mut entry = Vec::new();
+                for _ in 0..r.u32()? {
+                    entry.push(r.u16()?);
+                }
+                list.push(entry);
+            }
+        }
+        let [path_map, children_map] = lists;
+
+        if r.pos != bytes.len() {
+            return Err("trailing bytes after precompiled manifest".into());
+        }
+        if keys.is_empty() || values.is_empty() || path_map.is_empty() || children_map.is_empty() {
+            return Err("precompiled manifest is missing null entries".into());
+        }
+
+        Ok(Self { files, dynamic, keys, values, path_map, children_map, normalize: NormalizeMode::Exact, options: ParseOptions::default(), meta_cache: Mutex::new(BTreeMap::new()), static_overrides: BTreeMap::new() })
+    }
+
+    /// Appends every file of `other` into this manifest, remapping all pool indices.
+    /// Files already loaded here are left untouched. Fails, leaving the key/value
+    /// pools unchanged, when a combined pool would exceed the 16-bit index limit.
+    pub fn append(&mut self, other: &Manifest) -> Result<(), String> {
+        let max = fixed_bits::K_MASK_CHILD as usize;
+        for (name, own, added) in [
+            ("keys", self.keys.len(), other.keys.len()),
+            ("values", self.values.len(), other.values.len()),
+            ("path_map", self.path_map.len(), other.path_map.len()),
+            ("children_map", self.children_map.len(), other.children_map.len()),
+        ] {
+            if own + added.saturating_sub(1) > max + 1 {
+                return Err(format!("manifest exceeds {} limit of {} entries", name, max));
+            }
+        }
+
+        self.meta_cache.get_mut().unwrap().clear();
+        let dyn_map: Vec<u16> = (0..other.dynamic.len())
+            .map(|i| match i {
+                0 => Ok(0),
+                _ => self.dynamic.try_intern(other.dynamic.get(i as u16).unwrap_or_default()),
+            })
+            .collect::<Result<_, _>>()?;
+        let remap_dyn = |i: u64| dyn_map.get(i as usize).copied().unwrap_or(0) as u64;
+
+        let key_base   = self.keys.len() as u64 - 1;
+        let value_base = self.values.len() as u64 - 1;
+        let path_base  = self.path_map.len() as u64 - 1;
+        let child_base = self.children_map.len() as u64 - 1;
+        let shift = |i: u64, base: u64| if i == 0 { 0 } else { i + base };
+
+        for segs in other.path_map.iter().skip(1) {
+            self.path_map.push(segs.iter().map(|&s| remap_dyn(s as u64) as u16).collect());
+        }
+
+        for &vo in other.values.iter().skip(1) {
+            let mut vo = vo;
+            for (i, (off_is_path, off_dynamic)) in VALUE_TOKEN_OFFSETS.iter().enumerate() {
+                let word = if i < 3 { 0 } else { 1 };
+                let idx = fixed_bits::get(vo[word], *off_dynamic, fixed_bits::V_MASK_DYNAMIC);
+                if idx == 0 { break; }
+                let is_path = fixed_bits::get(vo[word], *off_is_path, fixed_bits::V_MASK_IS_PATH) == 1;
+                let new = if is_path { shift(idx, path_base) } else { remap_dyn(idx) };
+                vo[word] = fixed_bits::set(vo[word], *off_dynamic, fixed_bits::V_MASK_DYNAMIC, new);
+            }
+            self.values.push(vo);
+        }
+
+        for &record in other.keys.iter().skip(1) {
+            let is_path = fixed_bits::get(record, fixed_bits::K_OFFSET_IS_PATH, fixed_bits::K_MASK_IS_PATH) == 1;
+            let is_leaf = fixed_bits::get(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF) == 1;
+            let has_children = fixed_bits::get(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN) == 1;
+
+            let dyn_idx = fixed_bits::get(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC);
+            let dyn_idx = if is_path { shift(dyn_idx, path_base) } else { remap_dyn(dyn_idx) };
+
+            let child = fixed_bits::get(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD);
+            let child = if is_leaf {
+                shift(child, value_base)
+            } else if has_children {
+                shift(child, child_base)
+            } else {
+                shift(child, key_base)
+            };
+
+            let record = fixed_bits::set(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC, dyn_idx);
+            let record = fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, child);
+            self.keys.push(record);
+        }
+
+        for children in other.children_map.iter().skip(1) {
+            self.children_map.push(children.iter().map(|&c| shift(c as u64, key_base) as u16).collect());
+        }
+
+        for (name, pm) in &other.files {
+            if !self.files.contains_key(name) {
+                let file_key_idx = shift(pm.file_key_idx as u64, key_base) as u16;
+                self.files.insert(name.clone(), ParsedManifest { file_key_idx });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fresh pools filled by `Manifest::compact`, with memoized old → new index maps.
+struct Compaction {
+    pool: DynamicPool,
+    values: Vec<[u64; 2]>,
+    path_map: Vec<Vec<u16>>,
+    children_map: Vec<Vec<u16>>,
+    dyn_map: BTreeMap<u16, u16>,
+    value_map: BTreeMap<u16, u16>,
+    path_idx_map: BTreeMap<u16, u16>,
+}
+
+impl Default for Compaction {
+    fn default() -> Self {
+        Self {
+            pool: DynamicPool::new(),
+            values: alloc::vec![[0, 0]],
+            path_map: alloc::vec![alloc::vec![]],
+            children_map: alloc::vec![alloc::vec![]],
+            dyn_map: BTreeMap::new(),
+            value_map: BTreeMap::new(),
+            path_idx_map: BTreeMap::new(),
+        }
+    }
+}
+
+impl Compaction {
+    fn dynamic(&mut self, from: &Manifest, idx: u16) -> u16 {
+        if idx == 0 {
+            return 0;
+        }
+        if let Some(&n) = self.dyn_map.get(&idx) {
+            return n;
+        }
+        let n = self.pool.intern(from.dynamic.get(idx).unwrap_or_default());
+        self.dyn_map.insert(idx, n);
+        n
+    }
+
+    fn path(&mut self, from: &Manifest, idx: u16) -> u16 {
+        if idx == 0 {
+            return 0;
+        }
+        if let Some(&n) = self.path_idx_map.get(&idx) {
+            return n;
+        }
+        let segments = from.path_map.get(idx as usize).cloned().unwrap_or_default();
+        let segments = segments.into_iter().map(|seg| self.dynamic(from, seg)).collect();
+        self.path_map.push(segments);
+        let n = (self.path_map.len() - 1) as u16;
+        self.path_idx_map.insert(idx, n);
+        n
+    }
+
+    fn value(&mut self, from: &Manifest, idx: u16) -> u16 {
+        if let Some(&n) = self.value_map.get(&idx) {
+            return n;
+        }
+        let mut vo = from.values.get(idx as usize).copied().unwrap_or([0, 0]);
+        for (i, (off_is_path, off_dynamic)) in VALUE_TOKEN_OFFSETS.iter().enumerate() {
+            let word = if i < 3 { 0 } else { 1 };
+            let token = fixed_bits::get(vo[word], *off_dynamic, fixed_bits::V_MASK_DYNAMIC) as u16;
+            if token == 0 { break; }
+            let is_path = fixed_bits::get(vo[word], *off_is_path, fixed_bits::V_MASK_IS_PATH) == 1;
+            let token = if is_path { self.path(from, token) } else { self.dynamic(from, token) };
+            vo[word] = fixed_bits::set(vo[word], *off_dynamic, fixed_bits::V_MASK_DYNAMIC, token as u64);
+        }
+        self.values.push(vo);
+        let n = (self.values.len() - 1) as u16;
+        self.value_map.insert(idx, n);
+        n
+    }
+}
+
+/// (is_path, dynamic) bit offsets of the six template tokens of a value record.
+const VALUE_TOKEN_OFFSETS: [(u32, u32); 6] = [
+    (fixed_bits::V_OFFSET_T0_IS_PATH, fixed_bits::V_OFFSET_T0_DYNAMIC),
+    (fixed_bits::V_OFFSET_T1_IS_PATH, fixed_bits::V_OFFSET_T1_DYNAMIC),
+    (fixed_bits::V_OFFSET_T2_IS_PATH, fixed_bits::V_OFFSET_T2_DYNAMIC),
+    (fixed_bits::V_OFFSET_T3_IS_PATH, fixed_bits::V_OFFSET_T3_DYNAMIC),
+    (fixed_bits::V_OFFSET_T4_IS_PATH, fixed_bits::V_OFFSET_T4_DYNAMIC),
+    (fixed_bits::V_OFFSET_T5_IS_PATH, fixed_bits::V_OFFSET_T5_DYNAMIC),
+];
+
+const MAGIC: &[u8] = b"SEM1";
+
+/// Splits a dot-separated key into its file and the path within it, at the first dot.
+/// A key with no dot names the whole file and yields an empty path.
+pub fn split_key(key: &str) -> (&str, &str) {
+    key.split_once('.').unwrap_or((key, ""))
+}
+
+fn put_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_bytes(out: &mut Vec<u8>, b: &[u8]) {
+    put_u32(out, b.len() as u32);
+    out.extend_from_slice(b);
+}
+
+/// Cursor over a `to_bytes` buffer.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n)
+            .filter(|&e| e <= self.buf.len())
+            .ok_or_else(|| alloc::format!("unexpected end of precompiled manifest at {}", self.pos))?;
+        let s = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(s)
+    }
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap_or_default()))
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap_or_default()))
+    }
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap_or_default()))
+    }
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let n = self.u32()? as usize;
+        self.take(n)
+    }
 }
 
 impl Default for Manifest {
@@ -358,7 +1098,7 @@ impl Default for Manifest {
 }
 
 /// Indices of meta records for a given node, collected from root to node (child overrides parent).
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct MetaIndices {
     pub load:  Option<u16>,
     pub store: Option<u16>,
@@ -368,14 +1108,14 @@ pub struct MetaIndices {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::parser::{Value, parse};
+    use super::super::parser::{Value, Pools, parse};
 
     /// Builds a Manifest from a inline DSL mapping.
     /// `entries` is the top-level key→subtree mapping for a single file.
     fn make(filename: &str, entries: Vec<(&str, Value)>) -> Manifest {
         let mut m = Manifest::new();
         let root = Value::Mapping(entries.into_iter().map(|(k, v)| (k.as_bytes().to_vec(), v)).collect());
-        let pm = parse(filename, root, &mut m.dynamic, &mut m.keys, &mut m.values, &mut m.path_map, &mut m.children_map).unwrap();
+        let pm = parse(filename, root, Pools { dynamic: &mut m.dynamic, keys: &mut m.keys, values: &mut m.values, path_map: &mut m.path_map, children_map: &mut m.children_map }, &ParseOptions::default()).unwrap();
         m.insert(filename.to_string(), pm);
         m
     }
@@ -457,7 +1197,7 @@ mod tests {
                 ])),
             ])),
         ]);
-        let pm2 = parse("connection", root2, &mut m.dynamic, &mut m.keys, &mut m.values, &mut m.path_map, &mut m.children_map).unwrap();
+        let pm2 = parse("connection", root2, Pools { dynamic: &mut m.dynamic, keys: &mut m.keys, values: &mut m.values, path_map: &mut m.path_map, children_map: &mut m.children_map }, &ParseOptions::default()).unwrap();
         m.insert("connection".to_string(), pm2);
 
         let cache_idx = m.find("cache", "user").unwrap();
@@ -465,6 +1205,174 @@ mod tests {
         assert_ne!(cache_idx, conn_idx);
     }
 
+    // --- key normalization ---
+
+    fn spaced_manifest(mode: NormalizeMode) -> Manifest {
+        let mut m = make("conn", vec![
+            (" host ", mapping(vec![
+                ("_store", mapping(vec![("client", scalar("InMemory")), ("key", scalar("h"))])),
+            ])),
+            ("Port", scalar("5432")),
+        ]);
+        m.normalize = mode;
+        m
+    }
+
+    #[test]
+    fn test_find_exact_by_default() {
+        let m = spaced_manifest(NormalizeMode::default());
+        assert!(m.find("conn", "host").is_none());
+        assert!(m.find("conn", " host ").is_some());
+    }
+
+    #[test]
+    fn test_find_trim_matches_spaced_key() {
+        let m = spaced_manifest(NormalizeMode::Trim);
+        assert_eq!(m.find("conn", "host"), m.find("conn", " host "));
+        assert!(m.find("conn", "host").is_some());
+        assert!(m.get_meta("conn", "host").store.is_some());
+        assert!(m.find("conn", "port").is_none());
+    }
+
+    #[test]
+    fn test_find_trim_lowercase_ignores_case() {
+        let m = spaced_manifest(NormalizeMode::TrimLowercase);
+        assert!(m.find("conn", "port").is_some());
+        assert!(m.find("conn", "HOST").is_some());
+    }
+
+    // --- key_path ---
+
+    #[test]
+    fn test_resolve_key_matches_parse_time_qualification() {
+        let m = cache_manifest();
+        // relative: qualified like `_load.key: "${org_id}"` under cache.user.tenant_id
+        assert_eq!(m.resolve_key("cache.user.tenant_id", "${org_id}"), "cache.user.org_id");
+        let load = m.get_meta("cache", "user.tenant_id").load.unwrap();
+        assert!(m.build_config(load).unwrap().iter()
+            .any(|(k, cv)| k == "key" && matches!(cv, ConfigValue::Placeholder(p) if p == "cache.user.org_id")));
+        // absolute and cross-file references are kept as written
+        assert_eq!(m.resolve_key("cache.user.tenant_id", "cache.user.id"), "cache.user.id");
+        assert_eq!(m.resolve_key("cache.user", "${session.sso_user_id}"), "session.sso_user_id");
+        // a top-level key has only the file as ancestor
+        assert_eq!(m.resolve_key("cache.user", "org_id"), "cache.org_id");
+    }
+
+    #[test]
+    fn test_key_path_round_trips_find() {
+        let m = cache_manifest();
+        for path in ["user", "user.id", "user.tenant_id"] {
+            let idx = m.find("cache", path).unwrap();
+            assert_eq!(m.key_path(idx), Some(alloc::format!("cache.{}", path)));
+        }
+        let meta = m.get_meta("cache", "user");
+        assert_eq!(m.key_path(meta.load.unwrap()), None);
+    }
+
+    #[test]
+    fn test_split_key() {
+        assert_eq!(split_key("file"), ("file", ""));
+        assert_eq!(split_key("file.a"), ("file", "a"));
+        assert_eq!(split_key("file.a.b"), ("file", "a.b"));
+        assert_eq!(split_key(""), ("", ""));
+
+        let m = cache_manifest();
+        let idx = m.find("cache", "user.id").unwrap();
+        let qualified = m.key_path(idx).unwrap();
+        let (file, path) = split_key(&qualified);
+        assert_eq!(m.find(file, path), Some(idx));
+    }
+
+    // --- get_type ---
+
+    #[test]
+    fn test_get_type() {
+        let m = cache_manifest();
+        assert_eq!(m.get_type(m.find("cache", "user.id").unwrap()), Some(&b"integer"[..]));
+        assert_eq!(m.get_type(m.find("cache", "user.tenant_id").unwrap()), Some(&b"integer"[..]));
+        assert_eq!(m.get_type(m.find("cache", "user").unwrap()), None);
+    }
+
+    #[test]
+    fn test_get_type_inherits_from_ancestor() {
+        let typed = |t: &str| mapping(vec![("type", scalar(t))]);
+        let m = make("ids", vec![
+            ("user", mapping(vec![
+                ("_state", typed("integer")),
+                ("id", Value::Null),
+                ("name", mapping(vec![("_state", typed("string"))])),
+                ("misc", mapping(vec![("_state", mapping(vec![]))])),
+                ("nested", mapping(vec![("deep", Value::Null)])),
+            ])),
+            ("group", mapping(vec![("label", Value::Null)])),
+        ]);
+        let ty = |path: &str| m.get_type(m.find("ids", path).unwrap());
+        assert_eq!(ty("user"), Some(&b"integer"[..]));
+        assert_eq!(ty("user.id"), Some(&b"integer"[..]));
+        assert_eq!(ty("user.nested.deep"), Some(&b"integer"[..]));
+        // a typeless _state does not drop the inherited type
+        assert_eq!(ty("user.misc"), Some(&b"integer"[..]));
+        // an own type overrides, without leaking to siblings
+        assert_eq!(ty("user.name"), Some(&b"string"[..]));
+        assert_eq!(ty("group.label"), None);
+        assert_eq!(ty("group"), None);
+    }
+
+    // --- static_fields ---
+
+    #[test]
+    fn test_field_children_skips_meta_keys() {
+        let m = cache_manifest();
+        let user = m.find("cache", "user").unwrap();
+        let names: Vec<String> = m.field_children(user).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, alloc::vec!["id".to_string(), "tenant_id".to_string()]);
+        assert_eq!(m.field_children(user)[0].1, m.find("cache", "user.id").unwrap());
+    }
+
+    #[test]
+    fn test_static_fields() {
+        let m = make("app", vec![
+            ("user", mapping(vec![
+                ("_load", mapping(vec![("client", scalar("InMemory")), ("key", scalar("u"))])),
+                ("role", scalar("member")),
+                ("greeting", scalar("hi ${app.user.name}")),
+                ("name", Value::Null),
+                ("nested", mapping(vec![("x", scalar("1"))])),
+            ])),
+        ]);
+        let idx = m.find("app", "user").unwrap();
+        assert_eq!(m.static_fields(idx), alloc::vec![
+            ("role".to_string(), "member".to_string()),
+            ("greeting".to_string(), "hi ${app.user.name}".to_string()),
+        ]);
+        assert!(m.static_fields(m.find("app", "user.role").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_set_static_overrides_until_cleared() {
+        let mut m = make("app", vec![
+            ("user", mapping(vec![
+                ("role", scalar("member")),
+                ("name", Value::Null),
+                ("nested", mapping(vec![("x", scalar("1"))])),
+            ])),
+        ]);
+        let user = m.find("app", "user").unwrap();
+        let role = m.find("app", "user.role").unwrap();
+        let name = m.find("app", "user.name").unwrap();
+        assert!(m.set_static(role, "admin"));
+        assert!(m.set_static(name, "alice"));
+        assert!(!m.set_static(m.find("app", "user.nested").unwrap(), "x"));
+        assert_eq!(m.static_fields(user), alloc::vec![
+            ("role".to_string(), "admin".to_string()),
+            ("name".to_string(), "alice".to_string()),
+        ]);
+        assert!(m.clear_static(role));
+        assert!(m.clear_static(name));
+        assert!(!m.clear_static(name));
+        assert_eq!(m.static_fields(user), alloc::vec![("role".to_string(), "member".to_string())]);
+    }
+
     // --- get_meta ---
 
     #[test]
@@ -509,6 +1417,44 @@ mod tests {
         assert!(meta.state.is_none());
     }
 
+    #[test]
+    fn test_get_meta_is_memoized_until_insert() {
+        let mut m = Manifest::new();
+        assert_eq!(m.get_meta("cache", "user"), MetaIndices::default());
+        assert_eq!(m.meta_cache.lock().unwrap().len(), 1);
+
+        let other = cache_manifest();
+        m.append(&other).unwrap();
+        assert!(m.meta_cache.lock().unwrap().is_empty());
+        let first = m.get_meta("cache", "user");
+        assert!(first.store.is_some());
+        assert_eq!(m.get_meta("cache", "user"), first);
+        assert_eq!(m.meta_cache.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_lint_flags_unusable_meta() {
+        let m = make("app", vec![
+            ("alias", mapping(vec![
+                ("_store", mapping(vec![("client", scalar("KVS")), ("key", scalar("alias"))])),
+                ("_load", mapping(vec![("client", scalar("State")), ("key", scalar("${app.target}"))])),
+            ])),
+            ("orphan", mapping(vec![
+                ("_load", mapping(vec![("key", scalar("x"))])),
+            ])),
+            ("target", mapping(vec![
+                ("_store", mapping(vec![("client", scalar("KVS")), ("key", scalar("target"))])),
+                ("_load", mapping(vec![("client", scalar("Env")), ("map", mapping(vec![("v", scalar("V"))]))])),
+            ])),
+        ]);
+        assert_eq!(m.lint("app"), vec![
+            "app.alias: _store is never read because _load.client is State".to_string(),
+            "app.orphan: _load has no client and never runs".to_string(),
+        ]);
+        assert!(cache_manifest().lint("cache").is_empty());
+        assert!(m.lint("missing").is_empty());
+    }
+
     #[test]
     fn test_get_meta_file_not_loaded_returns_default() {
         let m = Manifest::new();
@@ -573,6 +1519,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_config_order_is_declaration_order() {
+        let m = cache_manifest();
+        let meta = m.get_meta("cache", "user");
+        let keys = |m: &Manifest| -> Vec<String> {
+            m.build_config(meta.load.unwrap()).unwrap().into_iter().map(|(k, _)| k).collect()
+        };
+        let first = keys(&m);
+        assert_eq!(first, alloc::vec!["client", "connection", "table", "map"]);
+        for _ in 0..3 {
+            assert_eq!(keys(&m), first);
+        }
+        if let Some((_, ConfigValue::Map(pairs))) = m.build_config(meta.load.unwrap()).unwrap()
+            .into_iter().find(|(k, _)| k == "map")
+        {
+            assert!(pairs[0].0.ends_with("id") && pairs[1].0.ends_with("org_id"));
+        }
+    }
+
     #[test]
     fn test_build_config_key_with_template_is_str() {
         let m = cache_manifest();
@@ -585,6 +1550,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_config_structured_where_is_params() {
+        let m = make("orgs", vec![
+            ("org", mapping(vec![
+                ("_load", mapping(vec![
+                    ("client", scalar("Db")),
+                    ("table", scalar("orgs")),
+                    ("where", mapping(vec![
+                        ("org_id", scalar("${session.org_id}")),
+                        ("status", scalar("active")),
+                    ])),
+                ])),
+            ])),
+        ]);
+        let meta = m.get_meta("orgs", "org");
+        let entries = m.build_config(meta.load.unwrap()).unwrap();
+        let Some((_, ConfigValue::Params(params))) = entries.iter().find(|(k, _)| k == "where") else {
+            panic!("expected Params");
+        };
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].0, "org_id");
+        assert!(matches!(&params[0].1, ConfigValue::Placeholder(p) if p == "session.org_id"));
+        assert_eq!(params[1].0, "status");
+        assert!(matches!(&params[1].1, ConfigValue::Str(s) if s == "active"));
+    }
+
+    // --- to_bytes / from_bytes / append ---
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let m = cache_manifest();
+        let restored = Manifest::from_bytes(&m.to_bytes()).unwrap();
+        for path in ["user", "user.id", "user.tenant_id", "nonexistent"] {
+            assert_eq!(restored.find("cache", path), m.find("cache", path));
+            let (a, b) = (m.get_meta("cache", path), restored.get_meta("cache", path));
+            assert_eq!((a.load, a.store, a.state), (b.load, b.store, b.state));
+        }
+        let meta = restored.get_meta("cache", "user");
+        let entries = restored.build_config(meta.load.unwrap()).unwrap();
+        let conn = entries.iter().find(|(k, _)| k == "connection");
+        assert!(matches!(conn, Some((_, ConfigValue::Placeholder(p))) if p == "connection.tenant"));
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_garbage() {
+        assert!(Manifest::from_bytes(b"nope").is_err());
+        let bytes = cache_manifest().to_bytes();
+        assert!(Manifest::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_append_remaps_indices() {
+        let mut m = make("connection", vec![
+            ("common", mapping(vec![
+                ("_store", mapping(vec![("client", scalar("InMemory")), ("key", scalar("c"))])),
+            ])),
+        ]);
+        let compiled = Manifest::from_bytes(&cache_manifest().to_bytes()).unwrap();
+        m.append(&compiled).unwrap();
+
+        assert!(m.find("connection", "common").is_some());
+        assert!(m.find("cache", "user.tenant_id").is_some());
+
+        let meta = m.get_meta("cache", "user");
+        assert_eq!(m.get_client(meta.store.unwrap()), super::super::fixed_bits::CLIENT_KVS);
+        let entries = m.build_config(meta.store.unwrap()).unwrap();
+        let key = entries.iter().find(|(k, _)| k == "key");
+        assert!(matches!(key, Some((_, ConfigValue::Str(s))) if s == "user:${session.sso_user_id}"));
+        let entries = m.build_config(meta.load.unwrap()).unwrap();
+        let map = entries.iter().find(|(k, _)| k == "map");
+        assert!(matches!(map, Some((_, ConfigValue::Map(p))) if p[1] == ("cache.org_id".into(), "sso_org_id".into())));
+    }
+
+    #[test]
+    fn test_append_rejects_pool_overflow() {
+        let mut m = make("connection", vec![
+            ("common", mapping(vec![("host", scalar("db"))])),
+        ]);
+        m.keys.resize(fixed_bits::K_MASK_CHILD as usize, 0);
+        let keys_before = m.keys.len();
+        let err = m.append(&cache_manifest()).unwrap_err();
+        assert!(err.contains("keys limit"), "{}", err);
+        assert_eq!(m.keys.len(), keys_before);
+        assert!(!m.is_loaded("cache"));
+    }
+
     // --- decode_value ---
 
     #[test]
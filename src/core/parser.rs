@@ -16,8 +16,33 @@ pub struct ParsedManifest {
     pub file_key_idx: u16,
 }
 
-/// Parses a manifest value tree, appending into caller-owned vecs.
-/// Returns a `ParsedManifest` referencing the file root record's index.
+/// Parser settings shared by every file of a Manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOptions {
+    /// Opening placeholder delimiter. Default `${`. Must not be empty; `parse` fails otherwise.
+    pub placeholder_open: Vec<u8>,
+    /// Closing placeholder delimiter. Default `}`. Must not be empty; `parse` fails otherwise.
+    pub placeholder_close: Vec<u8>,
+    /// Reject `_`-prefixed keys other than `_load`/`_store`/`_state`. Default `false`
+    /// (unknown meta keys are ignored).
+    pub strict_meta: bool,
+    /// Deepest mapping/sequence nesting accepted, counting the file root as 1. Deeper
+    /// trees fail with `nesting too deep` instead of recursing further. Default `128`.
+    pub max_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            placeholder_open: b"${".to_vec(),
+            placeholder_close: b"}".to_vec(),
+            strict_meta: false,
+            max_depth: 128,
+        }
+    }
+}
+
+/// Caller-owned vecs a parse appends into.
 ///
 /// - `keys`: Vec<u64> — fixed-bits key records
 /// - `values`: Vec<[u64; 2]> — fixed-bits value records
@@ -25,47 +50,71 @@ pub struct ParsedManifest {
 /// - `children_map`: Vec<Vec<u16>> — multi-child index lists
 ///
 /// Index 0 of each vec is reserved as null by the caller.
-pub fn parse(
-    filename: &str,
-    root: Value,
-    dynamic: &mut DynamicPool,
-    keys: &mut Vec<u64>,
-    values: &mut Vec<[u64; 2]>,
-    path_map: &mut Vec<Vec<u16>>,
-    children_map: &mut Vec<Vec<u16>>,
-) -> Result<ParsedManifest, String> {
+pub struct Pools<'a> {
+    pub dynamic: &'a mut DynamicPool,
+    pub keys: &'a mut Vec<u64>,
+    pub values: &'a mut Vec<[u64; 2]>,
+    pub path_map: &'a mut Vec<Vec<u16>>,
+    pub children_map: &'a mut Vec<Vec<u16>>,
+}
+
+/// The file being parsed, its options and the pools, passed down the traversal.
+struct ParseCtx<'a> {
+    filename: &'a str,
+    opts: &'a ParseOptions,
+    dynamic: &'a mut DynamicPool,
+    keys: &'a mut Vec<u64>,
+    values: &'a mut Vec<[u64; 2]>,
+    path_map: &'a mut Vec<Vec<u16>>,
+    children_map: &'a mut Vec<Vec<u16>>,
+}
+
+/// Parses a manifest value tree, appending into `pools`.
+/// Returns a `ParsedManifest` referencing the file root record's index.
+pub fn parse(filename: &str, root: Value, pools: Pools<'_>, opts: &ParseOptions) -> Result<ParsedManifest, String> {
     let Value::Mapping(mapping) = root else {
         return Err("DSL root must be a mapping".to_string());
     };
+    if opts.placeholder_open.is_empty() || opts.placeholder_close.is_empty() {
+        return Err("placeholder delimiters must not be empty".to_string());
+    }
 
+    check_depth(&mapping, opts.max_depth)?;
+
+    let Pools { dynamic, keys, values, path_map, children_map } = pools;
+    let ctx = &mut ParseCtx { filename, opts, dynamic, keys, values, path_map, children_map };
+
+    // reserve for the whole file up front so the pools grow once per file
+    let nodes = count_nodes(&mapping);
+    ctx.dynamic.reserve(nodes);
+    ctx.keys.reserve(nodes + 1);
+    ctx.values.reserve(nodes);
 
     // filename root record (placeholder, child index filled below)
-    let dyn_idx = dynamic.intern(filename.as_bytes());
+    let dyn_idx = ctx.dynamic.try_intern(filename.as_bytes())?;
     let mut file_record = fixed_bits::new();
     file_record = fixed_bits::set(file_record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC, dyn_idx as u64);
-    let file_idx = keys.len() as u16;
-    keys.push(file_record);
+    let file_idx = push_index(ctx.keys, file_record, "keys")?;
 
     // traverse top-level keys
     let mut child_indices: Vec<u16> = Vec::new();
     for (key_bytes, value) in &mapping {
-        let child_idx = traverse_field_key(key_bytes, value, filename, &[], dynamic, keys, values, path_map, children_map)?;
+        let child_idx = traverse_field_key(key_bytes, value, &[], ctx)?;
         child_indices.push(child_idx);
     }
 
     // update file record with children
-    let file_record = keys[file_idx as usize];
+    let file_record = ctx.keys[file_idx as usize];
     let file_record = match child_indices.len() {
         0 => file_record,
         1 => fixed_bits::set(file_record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, child_indices[0] as u64),
         _ => {
-            let children_idx = children_map.len() as u16;
-            children_map.push(child_indices);
+            let children_idx = push_index(ctx.children_map, child_indices, "children_map")?;
             let r = fixed_bits::set(file_record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN, 1);
             fixed_bits::set(r, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, children_idx as u64)
         }
     };
-    keys[file_idx as usize] = file_record;
+    ctx.keys[file_idx as usize] = file_record;
 
     Ok(ParsedManifest { file_key_idx: file_idx })
 }
@@ -75,21 +124,15 @@ pub fn parse(
 fn traverse_field_key(
     key_bytes: &[u8],
     value: &Value,
-    filename: &str,
     ancestors: &[&[u8]],
-    dynamic: &mut DynamicPool,
-    keys: &mut Vec<u64>,
-    values: &mut Vec<[u64; 2]>,
-    path_map: &mut Vec<Vec<u16>>,
-    children_map: &mut Vec<Vec<u16>>,
+    ctx: &mut ParseCtx,
 ) -> Result<u16, String> {
-    let dyn_idx = dynamic.intern(key_bytes);
+    let dyn_idx = ctx.dynamic.try_intern(key_bytes)?;
     let mut record = fixed_bits::new();
     record = fixed_bits::set(record, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT, fixed_bits::ROOT_NULL);
     record = fixed_bits::set(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC, dyn_idx as u64);
 
-    let key_idx = keys.len() as u16;
-    keys.push(record);
+    let key_idx = push_index(ctx.keys, record, "keys")?;
 
     let mut current: Vec<&[u8]> = ancestors.to_vec();
     current.push(key_bytes);
@@ -100,10 +143,27 @@ fn traverse_field_key(
 
         for (k_bytes, v) in mapping {
             if k_bytes.first() == Some(&b'_') {
-                let meta_idx = traverse_meta_key(k_bytes, v, filename, ancestors, dynamic, keys, values, path_map, children_map)?;
+                if ctx.opts.strict_meta && codec::root_encode(k_bytes) == fixed_bits::ROOT_NULL {
+                    let location = build_qualified_path(ctx.filename, &current, k_bytes);
+                    return Err(format!(
+                        "unknown meta key '{}' at {}",
+                        String::from_utf8_lossy(k_bytes), String::from_utf8_lossy(&location),
+                    ));
+                }
+                if codec::root_encode(k_bytes) == fixed_bits::ROOT_STATE
+                    && let Value::Mapping(props) = v
+                    && props.iter().any(|(k, _)| k == b"ttl")
+                {
+                    let location = build_qualified_path(ctx.filename, &current, k_bytes);
+                    return Err(format!(
+                        "'ttl' is not a _state prop at {} (use 'cache_ttl')",
+                        String::from_utf8_lossy(&location),
+                    ));
+                }
+                let meta_idx = traverse_meta_key(k_bytes, v, ancestors, ctx)?;
                 meta_indices.push(meta_idx);
             } else {
-                let child_idx = traverse_field_key(k_bytes, v, filename, &current, dynamic, keys, values, path_map, children_map)?;
+                let child_idx = traverse_field_key(k_bytes, v, &current, ctx)?;
                 child_indices.push(child_idx);
             }
         }
@@ -113,25 +173,24 @@ fn traverse_field_key(
             .copied()
             .collect();
 
-        let record = keys[key_idx as usize];
+        let record = ctx.keys[key_idx as usize];
         let record = match all_children.len() {
             0 => record,
             1 => fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, all_children[0] as u64),
             _ => {
-                let children_idx = children_map.len() as u16;
-                children_map.push(all_children);
+                let children_idx = push_index(ctx.children_map, all_children, "children_map")?;
                 let r = fixed_bits::set(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN, 1);
                 fixed_bits::set(r, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, children_idx as u64)
             }
         };
-        keys[key_idx as usize] = record;
+        ctx.keys[key_idx as usize] = record;
     } else {
         // scalar value → is_leaf
-        let val_idx = build_yaml_value(value, filename, ancestors, dynamic, values, path_map)?;
-        let record = keys[key_idx as usize];
+        let val_idx = build_yaml_value(value, ancestors, ctx)?;
+        let record = ctx.keys[key_idx as usize];
         let record = fixed_bits::set(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF, 1);
         let record = fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, val_idx as u64);
-        keys[key_idx as usize] = record;
+        ctx.keys[key_idx as usize] = record;
     }
 
     Ok(key_idx)
@@ -141,42 +200,37 @@ fn traverse_field_key(
 fn traverse_meta_key(
     key_bytes: &[u8],
     value: &Value,
-    filename: &str,
     ancestors: &[&[u8]],
-    dynamic: &mut DynamicPool,
-    keys: &mut Vec<u64>,
-    values: &mut Vec<[u64; 2]>,
-    path_map: &mut Vec<Vec<u16>>,
-    children_map: &mut Vec<Vec<u16>>,
+    ctx: &mut ParseCtx,
 ) -> Result<u16, String> {
     let root_val = codec::root_encode(key_bytes);
 
     let mut record = fixed_bits::new();
     record = fixed_bits::set(record, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT, root_val);
 
-    let key_idx = keys.len() as u16;
-    keys.push(record);
+    let key_idx = push_index(ctx.keys, record, "keys")?;
 
     if let Value::Mapping(mapping) = value {
         let mut child_indices: Vec<u16> = Vec::new();
 
         for (k_bytes, v) in mapping {
-            let child_idx = traverse_prop_key(k_bytes, v, filename, ancestors, dynamic, keys, values, path_map, children_map)?;
+            // `_state.cache_ttl` reuses the ttl prop code, which `_state` has no other use for
+            let k_bytes: &[u8] = if root_val == fixed_bits::ROOT_STATE && k_bytes == b"cache_ttl" { b"ttl" } else { k_bytes };
+            let child_idx = traverse_prop_key(k_bytes, v, ancestors, ctx)?;
             child_indices.push(child_idx);
         }
 
-        let record = keys[key_idx as usize];
+        let record = ctx.keys[key_idx as usize];
         let record = match child_indices.len() {
             0 => record,
             1 => fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, child_indices[0] as u64),
             _ => {
-                let children_idx = children_map.len() as u16;
-                children_map.push(child_indices);
+                let children_idx = push_index(ctx.children_map, child_indices, "children_map")?;
                 let r = fixed_bits::set(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN, 1);
                 fixed_bits::set(r, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, children_idx as u64)
             }
         };
-        keys[key_idx as usize] = record;
+        ctx.keys[key_idx as usize] = record;
     }
 
     Ok(key_idx)
@@ -186,13 +240,8 @@ fn traverse_meta_key(
 fn traverse_prop_key(
     key_bytes: &[u8],
     value: &Value,
-    filename: &str,
     ancestors: &[&[u8]],
-    dynamic: &mut DynamicPool,
-    keys: &mut Vec<u64>,
-    values: &mut Vec<[u64; 2]>,
-    path_map: &mut Vec<Vec<u16>>,
-    children_map: &mut Vec<Vec<u16>>,
+    ctx: &mut ParseCtx,
 ) -> Result<u16, String> {
     let (prop_val, client_val) = if key_bytes == b"client" {
         (fixed_bits::PROP_NULL, codec::client_encode(
@@ -213,35 +262,75 @@ fn traverse_prop_key(
         record = fixed_bits::set(record, fixed_bits::K_OFFSET_TYPE, fixed_bits::K_MASK_TYPE, type_val);
     }
 
-    let key_idx = keys.len() as u16;
-    keys.push(record);
+    let key_idx = push_index(ctx.keys, record, "keys")?;
 
-    if key_bytes == b"map" {
+    if let (b"client", fixed_bits::CLIENT_NULL, Value::Scalar(name)) = (key_bytes, client_val, value)
+        && !name.is_empty() {
+        // not a built-in client: keep the name for a registered StoreBackend
+        let val_idx = build_yaml_value(value, ancestors, ctx)?;
+        let record = fixed_bits::set(record, fixed_bits::K_OFFSET_CLIENT, fixed_bits::K_MASK_CLIENT, fixed_bits::CLIENT_CUSTOM);
+        let record = fixed_bits::set(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF, 1);
+        ctx.keys[key_idx as usize] = fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, val_idx as u64);
+    } else if key_bytes == b"map" {
         if let Value::Mapping(mapping) = value {
             let mut child_indices: Vec<u16> = Vec::new();
             for (k_bytes, v) in mapping {
-                let child_idx = traverse_map_key(k_bytes, v, filename, ancestors, dynamic, keys, values, path_map)?;
+                let child_idx = traverse_map_key(k_bytes, v, ancestors, ctx)?;
                 child_indices.push(child_idx);
             }
-            let record = keys[key_idx as usize];
+            let record = ctx.keys[key_idx as usize];
             let record = match child_indices.len() {
                 0 => record,
                 1 => fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, child_indices[0] as u64),
                 _ => {
-                    let children_idx = children_map.len() as u16;
-                    children_map.push(child_indices);
+                    let children_idx = push_index(ctx.children_map, child_indices, "children_map")?;
                     let r = fixed_bits::set(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN, 1);
                     fixed_bits::set(r, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, children_idx as u64)
                 }
             };
-            keys[key_idx as usize] = record;
+            ctx.keys[key_idx as usize] = record;
+        }
+    } else if let (b"where" | b"body", Value::Mapping(mapping)) = (key_bytes, value) {
+        let mut child_indices: Vec<u16> = Vec::new();
+        for (k_bytes, v) in mapping {
+            let child_idx = traverse_param_key(k_bytes, v, ancestors, ctx)?;
+            child_indices.push(child_idx);
+        }
+        let record = ctx.keys[key_idx as usize];
+        let record = match child_indices.len() {
+            0 => record,
+            1 => fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, child_indices[0] as u64),
+            _ => {
+                let children_idx = push_index(ctx.children_map, child_indices, "children_map")?;
+                let r = fixed_bits::set(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN, 1);
+                fixed_bits::set(r, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, children_idx as u64)
+            }
+        };
+        ctx.keys[key_idx as usize] = record;
+    } else if let (b"fallback", Value::Mapping(mapping)) = (key_bytes, value) {
+        // a nested _load block, tried when this one fails
+        let mut child_indices: Vec<u16> = Vec::new();
+        for (k_bytes, v) in mapping {
+            let child_idx = traverse_prop_key(k_bytes, v, ancestors, ctx)?;
+            child_indices.push(child_idx);
         }
+        let record = ctx.keys[key_idx as usize];
+        let record = match child_indices.len() {
+            0 => record,
+            1 => fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, child_indices[0] as u64),
+            _ => {
+                let children_idx = push_index(ctx.children_map, child_indices, "children_map")?;
+                let r = fixed_bits::set(record, fixed_bits::K_OFFSET_HAS_CHILDREN, fixed_bits::K_MASK_HAS_CHILDREN, 1);
+                fixed_bits::set(r, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, children_idx as u64)
+            }
+        };
+        ctx.keys[key_idx as usize] = record;
     } else if key_bytes != b"client" {
-        let val_idx = build_yaml_value(value, filename, ancestors, dynamic, values, path_map)?;
-        let record = keys[key_idx as usize];
+        let val_idx = build_yaml_value(value, ancestors, ctx)?;
+        let record = ctx.keys[key_idx as usize];
         let record = fixed_bits::set(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF, 1);
         let record = fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, val_idx as u64);
-        keys[key_idx as usize] = record;
+        ctx.keys[key_idx as usize] = record;
     }
 
     Ok(key_idx)
@@ -251,41 +340,52 @@ fn traverse_prop_key(
 fn traverse_map_key(
     key_bytes: &[u8],
     value: &Value,
-    filename: &str,
     ancestors: &[&[u8]],
-    dynamic: &mut DynamicPool,
-    keys: &mut Vec<u64>,
-    values: &mut Vec<[u64; 2]>,
-    path_map: &mut Vec<Vec<u16>>,
+    ctx: &mut ParseCtx,
 ) -> Result<u16, String> {
-    let qualified = build_qualified_path(filename, ancestors, key_bytes);
+    let qualified = build_qualified_path(ctx.filename, ancestors, key_bytes);
     let seg_indices: Vec<u16> = qualified.split(|&b| b == b'.')
-        .map(|seg| dynamic.intern(seg))
-        .collect();
-    let path_idx = path_map.len() as u16;
-    path_map.push(seg_indices);
+        .map(|seg| ctx.dynamic.try_intern(seg))
+        .collect::<Result<_, _>>()?;
+    let path_idx = push_index(ctx.path_map, seg_indices, "path_map")?;
 
     let mut record = fixed_bits::new();
     record = fixed_bits::set(record, fixed_bits::K_OFFSET_IS_PATH, fixed_bits::K_MASK_IS_PATH, 1);
     record = fixed_bits::set(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC, path_idx as u64);
 
-    let val_idx = build_yaml_value(value, filename, ancestors, dynamic, values, path_map)?;
+    let val_idx = build_yaml_value(value, ancestors, ctx)?;
     record = fixed_bits::set(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF, 1);
     record = fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, val_idx as u64);
 
-    let key_idx = keys.len() as u16;
-    keys.push(record);
+    let key_idx = push_index(ctx.keys, record, "keys")?;
+    Ok(key_idx)
+}
+
+/// Traverses a structured `where`/`body` param key (name kept unqualified, value may be a template).
+fn traverse_param_key(
+    key_bytes: &[u8],
+    value: &Value,
+    ancestors: &[&[u8]],
+    ctx: &mut ParseCtx,
+) -> Result<u16, String> {
+    let dyn_idx = ctx.dynamic.try_intern(key_bytes)?;
+
+    let mut record = fixed_bits::new();
+    record = fixed_bits::set(record, fixed_bits::K_OFFSET_DYNAMIC, fixed_bits::K_MASK_DYNAMIC, dyn_idx as u64);
+
+    let val_idx = build_yaml_value(value, ancestors, ctx)?;
+    record = fixed_bits::set(record, fixed_bits::K_OFFSET_IS_LEAF, fixed_bits::K_MASK_IS_LEAF, 1);
+    record = fixed_bits::set(record, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD, val_idx as u64);
+
+    let key_idx = push_index(ctx.keys, record, "keys")?;
     Ok(key_idx)
 }
 
 /// Builds a YAML value record ([u64; 2]) from a scalar or template string.
 fn build_yaml_value(
     value: &Value,
-    filename: &str,
     ancestors: &[&[u8]],
-    dynamic: &mut DynamicPool,
-    values: &mut Vec<[u64; 2]>,
-    path_map: &mut Vec<Vec<u16>>,
+    ctx: &mut ParseCtx,
 ) -> Result<u16, String> {
     let s = match value {
         Value::Scalar(s)   => s.clone(),
@@ -294,7 +394,7 @@ fn build_yaml_value(
         Value::Sequence(_) => return Err("unexpected sequence as scalar value".to_string()),
     };
 
-    let tokens = split_template(&s);
+    let tokens = split_template(&s, &ctx.opts.placeholder_open, &ctx.opts.placeholder_close);
     if tokens.len() > 6 {
         return Err(format!("value has {} tokens, max 6", tokens.len()));
     }
@@ -317,15 +417,13 @@ fn build_yaml_value(
 
     for (i, token) in tokens.iter().enumerate().take(6) {
         let dyn_idx = if token.is_path {
-            let qualified = qualify_path(&token.text, filename, ancestors);
+            let qualified = qualify_path(&token.text, ctx.filename, ancestors);
             let seg_indices: Vec<u16> = qualified.split(|&b| b == b'.')
-                .map(|seg| dynamic.intern(seg))
-                .collect();
-            let path_idx = path_map.len() as u16;
-            path_map.push(seg_indices);
-            path_idx
+                .map(|seg| ctx.dynamic.try_intern(seg))
+                .collect::<Result<_, _>>()?;
+            push_index(ctx.path_map, seg_indices, "path_map")?
         } else {
-            dynamic.intern(&token.text)
+            ctx.dynamic.try_intern(&token.text)?
         };
 
         let word = if i < 3 { 0 } else { 1 };
@@ -334,8 +432,7 @@ fn build_yaml_value(
         vo[word] = fixed_bits::set(vo[word], off_dynamic, fixed_bits::V_MASK_DYNAMIC, dyn_idx as u64);
     }
 
-    let val_idx = values.len() as u16;
-    values.push(vo);
+    let val_idx = push_index(ctx.values, vo, "values")?;
     Ok(val_idx)
 }
 
@@ -346,21 +443,21 @@ struct Token {
     is_path: bool,
 }
 
-/// Splits a byte slice by `${}` placeholders into tokens.
+/// Splits a byte slice by `open`..`close` placeholders (`${}` by default) into tokens.
 /// `b"user:${session.id}"` → [Token(b"user:", false), Token(b"session.id", true)]
-fn split_template(s: &[u8]) -> Vec<Token> {
+fn split_template(s: &[u8], open: &[u8], close: &[u8]) -> Vec<Token> {
     let mut tokens = Vec::new();
     let mut rest = s;
 
     loop {
-        if let Some(start) = find_bytes(rest, b"${") {
+        if let Some(start) = find_bytes(rest, open) {
             if start > 0 {
                 tokens.push(Token { text: rest[..start].to_vec(), is_path: false });
             }
-            rest = &rest[start + 2..];
-            if let Some(end) = rest.iter().position(|&b| b == b'}') {
+            rest = &rest[start + open.len()..];
+            if let Some(end) = find_bytes(rest, close) {
                 tokens.push(Token { text: rest[..end].to_vec(), is_path: true });
-                rest = &rest[end + 1..];
+                rest = &rest[end + close.len()..];
             } else {
                 tokens.push(Token { text: rest.to_vec(), is_path: false });
                 break;
@@ -380,12 +477,53 @@ fn split_template(s: &[u8]) -> Vec<Token> {
     tokens
 }
 
+/// Fails when mappings/sequences below the root `mapping` nest deeper than `max_depth`.
+/// Walks with an explicit stack, so the check itself cannot overflow.
+fn check_depth(mapping: &[(Vec<u8>, Value)], max_depth: usize) -> Result<(), String> {
+    let mut stack: Vec<(&Value, usize)> = mapping.iter().map(|(_, v)| (v, 2)).collect();
+    while let Some((value, depth)) = stack.pop() {
+        let children: Vec<&Value> = match value {
+            Value::Mapping(m) => m.iter().map(|(_, v)| v).collect(),
+            Value::Sequence(items) => items.iter().collect(),
+            _ => continue,
+        };
+        if depth > max_depth {
+            return Err(format!("nesting too deep (max {})", max_depth));
+        }
+        stack.extend(children.into_iter().map(|v| (v, depth + 1)));
+    }
+    Ok(())
+}
+
+/// Counts mapping entries and sequence items below `mapping`: an upper-bound estimate
+/// of the key/value records one file produces.
+fn count_nodes(mapping: &[(Vec<u8>, Value)]) -> usize {
+    fn count(value: &Value) -> usize {
+        match value {
+            Value::Mapping(m) => count_nodes(m),
+            Value::Sequence(items) => items.iter().map(|v| 1 + count(v)).sum(),
+            _ => 0,
+        }
+    }
+    mapping.iter().map(|(_, v)| 1 + count(v)).sum()
+}
+
+/// Pushes `item` and returns its index, failing when the index would not fit the
+/// 16-bit index fields of the key/value records.
+fn push_index<T>(pool: &mut Vec<T>, item: T, name: &str) -> Result<u16, String> {
+    if pool.len() > fixed_bits::K_MASK_CHILD as usize {
+        return Err(format!("manifest exceeds {} limit of {} entries", name, fixed_bits::K_MASK_CHILD));
+    }
+    pool.push(item);
+    Ok((pool.len() - 1) as u16)
+}
+
 fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
     haystack.windows(needle.len()).position(|w| w == needle)
 }
 
 /// Qualifies a placeholder path to an absolute path.
-fn qualify_path(path: &[u8], filename: &str, ancestors: &[&[u8]]) -> Vec<u8> {
+pub(crate) fn qualify_path(path: &[u8], filename: &str, ancestors: &[&[u8]]) -> Vec<u8> {
     if path.contains(&b'.') {
         return path.to_vec();
     }
@@ -430,9 +568,21 @@ mod tests {
 
     // --- split_template ---
 
+    fn split_template_default(s: &[u8]) -> Vec<Token> {
+        split_template(s, b"${", b"}")
+    }
+
+    #[test]
+    fn test_split_template_custom_delimiters() {
+        let tokens = split_template(b"${HOME}/<<session.id>>", b"<<", b">>");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!((tokens[0].text.as_slice(), tokens[0].is_path), (&b"${HOME}/"[..], false));
+        assert_eq!((tokens[1].text.as_slice(), tokens[1].is_path), (&b"session.id"[..], true));
+    }
+
     #[test]
     fn test_split_template_static() {
-        let tokens = split_template(b"literal");
+        let tokens = split_template_default(b"literal");
         assert_eq!(tokens.len(), 1);
         assert!(!tokens[0].is_path);
         assert_eq!(tokens[0].text, b"literal");
@@ -440,7 +590,7 @@ mod tests {
 
     #[test]
     fn test_split_template_path_only() {
-        let tokens = split_template(b"${connection.tenant}");
+        let tokens = split_template_default(b"${connection.tenant}");
         assert_eq!(tokens.len(), 1);
         assert!(tokens[0].is_path);
         assert_eq!(tokens[0].text, b"connection.tenant");
@@ -448,7 +598,7 @@ mod tests {
 
     #[test]
     fn test_split_template_mixed() {
-        let tokens = split_template(b"user:${session.id}");
+        let tokens = split_template_default(b"user:${session.id}");
         assert_eq!(tokens.len(), 2);
         assert!(!tokens[0].is_path);
         assert_eq!(tokens[0].text, b"user:");
@@ -479,7 +629,7 @@ mod tests {
     fn test_field_key_root_is_null() {
         let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
         let root = m(vec![("foo", m(vec![]))]);
-        let pm = parse("f", root, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
+        let pm = parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
 
         let file_rec = keys[pm.file_key_idx as usize];
         let child_idx = fixed_bits::get(file_rec, fixed_bits::K_OFFSET_CHILD, fixed_bits::K_MASK_CHILD) as usize;
@@ -496,7 +646,7 @@ mod tests {
             ("_load",  m(vec![("client", s("InMemory")), ("key", s("k"))])),
             ("_store", m(vec![("client", s("InMemory")), ("key", s("k"))])),
         ]))]);
-        parse("f", root, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
+        parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
 
         let roots: Vec<u64> = keys.iter().map(|&r| fixed_bits::get(r, fixed_bits::K_OFFSET_ROOT, fixed_bits::K_MASK_ROOT)).collect();
         assert!(roots.contains(&fixed_bits::ROOT_STATE));
@@ -512,7 +662,7 @@ mod tests {
         let root = m(vec![("foo", m(vec![
             ("_state", m(vec![("type", s("integer"))])),
         ]))]);
-        parse("f", root, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
+        parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
 
         let types: Vec<u64> = keys.iter().map(|&r| fixed_bits::get(r, fixed_bits::K_OFFSET_TYPE, fixed_bits::K_MASK_TYPE)).collect();
         assert!(types.contains(&fixed_bits::TYPE_I64));
@@ -526,7 +676,7 @@ mod tests {
         let root = m(vec![("foo", m(vec![
             ("_store", m(vec![("client", s("KVS")), ("key", s("k")), ("ttl", s("3600"))])),
         ]))]);
-        parse("f", root, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
+        parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
 
         let clients: Vec<u64> = keys.iter().map(|&r| fixed_bits::get(r, fixed_bits::K_OFFSET_CLIENT, fixed_bits::K_MASK_CLIENT)).collect();
         assert!(clients.contains(&fixed_bits::CLIENT_KVS));
@@ -540,7 +690,7 @@ mod tests {
         let root = m(vec![("foo", m(vec![
             ("_store", m(vec![("client", s("KVS")), ("key", s("foo:${session.id}"))])),
         ]))]);
-        parse("f", root, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
+        parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
 
         let has_template = values.iter().any(|&vo| fixed_bits::get(vo[0], fixed_bits::V_OFFSET_IS_TEMPLATE, fixed_bits::V_MASK_IS_TEMPLATE) == 1);
         assert!(has_template);
@@ -558,7 +708,7 @@ mod tests {
                 ("map", m(vec![("host", s("DB_HOST")), ("port", s("DB_PORT"))])),
             ])),
         ]))]);
-        parse("f", root, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
+        parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
 
         // map keys produce is_path=1 records
         let has_path = keys.iter().any(|&r| fixed_bits::get(r, fixed_bits::K_OFFSET_IS_PATH, fixed_bits::K_MASK_IS_PATH) == 1);
@@ -572,8 +722,8 @@ mod tests {
         let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
         let a = m(vec![("x", m(vec![]))]);
         let b = m(vec![("y", m(vec![]))]);
-        let pm_a = parse("a", a, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
-        let pm_b = parse("b", b, &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).unwrap();
+        let pm_a = parse("a", a, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
+        let pm_b = parse("b", b, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).unwrap();
 
         assert_ne!(pm_a.file_key_idx, pm_b.file_key_idx);
 
@@ -588,6 +738,70 @@ mod tests {
     #[test]
     fn test_root_must_be_mapping() {
         let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
-        assert!(parse("f", s("bad"), &mut dynamic, &mut keys, &mut values, &mut path_map, &mut children_map).is_err());
+        assert!(parse("f", s("bad"), Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_keys_overflow_is_error() {
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        let root = Value::Mapping(vec![(b"k".to_vec(), Value::Null); fixed_bits::K_MASK_CHILD as usize + 1]);
+        let err = parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).err().unwrap();
+        assert_eq!(err, "manifest exceeds keys limit of 65535 entries");
+    }
+
+    #[test]
+    fn test_strict_meta_rejects_unknown_meta_key() {
+        let root = m(vec![("user", m(vec![("_stoer", m(vec![("client", s("KVS"))]))]))]);
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        assert!(parse("cache", root.clone(), Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).is_ok());
+
+        let strict = ParseOptions { strict_meta: true, ..ParseOptions::default() };
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        let err = parse("cache", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &strict).err().unwrap();
+        assert_eq!(err, "unknown meta key '_stoer' at cache.user._stoer");
+    }
+
+    #[test]
+    fn test_state_ttl_is_rejected() {
+        let root = m(vec![("user", m(vec![("_state", m(vec![("ttl", s("60"))]))]))]);
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        let err = parse("cache", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).err().unwrap();
+        assert_eq!(err, "'ttl' is not a _state prop at cache.user._state (use 'cache_ttl')");
+
+        let root = m(vec![("user", m(vec![("_state", m(vec![("cache_ttl", s("60"))]))]))]);
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        assert!(parse("cache", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_rejects_deep_nesting() {
+        let nested = |depth: usize| {
+            let mut v = Value::Null;
+            for _ in 0..depth {
+                v = m(vec![("k", v)]);
+            }
+            v
+        };
+        let opts = ParseOptions { max_depth: 8, ..ParseOptions::default() };
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        assert!(parse("f", nested(8), Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &opts).is_ok());
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        let err = parse("f", nested(9), Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &opts).err().unwrap();
+        assert_eq!(err, "nesting too deep (max 8)");
+
+        let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+        let err = parse("f", nested(5000), Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &ParseOptions::default()).err().unwrap();
+        assert_eq!(err, "nesting too deep (max 128)");
+    }
+
+    #[test]
+    fn test_parse_rejects_empty_placeholder_delimiters() {
+        for (open, close) in [("", "}"), ("${", "")] {
+            let opts = ParseOptions { placeholder_open: open.into(), placeholder_close: close.into(), ..ParseOptions::default() };
+            let (mut dynamic, mut keys, mut values, mut path_map, mut children_map) = make_vecs();
+            let root = m(vec![("k", s("v"))]);
+            let err = parse("f", root, Pools { dynamic: &mut dynamic, keys: &mut keys, values: &mut values, path_map: &mut path_map, children_map: &mut children_map }, &opts).err().unwrap();
+            assert_eq!(err, "placeholder delimiters must not be empty");
+        }
     }
 }
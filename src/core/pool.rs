@@ -14,6 +14,28 @@ impl DynamicPool {
         Self { slots }
     }
 
+    /// Like `new`, with room for `n` entries besides the null slot.
+    pub fn with_capacity(n: usize) -> Self {
+        let mut slots = Vec::with_capacity(n + 1);
+        slots.push(Vec::new()); // index 0 = null
+        Self { slots }
+    }
+
+    /// Reserves room for at least `n` more entries.
+    pub fn reserve(&mut self, n: usize) {
+        self.slots.reserve(n);
+    }
+
+    /// Releases capacity beyond the current entries.
+    pub fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit();
+    }
+
+    /// Number of slots the pool holds without reallocating, including the null slot.
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
     pub fn intern(&mut self, s: &[u8]) -> u16 {
         if let Some(idx) = self.slots.iter().position(|x| x == s) {
             return idx as u16;
@@ -23,9 +45,27 @@ impl DynamicPool {
         idx
     }
 
+    /// Like `intern`, but fails instead of wrapping when a new slot would not fit in a u16 index.
+    pub fn try_intern(&mut self, s: &[u8]) -> Result<u16, alloc::string::String> {
+        if let Some(idx) = self.slots.iter().position(|x| x == s) {
+            return Ok(idx as u16);
+        }
+        if self.slots.len() > u16::MAX as usize {
+            return Err(alloc::format!("dynamic pool exceeds limit of {} entries", u16::MAX));
+        }
+        let idx = self.slots.len() as u16;
+        self.slots.push(s.to_vec());
+        Ok(idx)
+    }
+
     pub fn get(&self, index: u16) -> Option<&[u8]> {
         self.slots.get(index as usize).map(|s| s.as_slice())
     }
+
+    /// Number of slots including the null slot at index 0.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
 }
 
 impl Default for DynamicPool {
@@ -56,4 +96,33 @@ mod tests {
         assert_eq!(pool.get(0), Some(b"".as_slice()));  // null slot
         assert_eq!(pool.get(999), None);
     }
+
+    #[test]
+    fn test_try_intern_full() {
+        let mut pool = DynamicPool::new();
+        pool.slots.resize(u16::MAX as usize + 1, Vec::new());
+        assert_eq!(pool.try_intern(b""), Ok(0));
+        assert!(pool.try_intern(b"new").is_err());
+    }
+
+    #[test]
+    fn test_with_capacity_no_realloc() {
+        let mut pool = DynamicPool::with_capacity(8);
+        let cap = pool.capacity();
+        assert!(cap >= 9);
+        for i in 0..8u8 {
+            pool.intern(&[b'a' + i]);
+        }
+        assert_eq!(pool.capacity(), cap);
+        pool.shrink_to_fit();
+        assert_eq!(pool.capacity(), pool.len());
+
+        pool.reserve(4);
+        let cap = pool.capacity();
+        assert!(cap >= pool.len() + 4);
+        for i in 0..4u8 {
+            pool.intern(&[b'A' + i]);
+        }
+        assert_eq!(pool.capacity(), cap);
+    }
 }
@@ -4,16 +4,22 @@ pub mod ports;
 pub mod load;
 pub mod store;
 pub mod state;
+pub mod oplog;
 
 pub use log_format::LogFormat;
 pub use ports::provided::State as StateTrait;
-pub use ports::default::DefaultFileClient;
-pub use state::State;
+pub use ports::default::{DefaultFileClient, DefaultTransformClient};
+pub use state::{State, StateMetrics, DryRunWrite, EnvInterpolation};
+pub use oplog::Op;
+pub use store::KvsCodec;
+pub use core::manifest::{NormalizeMode, split_key};
 
 pub use ports::required::{
     DbClient, EnvClient,
     KVSClient, InMemoryClient,
-    HttpClient, FileClient,
+    HttpClient, FileClient, StoreBackend,
+    AsyncDbClient, AsyncHttpClient, BoxFuture,
+    TransformClient,
 };
 
 pub use ports::provided::{ManifestError, StateError, LoadError, StoreError, Value};
@@ -1,11 +1,33 @@
 use crate::ports::required::{
     DbClient, EnvClient, KVSClient,
     InMemoryClient, HttpClient, FileClient,
+    AsyncDbClient, AsyncHttpClient, TransformClient,
 };
+use crate::ports::default::DefaultTransformClient;
 use crate::ports::provided::{LoadError, Value};
 use crate::core::fixed_bits;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bound on files read by a single `_load.glob`.
+const MAX_GLOB_FILES: usize = 256;
+
+/// A named post-load function registered with `Load::register_transform`; it takes
+/// precedence over the `TransformClient` for its name.
+pub type Transform = Arc<dyn Fn(Value) -> Value + Send + Sync>;
+
+/// Receives non-fatal load diagnostics (e.g. a mapped DB column missing from the result).
+pub type WarningHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Resolved URL and headers of a cached HTTP GET.
+type ApiCacheKey = (String, Option<Vec<(Vec<u8>, Vec<u8>)>>);
+
+/// HTTP GET responses kept by `Load::with_api_cache`, keyed by URL and headers.
+struct ApiCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<ApiCacheKey, (Instant, Value)>>,
+}
 
 pub struct Load {
     db: Option<Arc<dyn DbClient>>,
@@ -14,6 +36,14 @@ pub struct Load {
     env: Option<Arc<dyn EnvClient>>,
     http: Option<Arc<dyn HttpClient>>,
     file: Option<Arc<dyn FileClient>>,
+    async_db: Option<Arc<dyn AsyncDbClient>>,
+    async_http: Option<Arc<dyn AsyncHttpClient>>,
+    on_warning: Option<WarningHandler>,
+    transform_client: Arc<dyn TransformClient>,
+    transforms: HashMap<String, Transform>,
+    timeout: Option<Duration>,
+    env_prefix: Option<String>,
+    api_cache: Option<ApiCache>,
 }
 
 impl Load {
@@ -25,6 +55,14 @@ impl Load {
             env: None,
             http: None,
             file: None,
+            async_db: None,
+            async_http: None,
+            on_warning: None,
+            transform_client: Arc::new(DefaultTransformClient),
+            transforms: HashMap::new(),
+            timeout: None,
+            env_prefix: None,
+            api_cache: None,
         }
     }
 
@@ -48,32 +86,128 @@ impl Load {
         self
     }
 
+    /// For `client: Env`, looks each `map` source up as `prefix` + name, uppercased
+    /// (`DB_host` → `APP_DB_HOST` with prefix `"app_"`). Target keys are unchanged.
+    pub fn with_env_prefix(mut self, prefix: &str) -> Self {
+        self.env_prefix = Some(prefix.to_string());
+        self
+    }
+
     pub fn with_http(mut self, client: Arc<dyn HttpClient>) -> Self {
         self.http = Some(client);
         self
     }
 
+    /// Keeps `client: HTTP` GET responses for `ttl`, keyed by the resolved URL and
+    /// headers; a hit skips the HTTP client. Requests with a `body` or a non-GET
+    /// `method` are never cached. Expired responses are dropped on the next insert.
+    /// Independent of `_store`.
+    pub fn with_api_cache(mut self, ttl: Duration) -> Self {
+        self.api_cache = Some(ApiCache { ttl, entries: Mutex::new(HashMap::new()) });
+        self
+    }
+
+    /// Drops every response kept by `with_api_cache`.
+    pub fn clear_api_cache(&self) {
+        if let Some(cache) = &self.api_cache {
+            cache.entries.lock().unwrap().clear();
+        }
+    }
+
     pub fn with_file(mut self, client: Arc<dyn FileClient>) -> Self {
         self.file = Some(client);
         self
     }
 
+    /// Used by `handle_async` for `client: Db`; `handle` keeps using `with_db`.
+    pub fn with_async_db(mut self, client: Arc<dyn AsyncDbClient>) -> Self {
+        self.async_db = Some(client);
+        self
+    }
+
+    /// Used by `handle_async` for `client: HTTP`; `handle` keeps using `with_http`.
+    pub fn with_async_http(mut self, client: Arc<dyn AsyncHttpClient>) -> Self {
+        self.async_http = Some(client);
+        self
+    }
+
+    /// Passes `timeout` to `DbClient::get_timeout`/`get_params_timeout` and
+    /// `HttpClient::request_timeout` for `handle`; an adapter's `Err` becomes
+    /// `LoadError::TimedOut`. Not applied by `handle_async`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_warning_handler(mut self, handler: WarningHandler) -> Self {
+        self.on_warning = Some(handler);
+        self
+    }
+
+    /// Applies `_load.transform` through `client` instead of `DefaultTransformClient`.
+    pub fn with_transform(mut self, client: Arc<dyn TransformClient>) -> Self {
+        self.transform_client = client;
+        self
+    }
+
+    /// Registers `f` under `name` for loads declaring `_load.transform: name`.
+    /// It is applied to the loaded value before it is returned, in place of the
+    /// TransformClient (e.g. a built-in `split_csv`) for that name.
+    pub fn register_transform(&mut self, name: &str, f: impl Fn(Value) -> Value + Send + Sync + 'static) {
+        self.transforms.insert(name.to_string(), Arc::new(f));
+    }
+
+    pub(crate) fn warn(&self, message: &str) {
+        #[cfg(feature = "logging")]
+        log::warn!("{}", message);
+        if let Some(handler) = &self.on_warning {
+            handler(message);
+        }
+    }
+
     pub fn handle(&self, config: &HashMap<String, Value>) -> Result<Value, LoadError> {
-        let client = match config.get("client") {
-            Some(Value::Scalar(b)) => {
-                u64::from_le_bytes(b.as_slice().try_into().unwrap_or([0u8; 8]))
-            }
-            _ => return Err(LoadError::ConfigMissing("client".into())),
-        };
+        let client = client_id(config)?;
 
-        match client {
+        let loaded = match client {
             fixed_bits::CLIENT_ENV       => self.load_from_env(config),
             fixed_bits::CLIENT_IN_MEMORY => self.load_from_in_memory(config),
             fixed_bits::CLIENT_KVS       => self.load_from_kvs(config),
             fixed_bits::CLIENT_DB        => self.load_from_db(config),
             fixed_bits::CLIENT_HTTP      => self.load_from_http(config),
             fixed_bits::CLIENT_FILE      => self.load_from_file(config),
+            fixed_bits::CLIENT_CUSTOM    => Err(LoadError::ConfigMissing(format!(
+                "unsupported client '{}'", scalar_str(config, "client_name").unwrap_or_default()
+            ))),
             _ => Err(LoadError::ConfigMissing(format!("unsupported client '{}'", client))),
+        }?;
+
+        self.apply_transform(config, loaded)
+    }
+
+    /// Async variant of `handle`. `Db` and `HTTP` loads await the async client when
+    /// one is configured and otherwise fall back to the sync client, so sync and
+    /// async clients can be mixed in one Load. Other clients always run sync.
+    pub async fn handle_async(&self, config: &HashMap<String, Value>) -> Result<Value, LoadError> {
+        let client = client_id(config)?;
+
+        let loaded = match client {
+            fixed_bits::CLIENT_DB if self.async_db.is_some() => self.load_from_async_db(config).await,
+            fixed_bits::CLIENT_HTTP if self.async_http.is_some() => self.load_from_async_http(config).await,
+            _ => return self.handle(config),
+        }?;
+
+        self.apply_transform(config, loaded)
+    }
+
+    /// Runs the `_load.transform` named in `config`, if any, on `loaded`.
+    fn apply_transform(&self, config: &HashMap<String, Value>, loaded: Value) -> Result<Value, LoadError> {
+        if !config.contains_key("transform") {
+            return Ok(loaded);
+        }
+        let name = scalar_str(config, "transform")?;
+        match self.transforms.get(name) {
+            Some(f) => Ok(f(loaded)),
+            None => self.transform_client.transform(name, loaded).map_err(LoadError::TransformFailed),
         }
     }
 
@@ -93,7 +227,11 @@ impl Load {
         for (config_key, env_key_value) in map {
             if let Value::Scalar(env_key) = env_key_value {
                 let env_key_str = std::str::from_utf8(env_key).unwrap_or("");
-                if let Some(value) = env.get(env_key_str) {
+                let env_key_str = match &self.env_prefix {
+                    Some(prefix) => format!("{}{}", prefix, env_key_str).to_uppercase(),
+                    None => env_key_str.to_string(),
+                };
+                if let Some(value) = env.get(&env_key_str) {
                     result.push((config_key.clone(), Value::Scalar(value)));
                 }
             }
@@ -108,6 +246,19 @@ impl Load {
         let in_memory = self.in_memory.as_deref()
             .ok_or(LoadError::ClientNotConfigured)?;
 
+        if let Some(Value::Mapping(map)) = config.get("map") {
+            let mut result = Vec::new();
+            for (config_key, source_key) in map {
+                if let Value::Scalar(source_key) = source_key {
+                    let source_key_str = std::str::from_utf8(source_key).unwrap_or("");
+                    if let Some(value) = in_memory.get(source_key_str) {
+                        result.push((config_key.clone(), value));
+                    }
+                }
+            }
+            return Ok(Value::Mapping(result));
+        }
+
         let key = scalar_str(config, "key")?;
         in_memory
             .get(key)
@@ -134,34 +285,69 @@ impl Load {
     ) -> Result<Value, LoadError> {
         let db = self.db.as_deref()
             .ok_or(LoadError::ClientNotConfigured)?;
+        let (connection, table, columns) = db_request(config)?;
 
-        let connection = config
-            .get("connection")
-            .ok_or(LoadError::ConfigMissing("connection".into()))?;
+        let where_clause = match config.get("where") {
+            Some(Value::Scalar(b)) => Some(b.as_slice()),
+            _ => None,
+        };
+        let rows = match (config.get("where"), self.timeout) {
+            (Some(Value::Mapping(params)), None) => db.get_params(connection, table, &columns, params)?,
+            (_, None) => db.get(connection, table, &columns, where_clause),
+            (Some(Value::Mapping(params)), Some(t)) => db.get_params_timeout(connection, table, &columns, params, t)?,
+            (_, Some(t)) => db.get_timeout(connection, table, &columns, where_clause, t)
+                .map_err(|e| timed_out(t, e))?,
+        };
+        self.db_result(config, table, &columns, rows)
+    }
 
-        let table = scalar_str(config, "table")?;
+    async fn load_from_async_db(
+        &self,
+        config: &HashMap<String, Value>,
+    ) -> Result<Value, LoadError> {
+        let db = self.async_db.as_deref()
+            .ok_or(LoadError::ClientNotConfigured)?;
+        let (connection, table, columns) = db_request(config)?;
 
-        let columns = match config.get("columns") {
-            Some(Value::Mapping(m)) => m.iter()
-                .filter_map(|(k, v)| {
-                    if let Value::Scalar(col) = v { Some((k.clone(), col.clone())) } else { None }
-                })
-                .collect::<Vec<_>>(),
-            _ => return Err(LoadError::ConfigMissing("columns".into())),
+        let rows = match config.get("where") {
+            Some(Value::Mapping(params)) => db.get_params(connection, table, &columns, params).await?,
+            Some(Value::Scalar(b)) => db.get(connection, table, &columns, Some(b.as_slice())).await,
+            _ => db.get(connection, table, &columns, None).await,
         };
+        self.db_result(config, table, &columns, rows)
+    }
 
-        let where_clause = config.get("where")
-            .and_then(|v| if let Value::Scalar(b) = v { Some(b.as_slice()) } else { None });
-
-        let rows = db
-            .get(connection, table, &columns, where_clause)
-            .ok_or_else(|| LoadError::NotFound(table.into()))?;
+    /// Checks and maps the rows returned for a `Db` load.
+    fn db_result(
+        &self,
+        config: &HashMap<String, Value>,
+        table: &str,
+        columns: &[(Vec<u8>, Vec<u8>)],
+        rows: Option<Vec<Value>>,
+    ) -> Result<Value, LoadError> {
+        let rows = rows.ok_or_else(|| LoadError::NotFound(table.into()))?;
 
         if rows.is_empty() {
             return Err(LoadError::NotFound(table.into()));
         }
 
-        Ok(Value::Sequence(rows))
+        if let Some(Value::Mapping(first)) = rows.first() {
+            for (yaml_key, db_column) in columns {
+                if !first.iter().any(|(k, _)| k == db_column || k == yaml_key) {
+                    self.warn(&format!(
+                        "Load::load_from_db: column '{}' (map '{}') is absent from '{}' rows",
+                        String::from_utf8_lossy(db_column), String::from_utf8_lossy(yaml_key), table,
+                    ));
+                }
+            }
+        }
+
+        let mut rows = rows.into_iter().map(|row| map_row(row, columns));
+        if matches!(config.get("multi"), Some(Value::Scalar(b)) if b == b"true") {
+            Ok(Value::Sequence(rows.collect()))
+        } else {
+            rows.next().ok_or_else(|| LoadError::NotFound(table.into()))
+        }
     }
 
     fn load_from_file(
@@ -171,6 +357,21 @@ impl Load {
         let file = self.file.as_deref()
             .ok_or(LoadError::ClientNotConfigured)?;
 
+        if config.contains_key("glob") {
+            let pattern = scalar_str(config, "glob")?;
+            let mut result = Vec::new();
+            for path in file.glob(pattern).into_iter().take(MAX_GLOB_FILES) {
+                let stem = match std::path::Path::new(&path).file_stem() {
+                    Some(s) => s.to_string_lossy().into_owned(),
+                    None => continue,
+                };
+                if let Some(bytes) = file.get(&path) {
+                    result.push((stem.into_bytes(), Value::Scalar(bytes)));
+                }
+            }
+            return Ok(Value::Mapping(result));
+        }
+
         let key = scalar_str(config, "key")?;
         let bytes = file
             .get(key)
@@ -186,21 +387,135 @@ impl Load {
             .ok_or(LoadError::ClientNotConfigured)?;
 
         let url = scalar_str(config, "url")?;
+        let headers = headers_list(config);
+        let method = if config.contains_key("method") { scalar_str(config, "method")? } else { "GET" };
+        let body = config.get("body");
+
+        let cache = self.api_cache.as_ref()
+            .filter(|_| body.is_none() && method.eq_ignore_ascii_case("GET"));
+        let cache_key = cache.map(|_| (url.to_string(), headers.clone()));
+        if let (Some(cache), Some(k)) = (cache, &cache_key)
+            && let Some((at, value)) = cache.entries.lock().unwrap().get(k)
+            && at.elapsed() < cache.ttl
+        {
+            return Ok(value.clone());
+        }
 
-        let headers = match config.get("headers") {
-            Some(Value::Mapping(m)) => Some(
-                m.iter()
-                    .filter_map(|(k, v)| {
-                        if let Value::Scalar(val) = v { Some((k.clone(), val.clone())) } else { None }
-                    })
-                    .collect::<Vec<_>>()
-            ),
-            _ => None,
-        };
+        let value = self.request_http(http, method, url, headers.as_deref(), body)?;
+        if let (Some(cache), Some(k)) = (cache, cache_key) {
+            let mut entries = cache.entries.lock().unwrap();
+            entries.retain(|_, (at, _)| at.elapsed() < cache.ttl);
+            entries.insert(k, (Instant::now(), value.clone()));
+        }
+        Ok(value)
+    }
 
-        http.get(url, headers.as_deref())
+    fn request_http(
+        &self,
+        http: &dyn HttpClient,
+        method: &str,
+        url: &str,
+        headers: Option<&[(Vec<u8>, Vec<u8>)]>,
+        body: Option<&Value>,
+    ) -> Result<Value, LoadError> {
+        if let Some(t) = self.timeout {
+            return http.request_timeout(method, url, headers, body, t)
+                .map_err(|e| timed_out(t, e))?
+                .ok_or_else(|| LoadError::NotFound(url.into()));
+        }
+        if body.is_none() && method.eq_ignore_ascii_case("GET") {
+            return http.get(url, headers)
+                .ok_or_else(|| LoadError::NotFound(url.into()));
+        }
+        http.request(method, url, headers, body)
             .ok_or_else(|| LoadError::NotFound(url.into()))
     }
+
+    async fn load_from_async_http(
+        &self,
+        config: &HashMap<String, Value>,
+    ) -> Result<Value, LoadError> {
+        let http = self.async_http.as_deref()
+            .ok_or(LoadError::ClientNotConfigured)?;
+
+        let url = scalar_str(config, "url")?;
+        let headers = headers_list(config);
+        let method = if config.contains_key("method") { scalar_str(config, "method")? } else { "GET" };
+        let body = config.get("body");
+
+        let response = if body.is_none() && method.eq_ignore_ascii_case("GET") {
+            http.get(url, headers.as_deref()).await
+        } else {
+            http.request(method, url, headers.as_deref(), body).await
+        };
+        response.ok_or_else(|| LoadError::NotFound(url.into()))
+    }
+}
+
+fn timed_out(timeout: Duration, reason: String) -> LoadError {
+    LoadError::TimedOut(format!("load timed out after {:?}: {}", timeout, reason))
+}
+
+fn client_id(config: &HashMap<String, Value>) -> Result<u64, LoadError> {
+    match config.get("client") {
+        Some(Value::Scalar(b)) => Ok(u64::from_le_bytes(b.as_slice().try_into().unwrap_or([0u8; 8]))),
+        _ => Err(LoadError::ConfigMissing("client".into())),
+    }
+}
+
+/// (connection, table, columns) of a `Db` load.
+type DbRequest<'a> = (&'a Value, &'a str, Vec<(Vec<u8>, Vec<u8>)>);
+
+fn db_request(config: &HashMap<String, Value>) -> Result<DbRequest<'_>, LoadError> {
+    let connection = match config.get("connection") {
+        Some(c @ Value::Mapping(_)) => c,
+        Some(_) => return Err(LoadError::ConfigMissing("connection must be a mapping after resolution".into())),
+        None => return Err(LoadError::ConfigMissing("connection".into())),
+    };
+
+    let table = scalar_str(config, "table")?;
+
+    let columns = match config.get("map").or_else(|| config.get("columns")) {
+        Some(Value::Mapping(m)) => m.iter()
+            .filter_map(|(k, v)| {
+                if let Value::Scalar(col) = v { Some((k.clone(), col.clone())) } else { None }
+            })
+            .collect::<Vec<_>>(),
+        _ => return Err(LoadError::ConfigMissing("map".into())),
+    };
+
+    Ok((connection, table, columns))
+}
+
+fn headers_list(config: &HashMap<String, Value>) -> Option<Vec<(Vec<u8>, Vec<u8>)>> {
+    match config.get("headers") {
+        Some(Value::Mapping(m)) => Some(
+            m.iter()
+                .filter_map(|(k, v)| {
+                    if let Value::Scalar(val) = v { Some((k.clone(), val.clone())) } else { None }
+                })
+                .collect::<Vec<_>>()
+        ),
+        _ => None,
+    }
+}
+
+/// Renames a row's db columns to yaml keys per `columns` (yaml_key, db_column).
+/// A column the adapter already returned under its yaml key is kept as-is.
+fn map_row(row: Value, columns: &[(Vec<u8>, Vec<u8>)]) -> Value {
+    let Value::Mapping(fields) = row else { return row };
+    if columns.is_empty() {
+        return Value::Mapping(fields);
+    }
+    let mapped = columns.iter()
+        .filter_map(|(yaml_key, db_column)| {
+            fields.iter()
+                .find(|(k, _)| k == db_column)
+                .or_else(|| fields.iter().find(|(k, _)| k == yaml_key))
+                .map(|(_, v)| (yaml_key.clone(), v.clone()))
+        })
+        .collect();
+    Value::Mapping(mapped)
 }
 
 fn scalar_str<'a>(config: &'a HashMap<String, Value>, key: &str) -> Result<&'a str, LoadError> {
@@ -240,6 +555,30 @@ mod tests {
         fn delete(&self, _key: &str) -> bool { false }
     }
 
+    #[test]
+    fn test_load_from_env_with_prefix() {
+        struct PrefixedEnv;
+        impl EnvClient for PrefixedEnv {
+            fn get(&self, key: &str) -> Option<Vec<u8>> {
+                (key == "APP_DB_HOST").then(|| b"db.internal".to_vec())
+            }
+            fn set(&self, _key: &str, _value: Vec<u8>) -> bool { false }
+            fn delete(&self, _key: &str) -> bool { false }
+        }
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_ENV));
+        config.insert("map".to_string(), Value::Mapping(vec![
+            (b"host".to_vec(), Value::Scalar(b"DB_HOST".to_vec())),
+            (b"port".to_vec(), Value::Scalar(b"db_port".to_vec())),
+        ]));
+        let load = Load::new().with_env(Arc::new(PrefixedEnv)).with_env_prefix("app_");
+        assert_eq!(load.handle(&config).unwrap(),
+            Value::Mapping(vec![(b"host".to_vec(), Value::Scalar(b"db.internal".to_vec()))]));
+
+        let load = Load::new().with_env(Arc::new(PrefixedEnv));
+        assert_eq!(load.handle(&config).unwrap(), Value::Mapping(vec![]));
+    }
+
     #[test]
     fn test_load_from_env() {
         let load = Load::new().with_env(Arc::new(MockEnvClient));
@@ -285,6 +624,111 @@ mod tests {
         assert_eq!(load.handle(&config).unwrap(), data);
     }
 
+    #[test]
+    fn test_load_from_in_memory_map() {
+        let client = Arc::new(MockInMemory::new(&[
+            ("req-host", Value::Scalar(b"localhost".to_vec())),
+            ("req-port", Value::Scalar(b"5432".to_vec())),
+        ]));
+        let load = Load::new().with_in_memory(client);
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_IN_MEMORY));
+        config.insert("map".to_string(), Value::Mapping(vec![
+            (b"host".to_vec(), Value::Scalar(b"req-host".to_vec())),
+            (b"port".to_vec(), Value::Scalar(b"req-port".to_vec())),
+            (b"user".to_vec(), Value::Scalar(b"req-user".to_vec())),
+        ]));
+        assert_eq!(load.handle(&config).unwrap(), Value::Mapping(vec![
+            (b"host".to_vec(), Value::Scalar(b"localhost".to_vec())),
+            (b"port".to_vec(), Value::Scalar(b"5432".to_vec())),
+        ]));
+    }
+
+    #[test]
+    fn test_load_transform_applies_only_when_declared() {
+        let data = Value::Mapping(vec![(b"db_host".to_vec(), Value::Scalar(b"localhost".to_vec()))]);
+        let client = Arc::new(MockInMemory::new(&[("conn", data.clone())]));
+        let mut load = Load::new().with_in_memory(client);
+        load.register_transform("rename_host", |v| match v {
+            Value::Mapping(fields) => Value::Mapping(fields.into_iter()
+                .map(|(k, v)| (if k == b"db_host" { b"host".to_vec() } else { k }, v))
+                .collect()),
+            other => other,
+        });
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_IN_MEMORY));
+        config.insert("key".to_string(), Value::Scalar(b"conn".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), data);
+
+        config.insert("transform".to_string(), Value::Scalar(b"rename_host".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(),
+            Value::Mapping(vec![(b"host".to_vec(), Value::Scalar(b"localhost".to_vec()))]));
+
+        config.insert("transform".to_string(), Value::Scalar(b"unknown".to_vec()));
+        assert!(matches!(load.handle(&config), Err(LoadError::TransformFailed(m)) if m.contains("unknown")));
+    }
+
+    /// Splits scalars on `,` under `split`; fails every other name.
+    struct MockTransform;
+    impl TransformClient for MockTransform {
+        fn transform(&self, name: &str, value: Value) -> Result<Value, String> {
+            match (name, value) {
+                ("split", Value::Scalar(bytes)) => Ok(Value::Sequence(
+                    bytes.split(|&b| b == b',').map(|s| Value::Scalar(s.to_vec())).collect()
+                )),
+                (name, _) => Err(format!("cannot apply '{}'", name)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_transform_client() {
+        let client = Arc::new(MockInMemory::new(&[("tags", Value::Scalar(b"a,b".to_vec()))]));
+        let load = Load::new().with_in_memory(client).with_transform(Arc::new(MockTransform));
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_IN_MEMORY));
+        config.insert("key".to_string(), Value::Scalar(b"tags".to_vec()));
+        config.insert("transform".to_string(), Value::Scalar(b"split".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(),
+            Value::Sequence(vec![Value::Scalar(b"a".to_vec()), Value::Scalar(b"b".to_vec())]));
+
+        // the client replaces the built-ins
+        config.insert("transform".to_string(), Value::Scalar(b"lowercase".to_vec()));
+        assert_eq!(load.handle(&config), Err(LoadError::TransformFailed("cannot apply 'lowercase'".into())));
+    }
+
+    #[test]
+    fn test_load_builtin_transforms() {
+        let row = Value::Mapping(vec![
+            (b"name".to_vec(), Value::Scalar(b"Smith, J.".to_vec())),
+        ]);
+        let client = Arc::new(MockInMemory::new(&[
+            ("tags", Value::Scalar(b"a, b".to_vec())),
+            ("none", Value::Scalar(vec![])),
+            ("row", row.clone()),
+            ("name", Value::Scalar("ÅBC".as_bytes().to_vec())),
+        ]));
+        let mut load = Load::new().with_in_memory(client);
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_IN_MEMORY));
+        config.insert("key".to_string(), Value::Scalar(b"tags".to_vec()));
+        config.insert("transform".to_string(), Value::Scalar(b"split_csv".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), Value::Sequence(vec![Value::Scalar(b"a".to_vec()), Value::Scalar(b"b".to_vec())]));
+        config.insert("key".to_string(), Value::Scalar(b"none".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), Value::Sequence(vec![]));
+        // a row's fields are left alone
+        config.insert("key".to_string(), Value::Scalar(b"row".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), row);
+
+        config.insert("key".to_string(), Value::Scalar(b"name".to_vec()));
+        config.insert("transform".to_string(), Value::Scalar(b"lowercase".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), Value::Scalar("åbc".as_bytes().to_vec()));
+
+        // a registered transform takes precedence over the TransformClient
+        load.register_transform("lowercase", |_| Value::Null);
+        assert_eq!(load.handle(&config).unwrap(), Value::Null);
+    }
+
     #[test]
     fn test_load_from_in_memory_key_not_found() {
         let client = Arc::new(MockInMemory::new(&[]));
@@ -370,7 +814,7 @@ mod tests {
         config.insert("client".to_string(), client_config(fixed_bits::CLIENT_DB));
         config.insert("table".to_string(), Value::Scalar(table.as_bytes().to_vec()));
         config.insert("connection".to_string(), Value::Mapping(vec![]));
-        config.insert("columns".to_string(), Value::Mapping(
+        config.insert("map".to_string(), Value::Mapping(
             columns.iter().map(|(k, v)| (k.as_bytes().to_vec(), Value::Scalar(v.as_bytes().to_vec()))).collect()
         ));
         config
@@ -383,11 +827,80 @@ mod tests {
         let load = Load::new().with_db(client);
         let config = db_config("users", &[("id", "id")]);
         let result = load.handle(&config).unwrap();
-        if let Value::Sequence(rows) = result {
-            assert_eq!(rows[0], row);
-        } else {
-            panic!("expected Sequence");
+        assert_eq!(result, row);
+    }
+
+    #[test]
+    fn test_load_timeout_reaches_adapters() {
+        struct SlowDb;
+        impl DbClient for SlowDb {
+            fn get(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> Option<Vec<Value>> {
+                Some(vec![Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"1".to_vec()))])])
+            }
+            fn set(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> bool { false }
+            fn delete(&self, _: &Value, _: &str, _: Option<&[u8]>) -> bool { false }
+            fn get_timeout(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>, timeout: Duration) -> Result<Option<Vec<Value>>, String> {
+                let work = Duration::from_millis(30);
+                std::thread::sleep(work.min(timeout));
+                if work > timeout { Err("query cancelled".into()) } else { Ok(None) }
+            }
         }
+        let config = db_config("users", &[("id", "id")]);
+
+        let load = Load::new().with_db(Arc::new(SlowDb)).with_timeout(Duration::from_millis(5));
+        assert_eq!(load.handle(&config), Err(LoadError::TimedOut("load timed out after 5ms: query cancelled".into())));
+        let load = Load::new().with_db(Arc::new(SlowDb)).with_timeout(Duration::from_secs(1));
+        assert_eq!(load.handle(&config), Err(LoadError::NotFound("users".into())));
+        assert!(Load::new().with_db(Arc::new(SlowDb)).handle(&config).is_ok());
+
+        // the default hook ignores the timeout
+        let response = Value::Scalar(b"ok".to_vec());
+        let load = Load::new().with_http(Arc::new(MockHttp::new(Some(response.clone())))).with_timeout(Duration::from_millis(1));
+        assert_eq!(load.handle(&http_config("http://example.com")), Ok(response));
+    }
+
+    #[test]
+    fn test_load_from_db_warns_on_absent_column() {
+        let row = Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(b"1".to_vec())),
+            (b"nickname".to_vec(), Value::Null),
+        ]);
+        let warnings: Arc<std::sync::Mutex<Vec<String>>> = Default::default();
+        let sink = warnings.clone();
+        let load = Load::new()
+            .with_db(Arc::new(MockDb::new(vec![row])))
+            .with_warning_handler(Arc::new(move |m: &str| sink.lock().unwrap().push(m.to_string())));
+        let config = db_config("users", &[("id", "id"), ("nickname", "nickname"), ("org_id", "sso_org_id")]);
+
+        let result = load.handle(&config).unwrap();
+        assert_eq!(result, Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(b"1".to_vec())),
+            (b"nickname".to_vec(), Value::Null),
+        ]));
+        let warnings = warnings.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'sso_org_id'"));
+    }
+
+    #[test]
+    fn test_load_from_db_multi_maps_each_row() {
+        let row = |id: &str, org: &str| Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(id.as_bytes().to_vec())),
+            (b"sso_org_id".to_vec(), Value::Scalar(org.as_bytes().to_vec())),
+        ]);
+        let client = Arc::new(MockDb::new(vec![row("1", "10"), row("2", "20")]));
+        let load = Load::new().with_db(client);
+        let mut config = db_config("users", &[("id", "id"), ("org_id", "sso_org_id")]);
+        config.insert("multi".to_string(), Value::Scalar(b"true".to_vec()));
+
+        let mapped = |id: &str, org: &str| Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(id.as_bytes().to_vec())),
+            (b"org_id".to_vec(), Value::Scalar(org.as_bytes().to_vec())),
+        ]);
+        assert_eq!(load.handle(&config).unwrap(), Value::Sequence(vec![mapped("1", "10"), mapped("2", "20")]));
+
+        config.remove("multi");
+        assert_eq!(load.handle(&config).unwrap(), mapped("1", "10"));
     }
 
     #[test]
@@ -398,6 +911,82 @@ mod tests {
         assert!(load.handle(&config).is_err());
     }
 
+    struct MockAsyncDb {
+        rows: Vec<Value>,
+    }
+    impl AsyncDbClient for MockAsyncDb {
+        fn get<'a>(&'a self, _: &'a Value, _: &'a str, _: &'a [(Vec<u8>, Vec<u8>)], _: Option<&'a [u8]>)
+            -> crate::ports::required::BoxFuture<'a, Option<Vec<Value>>> {
+            Box::pin(async move { Some(self.rows.clone()) })
+        }
+    }
+
+    /// Polls a future that never actually waits; the mocks complete immediately.
+    fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+        let mut fut = std::pin::pin!(fut);
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    #[test]
+    fn test_handle_async_uses_async_db_and_falls_back_to_sync() {
+        let row = |id: &[u8]| Value::Mapping(vec![(b"user_id".to_vec(), Value::Scalar(id.to_vec()))]);
+        let config = db_config("users", &[("id", "user_id")]);
+
+        let load = Load::new()
+            .with_db(Arc::new(MockDb::new(vec![row(b"sync")])))
+            .with_async_db(Arc::new(MockAsyncDb { rows: vec![row(b"async")] }));
+        assert_eq!(block_on(load.handle_async(&config)).unwrap(),
+            Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"async".to_vec()))]));
+        assert_eq!(load.handle(&config).unwrap(),
+            Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"sync".to_vec()))]));
+
+        let sync_only = Load::new().with_db(Arc::new(MockDb::new(vec![row(b"sync")])));
+        assert_eq!(block_on(sync_only.handle_async(&config)).unwrap(),
+            Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"sync".to_vec()))]));
+
+        let empty = Load::new().with_async_db(Arc::new(MockAsyncDb { rows: vec![] }));
+        assert!(matches!(block_on(empty.handle_async(&config)), Err(LoadError::NotFound(_))));
+    }
+
+    /// Records the method and body each request arrives with.
+    #[derive(Default)]
+    struct RecordingAsyncHttp {
+        requests: std::sync::Mutex<Vec<(String, Option<Value>)>>,
+    }
+    impl AsyncHttpClient for RecordingAsyncHttp {
+        fn get<'a>(&'a self, _: &'a str, _: Option<&'a [(Vec<u8>, Vec<u8>)]>)
+            -> crate::ports::required::BoxFuture<'a, Option<Value>> {
+            self.requests.lock().unwrap().push(("GET".into(), None));
+            Box::pin(async { Some(Value::Scalar(b"got".to_vec())) })
+        }
+        fn request<'a>(&'a self, method: &'a str, _: &'a str, _: Option<&'a [(Vec<u8>, Vec<u8>)]>, body: Option<&'a Value>)
+            -> crate::ports::required::BoxFuture<'a, Option<Value>> {
+            self.requests.lock().unwrap().push((method.into(), body.cloned()));
+            Box::pin(async { Some(Value::Scalar(b"sent".to_vec())) })
+        }
+    }
+
+    #[test]
+    fn test_handle_async_http_sends_method_and_body() {
+        let client = Arc::new(RecordingAsyncHttp::default());
+        let load = Load::new().with_async_http(client.clone());
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_HTTP));
+        config.insert("url".to_string(), Value::Scalar(b"http://example.com/search".to_vec()));
+        assert_eq!(block_on(load.handle_async(&config)).unwrap(), Value::Scalar(b"got".to_vec()));
+
+        let body = Value::Mapping(vec![(b"q".to_vec(), Value::Scalar(b"x".to_vec()))]);
+        config.insert("method".to_string(), Value::Scalar(b"POST".to_vec()));
+        config.insert("body".to_string(), body.clone());
+        assert_eq!(block_on(load.handle_async(&config)).unwrap(), Value::Scalar(b"sent".to_vec()));
+        assert_eq!(*client.requests.lock().unwrap(), vec![("GET".to_string(), None), ("POST".to_string(), Some(body))]);
+    }
+
     #[test]
     fn test_load_from_db_client_not_configured() {
         let load = Load::new();
@@ -405,6 +994,67 @@ mod tests {
         assert!(load.handle(&config).is_err());
     }
 
+    /// Records whether `get` is reached; uses the default `get_params`.
+    struct RecordingDb {
+        where_clause: std::sync::Mutex<Option<Vec<u8>>>,
+    }
+    impl DbClient for RecordingDb {
+        fn get(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], where_clause: Option<&[u8]>) -> Option<Vec<Value>> {
+            *self.where_clause.lock().unwrap() = where_clause.map(|w| w.to_vec());
+            Some(vec![Value::Mapping(vec![])])
+        }
+        fn set(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> bool { false }
+        fn delete(&self, _: &Value, _: &str, _: Option<&[u8]>) -> bool { false }
+    }
+
+    /// Binds params itself; `get` must not be reached.
+    struct BindingDb {
+        params: std::sync::Mutex<Vec<(Vec<u8>, Value)>>,
+    }
+    impl DbClient for BindingDb {
+        fn get(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> Option<Vec<Value>> { None }
+        fn set(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> bool { false }
+        fn delete(&self, _: &Value, _: &str, _: Option<&[u8]>) -> bool { false }
+        fn get_params(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], params: &[(Vec<u8>, Value)]) -> Result<Option<Vec<Value>>, LoadError> {
+            *self.params.lock().unwrap() = params.to_vec();
+            Ok(Some(vec![Value::Mapping(vec![])]))
+        }
+    }
+
+    fn where_params() -> Vec<(Vec<u8>, Value)> {
+        vec![
+            (b"org_id".to_vec(), Value::Scalar(b"7".to_vec())),
+            (b"status".to_vec(), Value::Scalar(b"active' OR '1'='1".to_vec())),
+        ]
+    }
+
+    #[test]
+    fn test_load_from_db_structured_where_is_bound() {
+        let client = Arc::new(BindingDb { params: Default::default() });
+        let load = Load::new().with_db(client.clone());
+        let mut config = db_config("users", &[("id", "id")]);
+        config.insert("where".to_string(), Value::Mapping(where_params()));
+        assert!(load.handle(&config).is_ok());
+        assert_eq!(*client.params.lock().unwrap(), where_params());
+    }
+
+    #[test]
+    fn test_load_from_db_structured_where_default_is_unsupported() {
+        let client = Arc::new(RecordingDb { where_clause: Default::default() });
+        let load = Load::new().with_db(client.clone());
+        let mut config = db_config("users", &[("id", "id")]);
+        config.insert("where".to_string(), Value::Mapping(where_params()));
+        assert_eq!(load.handle(&config),
+            Err(LoadError::Unsupported("structured where not supported by this DbClient".into())));
+        assert_eq!(*client.where_clause.lock().unwrap(), None);
+
+        let timed = Load::new().with_db(client.clone()).with_timeout(Duration::from_secs(1));
+        assert!(matches!(timed.handle(&config), Err(LoadError::Unsupported(_))));
+
+        let async_db = Load::new().with_async_db(Arc::new(MockAsyncDb { rows: vec![] }));
+        assert!(matches!(block_on(async_db.handle_async(&config)), Err(LoadError::Unsupported(_))));
+    }
+
     // --- HTTP ---
 
     struct MockHttp {
@@ -443,6 +1093,41 @@ mod tests {
         assert!(load.handle(&config).is_err());
     }
 
+    #[test]
+    fn test_api_cache_serves_repeated_get() {
+        struct CountingHttp(std::sync::atomic::AtomicUsize);
+        impl HttpClient for CountingHttp {
+            fn get(&self, _: &str, _: Option<&[(Vec<u8>, Vec<u8>)]>) -> Option<Value> {
+                let n = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Some(Value::Scalar(n.to_string().into_bytes()))
+            }
+            fn set(&self, _: &str, _: Value, _: Option<&[(Vec<u8>, Vec<u8>)]>) -> bool { false }
+            fn delete(&self, _: &str, _: Option<&[(Vec<u8>, Vec<u8>)]>) -> bool { false }
+        }
+        let client = Arc::new(CountingHttp(std::sync::atomic::AtomicUsize::new(0)));
+        let load = Load::new().with_http(client.clone()).with_api_cache(Duration::from_secs(60));
+        let config = http_config("http://example.com/countries");
+
+        assert_eq!(load.handle(&config).unwrap(), Value::Scalar(b"0".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), Value::Scalar(b"0".to_vec()));
+        assert_eq!(client.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        assert_eq!(load.handle(&http_config("http://example.com/regions")).unwrap(), Value::Scalar(b"1".to_vec()));
+        load.clear_api_cache();
+        assert_eq!(load.handle(&config).unwrap(), Value::Scalar(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_api_cache_evicts_expired_entries() {
+        let client = Arc::new(MockHttp::new(Some(Value::Scalar(b"ok".to_vec()))));
+        let load = Load::new().with_http(client).with_api_cache(Duration::ZERO);
+        load.handle(&http_config("http://example.com/countries")).unwrap();
+        load.handle(&http_config("http://example.com/regions")).unwrap();
+        let entries = load.api_cache.as_ref().unwrap().entries.lock().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries.contains_key(&("http://example.com/regions".to_string(), None)));
+    }
+
     #[test]
     fn test_load_from_http_client_not_configured() {
         let load = Load::new();
@@ -475,6 +1160,42 @@ mod tests {
         fn delete(&self, key: &str) -> bool {
             self.store.lock().unwrap().remove(key).is_some()
         }
+        fn glob(&self, pattern: &str) -> Vec<String> {
+            let prefix = pattern.trim_end_matches('*');
+            let mut keys: Vec<String> = self.store.lock().unwrap().keys()
+                .filter(|k| k.starts_with(prefix))
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        }
+    }
+
+    #[test]
+    fn test_load_from_file_glob_merges_by_stem() {
+        let file = MockFileClient::new(&[
+            ("conf.d/db.yml", b"host: a"),
+            ("conf.d/cache.yml", b"host: b"),
+            ("other/skip.yml", b"x"),
+        ]);
+        let load = Load::new().with_file(Arc::new(file));
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_FILE));
+        config.insert("glob".to_string(), Value::Scalar(b"conf.d/*".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), Value::Mapping(vec![
+            (b"cache".to_vec(), Value::Scalar(b"host: b".to_vec())),
+            (b"db".to_vec(), Value::Scalar(b"host: a".to_vec())),
+        ]));
+    }
+
+    #[test]
+    fn test_load_from_file_glob_no_match_is_empty() {
+        let file = MockFileClient::new(&[]);
+        let load = Load::new().with_file(Arc::new(file));
+        let mut config = HashMap::new();
+        config.insert("client".to_string(), client_config(fixed_bits::CLIENT_FILE));
+        config.insert("glob".to_string(), Value::Scalar(b"conf.d/*".to_vec()));
+        assert_eq!(load.handle(&config).unwrap(), Value::Mapping(vec![]));
     }
 
     #[test]
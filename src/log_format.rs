@@ -59,6 +59,115 @@ impl LogFormat {
     }
 }
 
+/// Returns a process-wide, monotonically increasing id for tying log records together.
+pub fn next_op_id() -> u64 {
+    static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+    NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+#[cfg(feature = "logging")]
+thread_local! {
+    /// Id of the innermost `OpSpan` open on this thread; 0 when none is.
+    static CURRENT_OP: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+}
+
+/// The enclosing `OpSpan`'s id, so `fn_log!` records share it; a fresh id outside any span.
+#[cfg(feature = "logging")]
+pub fn current_op_id() -> u64 {
+    match CURRENT_OP.with(|c| c.get()) {
+        0 => next_op_id(),
+        id => id,
+    }
+}
+
+/// Key/value fields of one emitted record, stringified.
+#[cfg(all(feature = "logging", test))]
+type CapturedFields = Vec<(String, String)>;
+
+#[cfg(all(feature = "logging", test))]
+thread_local! {
+    /// Fields of each record emitted on this thread while `tests::capture` runs.
+    static CAPTURED: std::cell::RefCell<Option<Vec<CapturedFields>>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Sends one debug record carrying `kvs` to the installed logger.
+#[cfg(feature = "logging")]
+#[doc(hidden)]
+pub fn emit(target: &str, kvs: &[(&str, log::kv::Value<'_>)], args: std::fmt::Arguments<'_>) {
+    #[cfg(test)]
+    if CAPTURED.with(|c| c.borrow().is_some()) {
+        let fields = kvs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        CAPTURED.with(|c| c.borrow_mut().as_mut().unwrap().push(fields));
+        return;
+    }
+    if log::Level::Debug > log::STATIC_MAX_LEVEL || log::Level::Debug > log::max_level() {
+        return;
+    }
+    log::logger().log(&log::Record::builder()
+        .level(log::Level::Debug)
+        .target(target)
+        .args(args)
+        .key_values(&kvs)
+        .build());
+}
+
+/// Entry/exit log pair around one State operation. Records carry the structured
+/// fields `op_id`, `op`, `key`, `depth`, and on exit `outcome` and `elapsed_us`.
+/// While the span is open, `fn_log!` records on the same thread carry its `op_id`.
+/// Without the `logging` feature this does nothing.
+#[must_use]
+pub struct OpSpan {
+    #[cfg(feature = "logging")]
+    op_id: u64,
+    #[cfg(feature = "logging")]
+    op: &'static str,
+    #[cfg(feature = "logging")]
+    key: String,
+    #[cfg(feature = "logging")]
+    depth: usize,
+    #[cfg(feature = "logging")]
+    start: std::time::Instant,
+    #[cfg(feature = "logging")]
+    outer_op_id: u64,
+}
+
+impl OpSpan {
+    #[allow(unused_variables)]
+    pub fn enter(op: &'static str, key: &str, depth: usize) -> Self {
+        #[cfg(feature = "logging")]
+        {
+            let op_id = next_op_id();
+            let outer_op_id = CURRENT_OP.with(|c| c.replace(op_id));
+            emit(module_path!(), &[("op_id", op_id.into()), ("op", op.into()), ("key", key.into()), ("depth", depth.into())],
+                format_args!("{} enter", LogFormat::call("State", op, &[LogFormat::format_str_arg(key)])));
+            Self { op_id, op, key: key.to_string(), depth, start: std::time::Instant::now(), outer_op_id }
+        }
+        #[cfg(not(feature = "logging"))]
+        Self {}
+    }
+
+    /// `outcome` is e.g. `"hit"`, `"miss"`, `"ok"` or `"err"`.
+    #[allow(unused_variables)]
+    pub fn exit(self, outcome: &str) {
+        #[cfg(feature = "logging")]
+        {
+            let elapsed_us = self.start.elapsed().as_micros() as u64;
+            emit(module_path!(), &[
+                ("op_id", self.op_id.into()), ("op", self.op.into()), ("key", self.key.as_str().into()),
+                ("depth", self.depth.into()), ("outcome", outcome.into()), ("elapsed_us", elapsed_us.into()),
+            ], format_args!("{} exit {} ({}us)",
+                LogFormat::call("State", self.op, &[LogFormat::format_str_arg(&self.key)]), outcome, elapsed_us));
+        }
+    }
+}
+
+#[cfg(feature = "logging")]
+impl Drop for OpSpan {
+    fn drop(&mut self) {
+        CURRENT_OP.with(|c| c.set(self.outer_op_id));
+    }
+}
+
 /// Log macro: fn call
 ///
 /// # Examples
@@ -66,19 +175,22 @@ impl LogFormat {
 /// use crate::fn_log;
 ///
 /// fn_log!("State", "get", "cache.user");
-/// // Logs: State::get('cache.user')
+/// // Logs: State::get('cache.user')  {op_id=…, key="cache.user"}
+/// // op_id is the enclosing OpSpan's when one is open on this thread.
 /// ```
 #[macro_export]
 macro_rules! fn_log {
     ($class:expr, $fun:expr $(, $arg:expr)*) => {{
         #[cfg(feature = "logging")]
         {
-            let args: Vec<String> = vec![
-                $(
-                    $crate::log_format::LogFormat::format_str_arg($arg),
-                )*
-            ];
-            log::debug!("{}", $crate::log_format::LogFormat::call($class, $fun, &args));
+            let raw: &[&str] = &[$($arg,)*];
+            let args: Vec<String> = raw.iter()
+                .map(|a| $crate::log_format::LogFormat::format_str_arg(a))
+                .collect();
+            $crate::log_format::emit(module_path!(), &[
+                ("op_id", $crate::log_format::current_op_id().into()),
+                ("key", raw.first().copied().unwrap_or("").into()),
+            ], format_args!("{}", $crate::log_format::LogFormat::call($class, $fun, &args)));
         }
     }};
 }
@@ -105,6 +217,53 @@ mod tests {
         assert_eq!(result.len(), 52);
     }
 
+    /// Runs `f`, returning the fields of each record it emits on this thread.
+    #[cfg(feature = "logging")]
+    fn capture(f: impl FnOnce()) -> Vec<CapturedFields> {
+        CAPTURED.with(|c| *c.borrow_mut() = Some(Vec::new()));
+        f();
+        CAPTURED.with(|c| c.borrow_mut().take().unwrap())
+    }
+
+    #[cfg(feature = "logging")]
+    fn field(fields: &[(String, String)], name: &str) -> Option<String> {
+        fields.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+    }
+
+    #[cfg(feature = "logging")]
+    #[test]
+    fn test_op_span_emits_structured_records() {
+        let records = capture(|| OpSpan::enter("get", "span.test.key", 2).exit("miss"));
+        assert_eq!(records.len(), 2);
+        assert_eq!(field(&records[0], "op_id"), field(&records[1], "op_id"));
+        assert_eq!(field(&records[0], "depth").as_deref(), Some("2"));
+        assert_eq!(field(&records[1], "outcome").as_deref(), Some("miss"));
+        assert!(field(&records[1], "elapsed_us").is_some());
+    }
+
+    #[cfg(feature = "logging")]
+    #[test]
+    fn test_fn_log_shares_enclosing_span_op_id() {
+        let records = capture(|| {
+            let outer = OpSpan::enter("get", "a", 0);
+            crate::fn_log!("State", "load", "a");
+            let inner = OpSpan::enter("get", "b", 1);
+            crate::fn_log!("State", "load", "b");
+            inner.exit("ok");
+            crate::fn_log!("State", "store", "a");
+            outer.exit("ok");
+            crate::fn_log!("State", "free", "c");
+        });
+        let ids: Vec<_> = records.iter().map(|f| field(f, "op_id").unwrap()).collect();
+        // outer enter, fn_log, inner enter, fn_log, inner exit, fn_log, outer exit, fn_log
+        assert_eq!(ids[1], ids[0]);
+        assert_ne!(ids[2], ids[0]);
+        assert_eq!(ids[3], ids[2]);
+        assert_eq!(ids[5], ids[0]);
+        assert_ne!(ids[7], ids[0]);
+        assert_ne!(ids[7], ids[2]);
+    }
+
     #[test]
     fn test_format_str_arg_long_string() {
         let long_str = "a".repeat(60);
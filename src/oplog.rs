@@ -0,0 +1,243 @@
+use crate::ports::provided::Value;
+use crate::state::State;
+
+const MAGIC: &[u8; 4] = b"SEO1";
+
+const OP_GET: u8 = 1;
+const OP_SET: u8 = 2;
+const OP_DELETE: u8 = 3;
+
+const VAL_NULL: u8 = 0;
+const VAL_SCALAR: u8 = 1;
+const VAL_SEQUENCE: u8 = 2;
+const VAL_MAPPING: u8 = 3;
+
+/// One public State operation, as recorded by `State::enable_oplog`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Op {
+    Get { key: String },
+    Set { key: String, value: Value, ttl: Option<u64> },
+    Delete { key: String },
+}
+
+impl Op {
+    /// Encodes `ops` into a self-contained byte buffer for saving.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use state_engine::{Op, Value};
+    ///
+    /// let ops = vec![Op::Set { key: "a.b".into(), value: Value::Scalar(b"1".to_vec()), ttl: Some(60) }];
+    /// assert_eq!(Op::decode_all(&Op::encode_all(&ops)).unwrap(), ops);
+    /// ```
+    pub fn encode_all(ops: &[Op]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        put_u32(&mut out, ops.len() as u32);
+        for op in ops {
+            match op {
+                Op::Get { key } => {
+                    out.push(OP_GET);
+                    put_bytes(&mut out, key.as_bytes());
+                }
+                Op::Set { key, value, ttl } => {
+                    out.push(OP_SET);
+                    put_bytes(&mut out, key.as_bytes());
+                    put_value(&mut out, value);
+                    match ttl {
+                        Some(t) => { out.push(1); out.extend_from_slice(&t.to_le_bytes()); }
+                        None => out.push(0),
+                    }
+                }
+                Op::Delete { key } => {
+                    out.push(OP_DELETE);
+                    put_bytes(&mut out, key.as_bytes());
+                }
+            }
+        }
+        out
+    }
+
+    /// Decodes a buffer produced by `encode_all`.
+    pub fn decode_all(bytes: &[u8]) -> Result<Vec<Op>, String> {
+        let mut r = Reader { buf: bytes, pos: 0 };
+        if r.take(4)? != MAGIC {
+            return Err("not an oplog buffer".to_string());
+        }
+        let count = r.u32()?;
+        let mut ops = Vec::new();
+        for _ in 0..count {
+            let op = match r.u8()? {
+                OP_GET => Op::Get { key: r.string()? },
+                OP_SET => {
+                    let key = r.string()?;
+                    let value = r.value()?;
+                    let ttl = match r.u8()? {
+                        0 => None,
+                        _ => Some(u64::from_le_bytes(r.take(8)?.try_into().unwrap_or([0; 8]))),
+                    };
+                    Op::Set { key, value, ttl }
+                }
+                OP_DELETE => Op::Delete { key: r.string()? },
+                tag => return Err(format!("unknown op tag {} at {}", tag, r.pos - 1)),
+            };
+            ops.push(op);
+        }
+        if r.pos != bytes.len() {
+            return Err(format!("trailing bytes after oplog at {}", r.pos));
+        }
+        Ok(ops)
+    }
+}
+
+/// Applies `ops` to `state` in order. Individual results are ignored so the
+/// whole sequence is reproduced even where the original run saw errors.
+pub fn replay(ops: &[Op], state: &mut State) {
+    for op in ops {
+        let _ = match op {
+            Op::Get { key } => state.get(key).map(|_| true),
+            Op::Set { key, value, ttl } => state.set(key, value.clone(), *ttl),
+            Op::Delete { key } => state.delete(key),
+        };
+    }
+}
+
+/// Encodes a single value with the oplog's tagged layout. Used by `KvsCodec::Binary`.
+pub(crate) fn encode_value(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_value(&mut out, value);
+    out
+}
+
+/// Decodes a buffer produced by `encode_value`; trailing bytes are an error.
+pub(crate) fn decode_value(bytes: &[u8]) -> Result<Value, String> {
+    let mut r = Reader { buf: bytes, pos: 0 };
+    let value = r.value()?;
+    if r.pos != bytes.len() {
+        return Err(format!("trailing bytes after value at {}", r.pos));
+    }
+    Ok(value)
+}
+
+fn put_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_bytes(out: &mut Vec<u8>, b: &[u8]) {
+    put_u32(out, b.len() as u32);
+    out.extend_from_slice(b);
+}
+
+fn put_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(VAL_NULL),
+        Value::Scalar(b) => {
+            out.push(VAL_SCALAR);
+            put_bytes(out, b);
+        }
+        Value::Sequence(items) => {
+            out.push(VAL_SEQUENCE);
+            put_u32(out, items.len() as u32);
+            for item in items {
+                put_value(out, item);
+            }
+        }
+        Value::Mapping(fields) => {
+            out.push(VAL_MAPPING);
+            put_u32(out, fields.len() as u32);
+            for (k, v) in fields {
+                put_bytes(out, k);
+                put_value(out, v);
+            }
+        }
+    }
+}
+
+/// Cursor over an `encode_all` buffer.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n)
+            .filter(|&e| e <= self.buf.len())
+            .ok_or_else(|| format!("unexpected end of oplog at {}", self.pos))?;
+        let s = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(s)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap_or([0; 4])))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, String> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        String::from_utf8(self.bytes()?).map_err(|e| format!("invalid key: {}", e))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.u8()? {
+            VAL_NULL => Ok(Value::Null),
+            VAL_SCALAR => Ok(Value::Scalar(self.bytes()?)),
+            VAL_SEQUENCE => {
+                let n = self.u32()?;
+                let mut items = Vec::new();
+                for _ in 0..n {
+                    items.push(self.value()?);
+                }
+                Ok(Value::Sequence(items))
+            }
+            VAL_MAPPING => {
+                let n = self.u32()?;
+                let mut fields = Vec::new();
+                for _ in 0..n {
+                    let k = self.bytes()?;
+                    fields.push((k, self.value()?));
+                }
+                Ok(Value::Mapping(fields))
+            }
+            tag => Err(format!("unknown value tag {} at {}", tag, self.pos - 1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let ops = vec![
+            Op::Get { key: "cache.user".into() },
+            Op::Set {
+                key: "cache.user".into(),
+                value: Value::Mapping(vec![
+                    (b"id".to_vec(), Value::Scalar(b"1".to_vec())),
+                    (b"tags".to_vec(), Value::Sequence(vec![Value::Null, Value::Scalar(vec![0, 255])])),
+                ]),
+                ttl: None,
+            },
+            Op::Delete { key: "cache.user".into() },
+        ];
+        assert_eq!(Op::decode_all(&Op::encode_all(&ops)).unwrap(), ops);
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated() {
+        let bytes = Op::encode_all(&[Op::Get { key: "a.b".into() }]);
+        assert!(Op::decode_all(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Op::decode_all(b"nope").is_err());
+    }
+}
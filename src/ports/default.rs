@@ -1,4 +1,5 @@
-use crate::ports::required::FileClient;
+use crate::ports::required::{FileClient, TransformClient};
+use crate::ports::provided::Value;
 
 pub struct DefaultFileClient;
 
@@ -12,4 +13,94 @@ impl FileClient for DefaultFileClient {
     fn delete(&self, path: &str) -> bool {
         std::fs::remove_file(path).is_ok()
     }
+    fn glob(&self, pattern: &str) -> Vec<String> {
+        let path = std::path::Path::new(pattern);
+        let name_pattern = match path.file_name().and_then(|n| n.to_str()) {
+            Some(n) => n,
+            None => return Vec::new(),
+        };
+        let dir = match path.parent() {
+            Some(d) if !d.as_os_str().is_empty() => d,
+            _ => std::path::Path::new("."),
+        };
+        let entries = match std::fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(_) => return Vec::new(),
+        };
+        let mut matched: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+            .filter_map(|e| {
+                let name = e.file_name();
+                let name = name.to_str()?;
+                if wildcard_match(name_pattern.as_bytes(), name.as_bytes()) {
+                    Some(dir.join(name).to_string_lossy().into_owned())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        matched.sort();
+        matched
+    }
+}
+
+/// The built-in transforms; any other name is an error.
+/// - `split_csv`: a scalar `"a, b"` → `["a", "b"]` (items trimmed; `""` → `[]`);
+///   mappings and sequences pass through unchanged
+/// - `lowercase`: lowercases UTF-8 text in every scalar, mapping fields and sequence
+///   items included
+pub struct DefaultTransformClient;
+
+impl TransformClient for DefaultTransformClient {
+    fn transform(&self, name: &str, value: Value) -> Result<Value, String> {
+        match name {
+            "split_csv" => Ok(match value {
+                Value::Scalar(bytes) if bytes.is_empty() => Value::Sequence(vec![]),
+                Value::Scalar(bytes) => Value::Sequence(bytes.split(|&b| b == b',')
+                    .map(|item| Value::Scalar(item.trim_ascii().to_vec()))
+                    .collect()),
+                other => other,
+            }),
+            "lowercase" => Ok(map_scalars(value, &|bytes| {
+                Value::Scalar(match String::from_utf8(bytes) {
+                    Ok(s) => s.to_lowercase().into_bytes(),
+                    Err(e) => e.into_bytes().to_ascii_lowercase(),
+                })
+            })),
+            _ => Err(format!("transform '{}' is not registered", name)),
+        }
+    }
+}
+
+fn map_scalars(value: Value, f: &dyn Fn(Vec<u8>) -> Value) -> Value {
+    match value {
+        Value::Scalar(bytes) => f(bytes),
+        Value::Sequence(items) => Value::Sequence(items.into_iter().map(|v| map_scalars(v, f)).collect()),
+        Value::Mapping(fields) => Value::Mapping(fields.into_iter().map(|(k, v)| (k, map_scalars(v, f))).collect()),
+        Value::Null => Value::Null,
+    }
+}
+
+/// Matches `name` against `pattern` supporting `*` (any run) and `?` (any single byte).
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| wildcard_match(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match(b"*.yml", b"a.yml"));
+        assert!(wildcard_match(b"part-?.yml", b"part-1.yml"));
+        assert!(!wildcard_match(b"*.yml", b"a.yaml"));
+        assert!(!wildcard_match(b"part-?.yml", b"part-10.yml"));
+    }
 }
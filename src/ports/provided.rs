@@ -8,6 +8,59 @@ pub enum Value {
     Null,
 }
 
+impl Value {
+    /// Flattens nested Mappings into `(dotted.key, leaf)` pairs in order, e.g.
+    /// `{a: {b: 1}}` → `[("a.b", 1)]`. Scalars, Nulls, Sequences (not indexed) and empty
+    /// Mappings are leaves; a root that is not a non-empty Mapping becomes `("", root)`.
+    pub fn flatten(&self) -> Vec<(String, Value)> {
+        fn walk(prefix: &str, value: &Value, out: &mut Vec<(String, Value)>) {
+            match value {
+                Value::Mapping(fields) if !fields.is_empty() => {
+                    for (k, v) in fields {
+                        let k = String::from_utf8_lossy(k);
+                        let path = if prefix.is_empty() { k.into_owned() } else { format!("{}.{}", prefix, k) };
+                        walk(&path, v, out);
+                    }
+                }
+                leaf => out.push((prefix.to_string(), leaf.clone())),
+            }
+        }
+        let mut out = Vec::new();
+        walk("", self, &mut out);
+        out
+    }
+
+    /// Rebuilds nested Mappings from `(dotted.key, value)` pairs, the inverse of `flatten`
+    /// for keys without `.` in their segments. Fields keep first-seen order; a later pair
+    /// replaces an earlier value at the same path, and `""` sets the root.
+    pub fn unflatten(entries: &[(String, Value)]) -> Value {
+        let mut root = Value::Mapping(Vec::new());
+        for (path, value) in entries {
+            if path.is_empty() {
+                root = value.clone();
+                continue;
+            }
+            let mut node = &mut root;
+            for segment in path.split('.') {
+                if !matches!(node, Value::Mapping(_)) {
+                    *node = Value::Mapping(Vec::new());
+                }
+                let Value::Mapping(fields) = node else { unreachable!() };
+                let pos = match fields.iter().position(|(k, _)| k.as_slice() == segment.as_bytes()) {
+                    Some(pos) => pos,
+                    None => {
+                        fields.push((segment.as_bytes().to_vec(), Value::Null));
+                        fields.len() - 1
+                    }
+                };
+                node = &mut fields[pos].1;
+            }
+            *node = value.clone();
+        }
+        root
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ManifestError {
     FileNotFound(String),
@@ -25,6 +78,8 @@ impl std::fmt::Display for ManifestError {
     }
 }
 
+impl std::error::Error for ManifestError {}
+
 #[derive(Debug, PartialEq)]
 pub enum LoadError {
     /// Required client (Env/KVS/DB/HTTP/File) is not configured.
@@ -35,6 +90,15 @@ pub enum LoadError {
     NotFound(String),
     /// JSON parse error from client response.
     ParseError(String),
+    /// Every block of a `_load` fallback chain failed, in the order tried.
+    AllFailed(Vec<LoadError>),
+    /// The DB/HTTP adapter gave up at the deadline set by `Load::with_timeout`.
+    TimedOut(String),
+    /// The client cannot serve this load, e.g. a mapping `_load.where` on a DbClient
+    /// without `get_params`.
+    Unsupported(String),
+    /// The `_load.transform` named for the load failed; holds the TransformClient's message.
+    TransformFailed(String),
 }
 
 impl std::fmt::Display for LoadError {
@@ -44,10 +108,23 @@ impl std::fmt::Display for LoadError {
             LoadError::ConfigMissing(msg)       => write!(f, "ConfigMissing: {}", msg),
             LoadError::NotFound(msg)            => write!(f, "NotFound: {}", msg),
             LoadError::ParseError(msg)          => write!(f, "ParseError: {}", msg),
+            LoadError::TimedOut(msg)            => write!(f, "TimedOut: {}", msg),
+            LoadError::Unsupported(msg)         => write!(f, "Unsupported: {}", msg),
+            LoadError::TransformFailed(msg)     => write!(f, "TransformFailed: {}", msg),
+            LoadError::AllFailed(errors)        => {
+                write!(f, "AllFailed: ")?;
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 { write!(f, "; ")?; }
+                    write!(f, "{}", e)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+impl std::error::Error for LoadError {}
+
 #[derive(Debug, PartialEq)]
 pub enum StoreError {
     /// Required client (KVS/InMemory/HTTP/File) is not configured.
@@ -58,6 +135,8 @@ pub enum StoreError {
     SerializeError(String),
     /// Unsupported client id in config.
     UnsupportedClient(u64),
+    /// The backend could not be reached (adapter's `try_get` failed).
+    Unavailable(String),
 }
 
 impl std::fmt::Display for StoreError {
@@ -67,27 +146,55 @@ impl std::fmt::Display for StoreError {
             StoreError::ConfigMissing(msg)     => write!(f, "ConfigMissing: {}", msg),
             StoreError::SerializeError(msg)    => write!(f, "SerializeError: {}", msg),
             StoreError::UnsupportedClient(id)  => write!(f, "UnsupportedClient: {}", id),
+            StoreError::Unavailable(msg)       => write!(f, "Unavailable: {}", msg),
         }
     }
 }
 
+impl std::error::Error for StoreError {}
+
 #[derive(Debug, PartialEq)]
 pub enum StateError {
     ManifestLoadFailed(String),
     KeyNotFound(String),
-    RecursionLimitExceeded,
+    /// `get` nested deeper than `max_recursion`; holds the key chain being resolved (`a -> b -> ...`).
+    RecursionLimitExceeded(String),
+    /// `get` came back to a key it was still resolving; holds the key chain (`a -> b -> a`).
+    /// Reported as `RecursionLimitExceeded` before this variant was added.
+    CircularReference(String),
     StoreFailed(StoreError),
     LoadFailed(LoadError),
+    /// The `_store` backend was unreachable, as opposed to a miss.
+    StoreUnavailable(String),
+    /// The `_load.client` named here (e.g. `Db`) has no client wired into State.
+    ClientNotConfigured(String),
+    /// The `_store.client` named here (e.g. a misspelled `KSV`) is neither built in
+    /// nor registered with `State::register_store`.
+    StoreClientNotConfigured(String),
 }
 
 impl std::fmt::Display for StateError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            StateError::ManifestLoadFailed(msg)  => write!(f, "ManifestLoadFailed: {}", msg),
-            StateError::KeyNotFound(msg)          => write!(f, "KeyNotFound: {}", msg),
-            StateError::RecursionLimitExceeded    => write!(f, "RecursionLimitExceeded"),
-            StateError::StoreFailed(e)            => write!(f, "StoreFailed: {}", e),
-            StateError::LoadFailed(e)             => write!(f, "LoadFailed: {}", e),
+            StateError::ManifestLoadFailed(msg)       => write!(f, "ManifestLoadFailed: {}", msg),
+            StateError::KeyNotFound(msg)              => write!(f, "KeyNotFound: {}", msg),
+            StateError::RecursionLimitExceeded(chain) => write!(f, "RecursionLimitExceeded: max recursion resolving: {}", chain),
+            StateError::CircularReference(chain)      => write!(f, "CircularReference: {}", chain),
+            StateError::StoreFailed(e)                => write!(f, "StoreFailed: {}", e),
+            StateError::LoadFailed(e)                 => write!(f, "LoadFailed: {}", e),
+            StateError::StoreUnavailable(msg)         => write!(f, "StoreUnavailable: {}", msg),
+            StateError::ClientNotConfigured(client)   => write!(f, "ClientNotConfigured: {}", client),
+            StateError::StoreClientNotConfigured(client) => write!(f, "StoreClientNotConfigured: {}", client),
+        }
+    }
+}
+
+impl std::error::Error for StateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StateError::StoreFailed(e) => Some(e),
+            StateError::LoadFailed(e)  => Some(e),
+            _ => None,
         }
     }
 }
@@ -107,3 +214,69 @@ pub trait State {
     /// Checks existence in cache or _store. Does not trigger _load.
     fn exists(&mut self, key: &str) -> Result<bool, StateError>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_value_flatten_round_trip() {
+        let s = |v: &str| Value::Scalar(v.as_bytes().to_vec());
+        let m = |fields: Vec<(&str, Value)>| Value::Mapping(fields.into_iter().map(|(k, v)| (k.as_bytes().to_vec(), v)).collect());
+        let nested = m(vec![
+            ("a", m(vec![("b", s("1")), ("c", m(vec![("d", Value::Null)]))])),
+            ("tags", Value::Sequence(vec![s("x"), m(vec![("y", s("2"))])])),
+            ("empty", m(vec![])),
+            ("top", s("t")),
+        ]);
+        let flat = nested.flatten();
+        assert_eq!(flat, vec![
+            ("a.b".to_string(), s("1")),
+            ("a.c.d".to_string(), Value::Null),
+            ("tags".to_string(), Value::Sequence(vec![s("x"), m(vec![("y", s("2"))])])),
+            ("empty".to_string(), m(vec![])),
+            ("top".to_string(), s("t")),
+        ]);
+        assert_eq!(Value::unflatten(&flat), nested);
+
+        assert_eq!(s("v").flatten(), vec![(String::new(), s("v"))]);
+        assert_eq!(Value::unflatten(&s("v").flatten()), s("v"));
+        // a later pair replaces a scalar on the way
+        assert_eq!(Value::unflatten(&[("a".into(), s("1")), ("a.b".into(), s("2"))]), m(vec![("a", m(vec![("b", s("2"))]))]));
+    }
+
+    #[test]
+    fn test_state_error_display() {
+        let cases = [
+            (StateError::ManifestLoadFailed("FileNotFound: cache".into()), "ManifestLoadFailed: FileNotFound: cache"),
+            (StateError::KeyNotFound("cache.user".into()), "KeyNotFound: cache.user"),
+            (StateError::RecursionLimitExceeded("cache.user -> connection.tenant".into()), "RecursionLimitExceeded: max recursion resolving: cache.user -> connection.tenant"),
+            (StateError::CircularReference("cache.user -> connection.tenant -> cache.user".into()), "CircularReference: cache.user -> connection.tenant -> cache.user"),
+            (StateError::StoreFailed(StoreError::ClientNotConfigured), "StoreFailed: ClientNotConfigured"),
+            (StateError::StoreClientNotConfigured("KSV".into()), "StoreClientNotConfigured: KSV"),
+            (StateError::LoadFailed(LoadError::NotFound("users".into())), "LoadFailed: NotFound: users"),
+            (StateError::StoreUnavailable("timeout".into()), "StoreUnavailable: timeout"),
+            (StateError::LoadFailed(LoadError::TransformFailed("bad csv".into())), "LoadFailed: TransformFailed: bad csv"),
+            (
+                StateError::LoadFailed(LoadError::AllFailed(vec![LoadError::NotFound("user:1".into()), LoadError::ClientNotConfigured])),
+                "LoadFailed: AllFailed: NotFound: user:1; ClientNotConfigured",
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_state_error_source() {
+        let err = StateError::LoadFailed(LoadError::NotFound("users".into()));
+        assert_eq!(err.source().unwrap().to_string(), "NotFound: users");
+        assert!(StateError::KeyNotFound("cache.user".into()).source().is_none());
+
+        fn boxed() -> Result<(), Box<dyn Error>> {
+            Err(StateError::KeyNotFound("cache.user".into()))?
+        }
+        assert_eq!(boxed().unwrap_err().to_string(), "KeyNotFound: cache.user");
+    }
+}
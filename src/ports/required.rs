@@ -1,19 +1,65 @@
-use crate::ports::provided::Value;
+use crate::ports::provided::{LoadError, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Boxed future returned by the async client traits.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 /// In-process memory store. Internal mutability is the implementor's responsibility.
 pub trait InMemoryClient: Send + Sync {
     fn get(&self, key: &str) -> Option<Value>;
     fn set(&self, key: &str, value: Value) -> bool;
     fn delete(&self, key: &str) -> bool;
+    /// Field-level existence for `field` (dot path) inside the value at `key`.
+    /// Used by `State::exists_deep`. `None` (default) means unsupported; State then reads the whole value.
+    fn has_field(&self, _key: &str, _field: &str) -> Option<bool> { None }
+    /// Keys starting with `prefix`. Used by `State::delete_prefix`. Default returns none.
+    fn scan(&self, _prefix: &str) -> Vec<String> { Vec::new() }
 }
 
 /// KVS store. Serialization/deserialization is handled by the adapter.
 /// Internal mutability is the implementor's responsibility.
 pub trait KVSClient: Send + Sync {
     fn get(&self, key: &str) -> Option<Vec<u8>>;
+    /// Like `get`, but reports an unreachable backend as `Err` instead of a miss.
+    /// Used by `State::get`. Default wraps `get`, so it never fails.
+    fn try_get(&self, key: &str) -> Result<Option<Vec<u8>>, String> { Ok(self.get(key)) }
     /// `ttl` in seconds.
     fn set(&self, key: &str, value: Vec<u8>, ttl: Option<u64>) -> bool;
     fn delete(&self, key: &str) -> bool;
+    /// Sets `key` only if it has no value (e.g. Redis `SET NX`). Returns `false` if it exists.
+    /// Used by `State::set_if_absent`. The default is a `get` followed by a `set` and is
+    /// **not atomic**; override it when the backend offers an atomic primitive.
+    fn set_nx(&self, key: &str, value: Vec<u8>, ttl: Option<u64>) -> bool {
+        if self.get(key).is_some() {
+            return false;
+        }
+        self.set(key, value, ttl)
+    }
+    /// Sets `key` to `value` only if its current value equals `expected` (`None`: must be
+    /// absent). Returns whether the swap happened. Used by `State::compare_and_set`. The
+    /// default is a `get` followed by a `set` and is **not atomic**; override it with the
+    /// backend's primitive (e.g. Redis `WATCH`/`MULTI` or a Lua script).
+    fn cas(&self, key: &str, expected: Option<&[u8]>, value: Vec<u8>, ttl: Option<u64>) -> bool {
+        if self.get(key).as_deref() != expected {
+            return false;
+        }
+        self.set(key, value, ttl)
+    }
+    /// Field-level existence for `field` (dot path) inside the value at `key`, e.g. a hash store.
+    /// Used by `State::exists_deep`. `None` (default) means unsupported; State then reads the whole value.
+    fn has_field(&self, _key: &str, _field: &str) -> Option<bool> { None }
+    /// Whether `key` has a value (e.g. Redis `EXISTS`). Used by `State::exists` so the
+    /// value is neither fetched nor decoded. Default is `get(key).is_some()`.
+    fn exists(&self, key: &str) -> bool { self.get(key).is_some() }
+    /// Keys starting with `prefix` (e.g. Redis `SCAN MATCH prefix*`). Used by
+    /// `State::delete_prefix`. Default returns none.
+    fn scan(&self, _prefix: &str) -> Vec<String> { Vec::new() }
+    /// Seconds until `key` expires (e.g. Redis `TTL`); `None` when it has no expiry or
+    /// no value. Used by `State::ttl`. Default returns `None`.
+    fn ttl(&self, _key: &str) -> Option<i64> { None }
 }
 
 /// Environment / config store.
@@ -49,6 +95,69 @@ pub trait DbClient: Send + Sync {
         table: &str,
         where_clause: Option<&[u8]>,
     ) -> bool;
+    /// Fetches rows matching every (column, value) pair in `params`.
+    /// Used when `_load.where` is a mapping. Implement it by binding the values as
+    /// query parameters; the default fails with `LoadError::Unsupported`, so such a
+    /// load is not mistaken for an empty result.
+    fn get_params(
+        &self,
+        connection: &Value,
+        table: &str,
+        columns: &[(Vec<u8>, Vec<u8>)],
+        params: &[(Vec<u8>, Value)],
+    ) -> Result<Option<Vec<Value>>, LoadError> {
+        let _ = (connection, table, columns, params);
+        Err(LoadError::Unsupported("structured where not supported by this DbClient".into()))
+    }
+    /// `get` with the deadline set by `Load::with_timeout`; called instead of `get` when
+    /// one is set. Return `Err(reason)` when the call gave up at the deadline. Enforcing it
+    /// is the adapter's job; the default ignores `timeout`.
+    fn get_timeout(
+        &self,
+        connection: &Value,
+        table: &str,
+        columns: &[(Vec<u8>, Vec<u8>)],
+        where_clause: Option<&[u8]>,
+        timeout: Duration,
+    ) -> Result<Option<Vec<Value>>, String> {
+        let _ = timeout;
+        Ok(self.get(connection, table, columns, where_clause))
+    }
+    /// `get_params` counterpart of `get_timeout`; return `Err(LoadError::TimedOut(..))`
+    /// when the call gave up at the deadline.
+    fn get_params_timeout(
+        &self,
+        connection: &Value,
+        table: &str,
+        columns: &[(Vec<u8>, Vec<u8>)],
+        params: &[(Vec<u8>, Value)],
+        timeout: Duration,
+    ) -> Result<Option<Vec<Value>>, LoadError> {
+        let _ = timeout;
+        self.get_params(connection, table, columns, params)
+    }
+}
+
+/// Async counterpart of `DbClient` for loads, used by `Load::handle_async`.
+/// Same contract as `DbClient::get` / `DbClient::get_params`.
+pub trait AsyncDbClient: Send + Sync {
+    fn get<'a>(
+        &'a self,
+        connection: &'a Value,
+        table: &'a str,
+        columns: &'a [(Vec<u8>, Vec<u8>)],
+        where_clause: Option<&'a [u8]>,
+    ) -> BoxFuture<'a, Option<Vec<Value>>>;
+    fn get_params<'a>(
+        &'a self,
+        connection: &'a Value,
+        table: &'a str,
+        columns: &'a [(Vec<u8>, Vec<u8>)],
+        params: &'a [(Vec<u8>, Value)],
+    ) -> BoxFuture<'a, Result<Option<Vec<Value>>, LoadError>> {
+        let _ = (connection, table, columns, params);
+        Box::pin(async { Err(LoadError::Unsupported("structured where not supported by this AsyncDbClient".into())) })
+    }
 }
 
 /// HTTP client.
@@ -70,6 +179,66 @@ pub trait HttpClient: Send + Sync {
         url: &str,
         headers: Option<&[(Vec<u8>, Vec<u8>)]>,
     ) -> bool;
+    /// Sends a `method` request with an optional `body` and returns the response.
+    /// Used by `_load` when `method` or `body` is set. The default handles `GET`
+    /// via `get` and returns `None` for other methods.
+    fn request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: Option<&[(Vec<u8>, Vec<u8>)]>,
+        body: Option<&Value>,
+    ) -> Option<Value> {
+        let _ = body;
+        if method.eq_ignore_ascii_case("GET") { self.get(url, headers) } else { None }
+    }
+    /// A `_load` call (`get`, or `request` when a `method`/`body` is set) with the deadline
+    /// set by `Load::with_timeout`. Return `Err(reason)` when the call gave up at the
+    /// deadline. Enforcing it is the adapter's job; the default ignores `timeout`.
+    fn request_timeout(
+        &self,
+        method: &str,
+        url: &str,
+        headers: Option<&[(Vec<u8>, Vec<u8>)]>,
+        body: Option<&Value>,
+        timeout: Duration,
+    ) -> Result<Option<Value>, String> {
+        let _ = timeout;
+        if body.is_none() && method.eq_ignore_ascii_case("GET") {
+            Ok(self.get(url, headers))
+        } else {
+            Ok(self.request(method, url, headers, body))
+        }
+    }
+}
+
+/// Async counterpart of `HttpClient` for loads, used by `Load::handle_async`.
+pub trait AsyncHttpClient: Send + Sync {
+    fn get<'a>(
+        &'a self,
+        url: &'a str,
+        headers: Option<&'a [(Vec<u8>, Vec<u8>)]>,
+    ) -> BoxFuture<'a, Option<Value>>;
+    /// Async counterpart of `HttpClient::request`, used when `method` or `body` is set.
+    /// The default handles `GET` via `get` and returns `None` for other methods.
+    fn request<'a>(
+        &'a self,
+        method: &'a str,
+        url: &'a str,
+        headers: Option<&'a [(Vec<u8>, Vec<u8>)]>,
+        body: Option<&'a Value>,
+    ) -> BoxFuture<'a, Option<Value>> {
+        let _ = body;
+        if method.eq_ignore_ascii_case("GET") { self.get(url, headers) } else { Box::pin(async { None }) }
+    }
+}
+
+/// Post-load transform, selected per load with `_load.transform: name`.
+/// `DefaultTransformClient` provides the built-ins (`split_csv`, `lowercase`).
+pub trait TransformClient: Send + Sync {
+    /// Applies the transform `name` to a loaded value. `Err` fails the load
+    /// with `LoadError::TransformFailed`.
+    fn transform(&self, name: &str, value: Value) -> Result<Value, String>;
 }
 
 /// File client.
@@ -77,4 +246,18 @@ pub trait FileClient: Send + Sync {
     fn get(&self, key: &str) -> Option<Vec<u8>>;
     fn set(&self, key: &str, value: Vec<u8>) -> bool;
     fn delete(&self, key: &str) -> bool;
+    /// Lists keys matching `pattern` (`*` and `?` in the last path segment).
+    /// Used by `_load.glob`. Default returns no matches.
+    fn glob(&self, _pattern: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Custom `_store` backend, registered by client name with `State::register_store`.
+/// `config` holds the resolved `_store` props (`key`, `ttl`, `url`, ...) plus
+/// `client_name`. Internal mutability is the implementor's responsibility.
+pub trait StoreBackend: Send + Sync {
+    fn get(&self, config: &HashMap<String, Value>) -> Option<Value>;
+    fn set(&self, config: &HashMap<String, Value>, value: Value, ttl: Option<u64>) -> bool;
+    fn delete(&self, config: &HashMap<String, Value>) -> bool;
 }
@@ -1,25 +1,91 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::path::PathBuf;
-use crate::core::fixed_bits;
-use crate::core::manifest::{Manifest, ConfigValue};
-use crate::core::parser::{Value as ParseValue, parse};
-use crate::ports::provided::{ManifestError, StateError, Value};
-use crate::ports::required::FileClient;
+use crate::core::{codec, fixed_bits};
+use crate::core::manifest::{Manifest, ConfigValue, NormalizeMode, split_key};
+use crate::core::parser::{Value as ParseValue, Pools, parse};
+use crate::ports::provided::{LoadError, ManifestError, StateError, StoreError, Value};
+use crate::ports::required::{FileClient, StoreBackend};
 use crate::store::Store;
 use crate::load::Load;
+use crate::oplog::Op;
+#[cfg(feature = "watch")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::log_format::OpSpan;
 
 use std::sync::Arc;
 
+type KeyNormalizer = Box<dyn Fn(&str) -> String + Send + Sync>;
+/// Resolves `${env:NAME}` during manifest interpolation; see `State::with_env_lookup`.
+type EnvLookup = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+/// Time source for `_state.cache_ttl`; see `State::with_clock`.
+type Clock = Box<dyn Fn() -> std::time::Instant + Send + Sync>;
+/// A `_store` config written inside `State::transaction`, with the value it held before.
+type JournalEntry = (HashMap<String, Value>, Option<Value>);
+
 pub struct State {
     manifest_dir: PathBuf,
     manifest_file: Box<dyn FileClient>,
     manifest: Manifest,
     state_keys: Vec<u16>,
     state_vals: Vec<Value>,
+    /// When each entry of a key with `_state.cache_ttl` was cached.
+    cached_at: HashMap<u16, std::time::Instant>,
+    clock: Option<Clock>,
     store: Store,
     load: Load,
     max_recursion: usize,
-    called_keys: HashSet<String>,
+    called_keys: Vec<String>,
+    manifest_overlay: Option<String>,
+    extension_priority: Option<Vec<String>>,
+    key_normalizer: Option<KeyNormalizer>,
+    oplog: Option<Vec<Op>>,
+    frozen: Option<HashMap<String, Option<Value>>>,
+    metrics: StateMetrics,
+    env_interpolation: Option<EnvInterpolation>,
+    env_lookup: Option<EnvLookup>,
+    touched_keys: Vec<String>,
+    missing_keys: Vec<String>,
+    dry_run: Option<Vec<DryRunWrite>>,
+    journal: Option<Vec<JournalEntry>>,
+    #[cfg(feature = "watch")]
+    manifest_changed: Option<Arc<AtomicBool>>,
+}
+
+/// How `${env:NAME}` tokens in manifest YAML are handled when `NAME` is not set.
+/// See `State::with_env_interpolation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvInterpolation {
+    /// Leave the `${env:NAME}` token in place (it is then parsed like any other placeholder).
+    KeepMissing,
+    /// Fail the manifest load with `ManifestError::ParseError`.
+    ErrorOnMissing,
+}
+
+/// Counters for the outcome of each `State::get` resolution, including lookups
+/// made while resolving placeholders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateMetrics {
+    /// Served from the instance cache.
+    pub cache_hits: u64,
+    /// Served from the `_store` backend.
+    pub store_hits: u64,
+    /// Served by a successful `_load`.
+    pub loads: u64,
+    /// Resolved to `None`.
+    pub misses: u64,
+}
+
+/// A `_store` write skipped by `State::with_dry_run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DryRunWrite {
+    /// `"set"` (also for `set_if_absent`, `compare_and_set` and `_load` write-through) or `"delete"`.
+    pub op: String,
+    /// The resolved `_store.key` (or `_store.url` for HTTP).
+    pub store_key: String,
+    /// The `_store.client` name.
+    pub client: String,
+    /// The value that would have been written; `None` for deletes.
+    pub value: Option<Value>,
 }
 
 impl State {
@@ -39,13 +105,45 @@ impl State {
             manifest: Manifest::new(),
             state_keys: vec![0],
             state_vals: vec![Value::Null],
+            cached_at: HashMap::new(),
+            clock: None,
             store: Store::new(),
             load: Load::new(),
             max_recursion: 20,
-            called_keys: HashSet::new(),
+            called_keys: Vec::new(),
+            manifest_overlay: None,
+            extension_priority: None,
+            key_normalizer: None,
+            oplog: None,
+            frozen: None,
+            metrics: StateMetrics::default(),
+            env_interpolation: None,
+            env_lookup: None,
+            touched_keys: Vec::new(),
+            missing_keys: Vec::new(),
+            dry_run: None,
+            journal: None,
+            #[cfg(feature = "watch")]
+            manifest_changed: None,
         }
     }
 
+    /// Deep-merges `{file}.{env}.yml` (or `.yaml`) over each manifest file when present.
+    /// Overlay values win; mappings, including `_load`/`_store`, are merged key by key.
+    pub fn with_manifest_overlay(mut self, env: &str) -> Self {
+        self.manifest_overlay = Some(env.to_string());
+        self
+    }
+
+    /// Looks manifest files up by these extensions in order (e.g. `["yml", "yaml", "json"]`)
+    /// and uses the first that exists. Others found alongside it are reported to the
+    /// warning handler instead of failing with `ManifestError::AmbiguousFile`, which
+    /// remains the default when `.yml` and `.yaml` both exist.
+    pub fn with_extension_priority(mut self, extensions: &[&str]) -> Self {
+        self.extension_priority = Some(extensions.iter().map(|e| e.to_string()).collect());
+        self
+    }
+
     pub fn with_in_memory(mut self, client: Arc<dyn crate::ports::required::InMemoryClient>) -> Self {
         self.store = self.store.with_in_memory(Arc::clone(&client));
         self.load = self.load.with_in_memory(client);
@@ -58,39 +156,367 @@ impl State {
         self
     }
 
+    /// Selects how `_store` values are serialized for `KVSClient` (default `KvsCodec::Raw`).
+    /// Every instance reading the same keys must use the same codec.
+    pub fn with_kvs_codec(mut self, codec: crate::store::KvsCodec) -> Self {
+        self.store = self.store.with_kvs_codec(codec);
+        self
+    }
+
     pub fn with_db(mut self, client: Arc<dyn crate::ports::required::DbClient>) -> Self {
         self.load = self.load.with_db(client);
         self
     }
 
+    /// Deadline passed to the DB/HTTP adapters' `*_timeout` hooks for `_load` calls.
+    /// See `Load::with_timeout`.
+    pub fn with_load_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.load = self.load.with_timeout(timeout);
+        self
+    }
+
     pub fn with_env(mut self, client: Arc<dyn crate::ports::required::EnvClient>) -> Self {
         self.load = self.load.with_env(client);
         self
     }
 
+    /// Prefixes and uppercases the variable names `client: Env` loads read; see
+    /// `Load::with_env_prefix`. `${env:NAME}` interpolation is not affected.
+    pub fn with_env_prefix(mut self, prefix: &str) -> Self {
+        self.load = self.load.with_env_prefix(prefix);
+        self
+    }
+
     pub fn with_http(mut self, client: Arc<dyn crate::ports::required::HttpClient>) -> Self {
         self.store = self.store.with_http(Arc::clone(&client));
         self.load = self.load.with_http(client);
         self
     }
 
+    /// Caches `client: HTTP` GET responses inside the loader for `ttl`; see
+    /// `Load::with_api_cache`.
+    pub fn with_api_cache(mut self, ttl: std::time::Duration) -> Self {
+        self.load = self.load.with_api_cache(ttl);
+        self
+    }
+
+    /// Drops the responses kept by `with_api_cache`.
+    pub fn clear_api_cache(&self) {
+        self.load.clear_api_cache();
+    }
+
     pub fn with_file(mut self, client: Arc<dyn crate::ports::required::FileClient>) -> Self {
         self.store = self.store.with_file(Arc::clone(&client));
         self.load = self.load.with_file(client);
         self
     }
 
+    /// Receives non-fatal diagnostics from loads, such as a `_load.map` column
+    /// that is absent from the rows a DbClient returned, and manifest files skipped
+    /// under `with_extension_priority`.
+    pub fn with_warning_handler(mut self, handler: crate::load::WarningHandler) -> Self {
+        self.load = self.load.with_warning_handler(handler);
+        self
+    }
+
+    /// Applies `_load.transform` through `client`; see `Load::with_transform`.
+    pub fn with_transform(mut self, client: Arc<dyn crate::ports::required::TransformClient>) -> Self {
+        self.load = self.load.with_transform(client);
+        self
+    }
+
+    /// Registers a named transform for loads declaring `_load.transform: name`.
+    pub fn register_transform(&mut self, name: &str, f: impl Fn(Value) -> Value + Send + Sync + 'static) {
+        self.load.register_transform(name, f);
+    }
+
+    /// Registers a backend for manifests declaring `_store.client: name`, where `name`
+    /// is not a built-in client.
+    pub fn register_store(&mut self, name: &str, backend: Box<dyn StoreBackend>) {
+        self.store.register(name, backend);
+    }
+
     pub fn with_manifest_file(mut self, client: impl FileClient + 'static) -> Self {
         self.manifest_file = Box::new(client);
         self
     }
 
+    /// Sets how manifest key segments are matched during lookups (default `Exact`).
+    pub fn with_manifest_key_normalization(mut self, mode: NormalizeMode) -> Self {
+        self.manifest = self.manifest.with_key_normalization(mode);
+        self
+    }
+
+    /// Uses `open`/`close` instead of `${`/`}` to mark placeholders in manifests,
+    /// e.g. `("<<", ">>")`. Text in the default form is then kept literally.
+    /// Fails with `ManifestError::ParseError` if `open` or `close` is empty.
+    pub fn with_placeholder_delimiters(mut self, open: &str, close: &str) -> Result<Self, ManifestError> {
+        if open.is_empty() || close.is_empty() {
+            return Err(ManifestError::ParseError("placeholder delimiters must not be empty".into()));
+        }
+        let mut options = self.manifest.options.clone();
+        options.placeholder_open = open.as_bytes().to_vec();
+        options.placeholder_close = close.as_bytes().to_vec();
+        self.manifest = self.manifest.with_parse_options(options);
+        Ok(self)
+    }
+
+    /// Fails manifest loading on `_`-prefixed keys other than `_load`/`_store`/`_state`
+    /// (e.g. a mistyped `_stoer`), naming the key and its location. Off by default.
+    pub fn with_strict_meta(mut self, strict: bool) -> Self {
+        self.manifest = self.manifest.with_strict_meta(strict);
+        self
+    }
+
+    /// Fails manifest loading with `nesting too deep` when mappings/sequences nest deeper
+    /// than `depth` (default 128), e.g. through chained `_include`s. Plain YAML text is
+    /// also bounded by the YAML reader's own limit of 128.
+    pub fn with_max_manifest_depth(mut self, depth: usize) -> Self {
+        self.manifest = self.manifest.with_max_depth(depth);
+        self
+    }
+
+    /// Replaces `${env:NAME}` in manifest string values with the process environment
+    /// variable `NAME` (or `with_env_lookup`) when the manifest is read, before parsing.
+    /// State placeholders (`${path}`) are left untouched. Disabled by default.
+    pub fn with_env_interpolation(mut self, mode: EnvInterpolation) -> Self {
+        self.env_interpolation = Some(mode);
+        self
+    }
+
+    /// Reads the time `_state.cache_ttl` is measured against from `f` instead of
+    /// `Instant::now`, e.g. to drive expiry from a test.
+    pub fn with_clock(mut self, f: impl Fn() -> std::time::Instant + Send + Sync + 'static) -> Self {
+        self.clock = Some(Box::new(f));
+        self
+    }
+
+    /// Reads `${env:NAME}` values through `f` instead of the process environment;
+    /// `None` counts as not set.
+    pub fn with_env_lookup(mut self, f: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        self.env_lookup = Some(Box::new(f));
+        self
+    }
+
+    /// Rewrites every key passed to `get`/`set`/`delete`/`exists` before resolution,
+    /// e.g. to map differently-cased keys onto the manifest's form. Defaults to identity.
+    /// Keys resolved from placeholders pass through it too, so it should be idempotent.
+    pub fn with_key_normalizer(mut self, f: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.key_normalizer = Some(Box::new(f));
+        self
+    }
+
+    /// Drops empty segments and trims ASCII whitespace around each one, so
+    /// `" a . b "`, `"a..b"` and `".a.b."` all become `"a.b"`. Idempotent; pass it to
+    /// `with_key_normalizer` to apply it to every key.
+    pub fn collapse_key(key: &str) -> String {
+        key.split('.')
+            .map(str::trim_ascii)
+            .filter(|seg| !seg.is_empty())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn normalize_key(&self, key: &str) -> String {
+        match &self.key_normalizer {
+            Some(f) => f(key),
+            None => key.to_string(),
+        }
+    }
+
+    /// Drops the parsed manifest `file` and the cached values of its keys; the next
+    /// access reads the file again. `_store` contents are not touched.
+    /// Returns whether `file` was loaded.
+    pub fn reload_manifest(&mut self, file: &str) -> bool {
+        crate::fn_log!("State", "reload_manifest", file);
+        if !self.manifest.is_loaded(file) {
+            return false;
+        }
+        let prefix = format!("{}.", file);
+        let in_file = |path: &str| path == file || path.starts_with(&prefix);
+        for i in 1..self.state_keys.len() {
+            let idx = self.state_keys[i];
+            if idx != 0 && self.manifest.key_path(idx).is_some_and(|p| in_file(&p)) {
+                self.state_keys[i] = 0;
+                self.state_vals[i] = Value::Null;
+            }
+        }
+        if let Some(frozen) = self.frozen.as_mut() {
+            frozen.retain(|k, _| !in_file(k));
+        }
+        let Some(key_map) = self.manifest.remove(file) else { return false };
+        // the pools were rebuilt: move cached entries to their keys' new indices
+        let remap = |idx: u16| key_map.get(idx as usize).copied().unwrap_or(0);
+        for i in 1..self.state_keys.len() {
+            let idx = remap(self.state_keys[i]);
+            self.state_keys[i] = idx;
+            if idx == 0 {
+                self.state_vals[i] = Value::Null;
+            }
+        }
+        self.cached_at = std::mem::take(&mut self.cached_at).into_iter()
+            .filter_map(|(idx, at)| Some((remap(idx), at)).filter(|(n, _)| *n != 0))
+            .collect();
+        true
+    }
+
+    /// Polls the manifest directory every `interval` on a background thread. After any
+    /// `.yml`/`.yaml` file changes, the next access reloads every loaded manifest (so
+    /// overlays and `_include` sources are covered). Reads the directory with `std::fs`
+    /// regardless of `with_manifest_file`. Polling stops when the returned handle is dropped.
+    #[cfg(feature = "watch")]
+    pub fn watch_manifests(&mut self, interval: std::time::Duration) -> ManifestWatcher {
+        let changed = Arc::new(AtomicBool::new(false));
+        self.manifest_changed = Some(changed.clone());
+        let stop = Arc::new(AtomicBool::new(false));
+        let dir = self.manifest_dir.clone();
+        let handle = {
+            let stop = stop.clone();
+            let mut seen = manifest_mtimes(&dir);
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(interval);
+                    let now = manifest_mtimes(&dir);
+                    if now != seen {
+                        changed.store(true, Ordering::Release);
+                        seen = now;
+                    }
+                }
+            })
+        };
+        ManifestWatcher { stop, handle: Some(handle) }
+    }
+
     fn load_manifest(&mut self, file: &str) -> Result<(), ManifestError> {
         crate::fn_log!("State", "load_manifest", file);
+        #[cfg(feature = "watch")]
+        if self.manifest_changed.as_ref().is_some_and(|c| c.swap(false, Ordering::Acquire)) {
+            let loaded: Vec<String> = self.manifest.files.keys().cloned().collect();
+            for f in loaded {
+                self.reload_manifest(&f);
+            }
+        }
         if self.manifest.is_loaded(file) {
             return Ok(());
         }
 
+        let bin_path = self.manifest_dir.join(format!("{}.bin", file));
+        if let Some(bytes) = self.manifest_file.get(&bin_path.to_string_lossy()) {
+            // Overlay, env interpolation and `_include` were fixed when the .bin was built.
+            if self.manifest_overlay.is_some() || self.env_interpolation.is_some() {
+                return Err(ManifestError::ParseError(format!(
+                    "precompiled '{}.bin' cannot apply the manifest overlay or env interpolation; remove it to load the YAML",
+                    file
+                )));
+            }
+            let compiled = Manifest::from_bytes(&bytes)
+                .map_err(|e| ManifestError::ParseError(format!("precompiled '{}.bin': {}", file, e)))?;
+            if !compiled.is_loaded(file) {
+                return Err(ManifestError::ParseError(
+                    format!("precompiled '{}.bin' does not contain '{}'", file, file)
+                ));
+            }
+            self.manifest.append(&compiled)
+                .map_err(|e| ManifestError::ParseError(format!("precompiled '{}.bin': {}", file, e)))?;
+            return Ok(());
+        }
+
+        let yaml = self.read_manifest_yaml(file)?;
+        parse_manifest_yaml(file, yaml, &mut self.manifest)
+    }
+
+    fn read_manifest_yaml(&self, file: &str) -> Result<serde_yaml_ng::Value, ManifestError> {
+        let mut yaml = self.read_manifest_tree(file)?;
+        self.expand_includes(&mut yaml, &mut Vec::new())?;
+        Ok(yaml)
+    }
+
+    /// Reads `file` with the overlay and env interpolation applied, but `_include` left as is.
+    fn read_manifest_tree(&self, file: &str) -> Result<serde_yaml_ng::Value, ManifestError> {
+        let mut yaml = parse_yaml_source(&self.read_manifest_source(file)?)?;
+        if let Some(env) = &self.manifest_overlay {
+            match self.read_manifest_source(&format!("{}.{}", file, env)) {
+                Ok(content) => merge_yaml(&mut yaml, parse_yaml_source(&content)?),
+                Err(ManifestError::FileNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if let Some(mode) = self.env_interpolation {
+            interpolate_env(&mut yaml, mode, &|name| self.env_var(name))?;
+        }
+        Ok(yaml)
+    }
+
+    /// Replaces every `_include: "file.path"` with a copy of that node, deep-merged
+    /// under the including node's own keys (local keys win). `stack` holds the
+    /// includes being expanded, to reject cycles.
+    fn expand_includes(&self, node: &mut serde_yaml_ng::Value, stack: &mut Vec<String>) -> Result<(), ManifestError> {
+        match node {
+            serde_yaml_ng::Value::Mapping(m) => {
+                if let Some(target) = m.remove("_include") {
+                    let target = match target {
+                        serde_yaml_ng::Value::String(t) => t,
+                        _ => return Err(ManifestError::ParseError("_include must be a string".into())),
+                    };
+                    if stack.contains(&target) {
+                        return Err(ManifestError::ParseError(format!(
+                            "_include cycle: {} -> {}", stack.join(" -> "), target
+                        )));
+                    }
+                    let (file, path) = split_key(&target);
+                    let tree = self.read_manifest_tree(file)?;
+                    let mut included = path.split('.')
+                        .filter(|seg| !seg.is_empty())
+                        .try_fold(&tree, |v, seg| v.get(seg))
+                        .cloned()
+                        .ok_or_else(|| ManifestError::ParseError(format!("_include '{}' not found", target)))?;
+                    stack.push(target);
+                    self.expand_includes(&mut included, stack)?;
+                    stack.pop();
+                    merge_yaml(&mut included, serde_yaml_ng::Value::Mapping(std::mem::take(m)));
+                    *node = included;
+                }
+                if let serde_yaml_ng::Value::Mapping(m) = node {
+                    for (_, v) in m.iter_mut() {
+                        self.expand_includes(v, stack)?;
+                    }
+                }
+            }
+            serde_yaml_ng::Value::Sequence(items) => {
+                for v in items {
+                    self.expand_includes(v, stack)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `name` from the `with_env_lookup` function, or the process environment.
+    fn env_var(&self, name: &str) -> Option<String> {
+        match &self.env_lookup {
+            Some(f) => f(name),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    fn read_manifest_source(&self, file: &str) -> Result<Vec<u8>, ManifestError> {
+        if let Some(priority) = &self.extension_priority {
+            let mut found = priority.iter().filter_map(|ext| {
+                let path = self.manifest_dir.join(format!("{}.{}", file, ext));
+                self.manifest_file.get(&path.to_string_lossy()).map(|c| (ext, c))
+            });
+            let Some((ext, content)) = found.next() else {
+                return Err(ManifestError::FileNotFound(
+                    priority.iter().map(|ext| format!("'{}.{}'", file, ext)).collect::<Vec<_>>().join(" or ")
+                ));
+            };
+            let skipped: Vec<String> = found.map(|(e, _)| format!("'{}.{}'", file, e)).collect();
+            if !skipped.is_empty() {
+                self.load.warn(&format!("manifest '{}.{}' used; ignoring {}", file, ext, skipped.join(", ")));
+            }
+            return Ok(content);
+        }
         let yml_path  = self.manifest_dir.join(format!("{}.yml",  file));
         let yaml_path = self.manifest_dir.join(format!("{}.yaml", file));
         let yml_key   = yml_path.to_string_lossy();
@@ -98,60 +524,108 @@ impl State {
         let yml_content  = self.manifest_file.get(&yml_key);
         let yaml_content = self.manifest_file.get(&yaml_key);
 
-        let content = match (yml_content, yaml_content) {
-            (Some(_), Some(_)) => return Err(ManifestError::AmbiguousFile(
+        match (yml_content, yaml_content) {
+            (Some(_), Some(_)) => Err(ManifestError::AmbiguousFile(
                 format!("both '{}.yml' and '{}.yaml' exist.", file, file)
             )),
-            (Some(c), None) => c,
-            (None, Some(c)) => c,
-            (None, None) => return Err(ManifestError::FileNotFound(
+            (Some(c), None) => Ok(c),
+            (None, Some(c)) => Ok(c),
+            (None, None) => Err(ManifestError::FileNotFound(
                 format!("'{}.yml' or '{}.yaml'", file, file)
             )),
-        };
+        }
+    }
 
-        let content_str = std::str::from_utf8(&content)
-            .map_err(|e| ManifestError::ParseError(format!("UTF-8 error: {}", e)))?;
+    /// Parses `content` as the manifest YAML for `file` without reading it via the
+    /// manifest FileClient. Later lookups of `file` behave as if it were loaded from disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use state_engine::State;
+    ///
+    /// let mut state = State::new("./manifest");
+    /// state.insert_manifest_source("session", "user_id:\n  _state:\n    type: integer\n").unwrap();
+    /// assert_eq!(state.store_client_of("session.user_id").unwrap(), None);
+    /// ```
+    pub fn insert_manifest_source(&mut self, file: &str, content: &str) -> Result<(), ManifestError> {
+        crate::fn_log!("State", "insert_manifest_source", file);
+        let mut yaml = parse_yaml_source(content.as_bytes())?;
+        if let Some(mode) = self.env_interpolation {
+            interpolate_env(&mut yaml, mode, &|name| self.env_var(name))?;
+        }
+        self.expand_includes(&mut yaml, &mut Vec::new())?;
+        parse_manifest_yaml(file, yaml, &mut self.manifest)
+    }
 
-        let yaml_root: serde_yaml_ng::Value = serde_yaml_ng::from_str(content_str)
-            .map_err(|e| ManifestError::ParseError(format!("YAML parse error: {}", e)))?;
+    /// Parses the manifest YAML for `file` and returns it in precompiled binary form.
+    /// Writing the result to `{manifest_dir}/{file}.bin` makes later States skip YAML parsing;
+    /// a `.bin` takes precedence over `.yml`/`.yaml` when both are present.
+    /// The manifest overlay and `${env:..}` interpolation, if configured, are applied
+    /// before compiling. A State with either configured refuses to load a `.bin`.
+    pub fn precompile_manifest(&self, file: &str) -> Result<Vec<u8>, ManifestError> {
+        let yaml = self.read_manifest_yaml(file)?;
+        let mut manifest = Manifest::new().with_parse_options(self.manifest.options.clone());
+        parse_manifest_yaml(file, yaml, &mut manifest)?;
+        Ok(manifest.to_bytes())
+    }
 
-        let pm = parse(
-            file,
-            yaml_to_parse_value(yaml_root),
-            &mut self.manifest.dynamic,
-            &mut self.manifest.keys,
-            &mut self.manifest.values,
-            &mut self.manifest.path_map,
-            &mut self.manifest.children_map,
-        ).map_err(|e| ManifestError::ParseError(e))?;
-
-        self.manifest.insert(file.to_string(), pm);
-        Ok(())
+    fn find_state_value(&self, key_idx: u16) -> Option<usize> {
+        self.state_keys.iter().skip(1).position(|&k| k == key_idx).map(|p| p + 1)
     }
 
-    fn split_key<'k>(key: &'k str) -> (&'k str, &'k str) {
-        match key.find('.') {
-            Some(pos) => (&key[..pos], &key[pos + 1..]),
-            None => (key, ""),
+    /// Like `find_state_value`, but first drops the entry when the key's
+    /// `_state.cache_ttl` has run out since it was cached.
+    fn find_fresh_state_value(&mut self, key_idx: u16) -> Option<usize> {
+        let sv_idx = self.find_state_value(key_idx)?;
+        let Some(ttl) = self.manifest.cache_ttl(key_idx) else { return Some(sv_idx) };
+        let now = self.now();
+        if self.cached_at.get(&key_idx).is_some_and(|t| now.duration_since(*t).as_secs_f64() < ttl) {
+            return Some(sv_idx);
         }
+        self.state_keys[sv_idx] = 0;
+        self.state_vals[sv_idx] = Value::Null;
+        self.cached_at.remove(&key_idx);
+        None
     }
 
-    fn find_state_value(&self, key_idx: u16) -> Option<usize> {
-        self.state_keys.iter().skip(1).position(|&k| k == key_idx).map(|p| p + 1)
+    /// Starts the `_state.cache_ttl` clock for a value being cached at `key_idx`.
+    fn stamp_cached(&mut self, key_idx: u16) {
+        if self.manifest.cache_ttl(key_idx).is_some() {
+            let now = self.now();
+            self.cached_at.insert(key_idx, now);
+        }
+    }
+
+    fn now(&self) -> std::time::Instant {
+        match &self.clock {
+            Some(f) => f(),
+            None => std::time::Instant::now(),
+        }
+    }
+
+    /// `(open, close)` placeholder delimiters of the loaded manifests.
+    fn delimiters(&self) -> (String, String) {
+        let o = &self.manifest.options;
+        (
+            String::from_utf8_lossy(&o.placeholder_open).into_owned(),
+            String::from_utf8_lossy(&o.placeholder_close).into_owned(),
+        )
     }
 
     fn resolve_template(&mut self, template: &str) -> Result<Option<String>, StateError> {
+        let (open, close) = self.delimiters();
         let mut result = String::new();
         let mut remaining = template;
-        while let Some(start) = remaining.find("${") {
+        while let Some(start) = remaining.find(open.as_str()) {
             result.push_str(&remaining[..start]);
-            remaining = &remaining[start + 2..];
-            let end = match remaining.find('}') {
+            remaining = &remaining[start + open.len()..];
+            let end = match remaining.find(close.as_str()) {
                 Some(e) => e,
                 None => return Ok(None),
             };
             let path = &remaining[..end];
-            remaining = &remaining[end + 1..];
+            remaining = &remaining[end + close.len()..];
             let resolved = match self.get(path)? {
                 Some(Value::Scalar(b)) => String::from_utf8_lossy(&b).into_owned(),
                 _ => return Ok(None),
@@ -162,11 +636,20 @@ impl State {
         Ok(Some(result))
     }
 
+    /// Resolves the placeholders of a static manifest value; `Ok(None)` when one is missing.
+    fn resolve_static(&mut self, value: String) -> Result<Option<String>, StateError> {
+        if value.contains(self.delimiters().0.as_str()) {
+            self.resolve_template(&value)
+        } else {
+            Ok(Some(value))
+        }
+    }
+
     fn resolve_config_value(&mut self, cv: ConfigValue) -> Result<Option<Value>, StateError> {
         match cv {
             ConfigValue::Client(c) => Ok(Some(Value::Scalar(c.to_le_bytes().to_vec()))),
             ConfigValue::Placeholder(path) => self.get(&path),
-            ConfigValue::Str(s) if s.contains("${") => {
+            ConfigValue::Str(s) if s.contains(self.delimiters().0.as_str()) => {
                 Ok(self.resolve_template(&s)?.map(|s| Value::Scalar(s.into_bytes())))
             }
             ConfigValue::Str(s) => Ok(Some(Value::Scalar(s.into_bytes()))),
@@ -177,7 +660,36 @@ impl State {
                         .collect()
                 )))
             }
+            ConfigValue::Params(params) => {
+                let mut resolved = Vec::with_capacity(params.len());
+                for (column, cv) in params {
+                    let v = self.resolve_config_value(cv)?.unwrap_or(Value::Null);
+                    resolved.push((column.into_bytes(), v));
+                }
+                Ok(Some(Value::Mapping(resolved)))
+            }
+        }
+    }
+
+    /// Fails with the client's name when `_store.client` is neither built-in nor
+    /// registered, so a typo is not mistaken for a miss.
+    fn check_store_client(&self, config: &HashMap<String, Value>) -> Result<(), StateError> {
+        match self.store.unknown_client(config) {
+            Some(name) => {
+                self.load.warn(&format!("unknown _store client '{}'", name));
+                Err(StateError::StoreClientNotConfigured(name.to_string()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// `resolve_config` for a `_store` meta, failing on an unknown `_store.client`.
+    fn resolve_store_config(&mut self, store_idx: u16) -> Result<Option<HashMap<String, Value>>, StateError> {
+        let config = self.resolve_config(store_idx)?;
+        if let Some(config) = &config {
+            self.check_store_client(config)?;
         }
+        Ok(config)
     }
 
     fn resolve_config(&mut self, meta_idx: u16) -> Result<Option<HashMap<String, Value>>, StateError> {
@@ -196,6 +708,7 @@ impl State {
     }
 
     /// Returns the value for `key`, checking state cache → _store → _load in order.
+    /// A stored null is `Ok(Some(Value::Null))`; `Ok(None)` means nothing was found.
     ///
     /// # Examples
     ///
@@ -220,36 +733,68 @@ impl State {
     /// assert!(state.get("connection.common").unwrap().is_some());
     /// ```
     pub fn get(&mut self, key: &str) -> Result<Option<Value>, StateError> {
+        let span = OpSpan::enter("get", key, self.called_keys.len());
+        self.touch(key);
+        let log = self.oplog.take();
+        let result = match self.frozen.as_ref().and_then(|f| f.get(key)) {
+            Some(v) => Ok(v.clone()),
+            None => {
+                let result = self.get_inner(key);
+                if let (Some(frozen), Ok(v)) = (self.frozen.as_mut(), &result) {
+                    frozen.insert(key.to_string(), v.clone());
+                }
+                result
+            }
+        };
+        self.oplog = log.map(|mut l| { l.push(Op::Get { key: key.to_string() }); l });
+        if matches!(result, Ok(None)) {
+            self.record_missing(key);
+        }
+        span.exit(match &result {
+            Ok(Some(_)) => "hit",
+            Ok(None) => "miss",
+            Err(_) => "err",
+        });
+        result
+    }
+
+    fn get_inner(&mut self, key: &str) -> Result<Option<Value>, StateError> {
         crate::fn_log!("State", "get", key);
-        if self.called_keys.len() >= self.max_recursion {
-            return Err(StateError::RecursionLimitExceeded);
+        let key = &self.normalize_key(key);
+        if self.called_keys.contains(key) {
+            let mut chain = self.called_keys.clone();
+            chain.push(key.to_string());
+            return Err(StateError::CircularReference(chain.join(" -> ")));
         }
-        if self.called_keys.contains(&key.to_string()) {
-            return Err(StateError::RecursionLimitExceeded);
+        if self.called_keys.len() >= self.max_recursion {
+            let mut chain = self.called_keys.clone();
+            chain.push(key.to_string());
+            return Err(StateError::RecursionLimitExceeded(chain.join(" -> ")));
         }
 
-        self.called_keys.insert(key.to_string());
+        self.called_keys.push(key.to_string());
 
-        let (file, path) = Self::split_key(key);
+        let (file, path) = split_key(key);
         let file = file.to_string();
         let path = path.to_string();
 
         if let Err(e) = self.load_manifest(&file) {
-            self.called_keys.remove(key);
+            self.leave_key(key);
             return Err(StateError::ManifestLoadFailed(e.to_string()));
         }
 
         let key_idx = match self.manifest.find(&file, &path) {
             Some(idx) => idx,
             None => {
-                self.called_keys.remove(key);
+                self.leave_key(key);
                 return Err(StateError::KeyNotFound(key.to_string()));
             }
         };
 
-        if let Some(sv_idx) = self.find_state_value(key_idx) {
+        if let Some(sv_idx) = self.find_fresh_state_value(key_idx) {
             let val = self.state_vals.get(sv_idx).cloned();
-            self.called_keys.remove(key);
+            self.leave_key(key);
+            self.metrics.cache_hits += 1;
             return Ok(val);
         }
 
@@ -262,17 +807,28 @@ impl State {
         if !has_state_client {
             if let Some(store_idx) = meta.store {
                 match self.resolve_config(store_idx) {
-                    Ok(Some(config)) => {
-                        if let Some(value) = self.store.get(&config) {
-                            self.state_keys.push(key_idx);
-                            self.state_vals.push(value.clone());
-                            self.called_keys.remove(key);
+                    Ok(Some(config)) if let Err(e) = self.check_store_client(&config) => {
+                        self.leave_key(key);
+                        return Err(e);
+                    }
+                    Ok(Some(config)) => match self.store.try_get(&config) {
+                        Ok(Some(value)) => {
+                            let value = self.fill_state_client_fields(&file, &path, key_idx, value);
+                            self.cache_push(key_idx, value.clone());
+                            self.leave_key(key);
+                            self.metrics.store_hits += 1;
                             return Ok(Some(value));
                         }
-                    }
+                        Ok(None) => {}
+                        Err(StoreError::Unavailable(msg)) => {
+                            self.leave_key(key);
+                            return Err(StateError::StoreUnavailable(msg));
+                        }
+                        Err(_) => {}
+                    },
                     Ok(None) => {}
                     Err(e) => {
-                        self.called_keys.remove(key);
+                        self.leave_key(key);
                         return Err(e);
                     }
                 }
@@ -293,65 +849,216 @@ impl State {
                     Some(k) => self.get(&k),
                     None => Ok(None),
                 };
-                self.called_keys.remove(key);
+                if let Ok(Some(value)) = &result {
+                    self.merge_into_parent(&file, &path, value.clone());
+                }
+                self.leave_key(key);
                 return result;
             }
         }
 
         let result = if let Some(load_idx) = meta.load {
-            match self.resolve_config(load_idx) {
-                Ok(Some(mut config)) => {
-                    if !config.contains_key("client") {
-                        self.called_keys.remove(key);
-                        return Ok(None);
-                    }
-
-                    // unqualify map keys for Load
-                    if let Some(Value::Mapping(map_pairs)) = config.get("map").cloned() {
-                        let unqualified: Vec<(Vec<u8>, Value)> = map_pairs.into_iter()
-                            .map(|(qk, v)| {
-                                let field = qk.iter().rposition(|&b| b == b'.')
-                                    .map_or(qk.clone(), |p| qk[p+1..].to_vec());
-                                (field, v)
-                            })
-                            .collect();
-                        config.insert("map".to_string(), Value::Mapping(unqualified));
-                    }
-
-                    match self.load.handle(&config) {
-                        Ok(loaded) => {
-                            if let Some(store_idx) = meta.store {
-                                match self.resolve_config(store_idx) {
-                                    Ok(Some(store_config)) => {
-                                        if self.store.set(&store_config, loaded.clone(), None).unwrap_or(false) {
-                                            self.state_keys.push(key_idx);
-                                            self.state_vals.push(loaded.clone());
-                                        }
-                                    }
-                                    Ok(None) => {
-                                        self.state_keys.push(key_idx);
-                                        self.state_vals.push(loaded.clone());
-                                    }
-                                    Err(_) => {}
+            let loaded = match self.run_load(load_idx) {
+                Ok(Some(loaded)) => self.merge_static_fields(key_idx, loaded)
+                    .map(|loaded| Some(self.fill_state_client_fields(&file, &path, key_idx, loaded))),
+                other => other,
+            };
+            match loaded {
+                Ok(Some(loaded)) => {
+                    if let Some(store_idx) = meta.store {
+                        match self.resolve_config(store_idx) {
+                            Ok(Some(store_config)) if self.dry_run.is_some() => {
+                                self.record_dry_run("set", store_idx, &store_config, Some(loaded.clone()));
+                                self.cache_push(key_idx, loaded.clone());
+                            }
+                            Ok(Some(store_config)) => {
+                                self.journal_write(&store_config);
+                                if self.store.set(&store_config, loaded.clone(), None).unwrap_or(false) {
+                                    self.cache_push(key_idx, loaded.clone());
                                 }
-                            } else {
-                                self.state_keys.push(key_idx);
-                                self.state_vals.push(loaded.clone());
                             }
-                            Ok(Some(loaded))
+                            Ok(None) => {
+                                self.cache_push(key_idx, loaded.clone());
+                            }
+                            Err(_) => {}
                         }
-                        Err(e) => Err(StateError::LoadFailed(e)),
+                    } else {
+                        self.cache_push(key_idx, loaded.clone());
                     }
+                    self.metrics.loads += 1;
+                    Ok(Some(loaded))
+                }
+                other => other,
+            }
+        } else if meta.store.is_none() && let Some(value) = self.manifest.static_value(key_idx) {
+            // a static-only leaf (no _store/_load) reads its manifest value
+            match self.resolve_static(value) {
+                Ok(Some(value)) => {
+                    let value = Value::Scalar(value.into_bytes());
+                    self.cache_push(key_idx, value.clone());
+                    Ok(Some(value))
                 }
-                Ok(None) => Ok(None),
-                Err(e) => Err(e),
+                other => other.map(|_| None),
             }
         } else { Ok(None) };
 
-        self.called_keys.remove(key);
+        if matches!(result, Ok(None)) {
+            self.metrics.misses += 1;
+        }
+        self.leave_key(key);
         result
     }
 
+    /// Runs the `_load` at `load_idx`, then each nested `fallback` block in order until
+    /// one succeeds. `Ok(None)` when the first block has no `client`. When every block
+    /// fails, a single error is returned as-is and several as `LoadError::AllFailed`.
+    /// A block whose config cannot be resolved (e.g. a placeholder naming a missing key)
+    /// fails like any other and the chain moves on.
+    fn run_load(&mut self, load_idx: u16) -> Result<Option<Value>, StateError> {
+        let mut errors = Vec::new();
+        // a block whose config could not be resolved; returned as is when it is the only failure
+        let mut config_error = None;
+        let mut next = Some(load_idx);
+        while let Some(idx) = next {
+            next = self.manifest.load_fallback(idx);
+            let connection_path = self.manifest.build_config(idx)
+                .and_then(|entries| entries.into_iter().find_map(|(k, cv)| match cv {
+                    ConfigValue::Placeholder(p) if k == "connection" => Some(p),
+                    _ => None,
+                }));
+            let mut config = match self.resolve_config(idx) {
+                Ok(Some(config)) => config,
+                Ok(None) => continue,
+                Err(e) => {
+                    errors.push(LoadError::ConfigMissing(e.to_string()));
+                    config_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            if !config.contains_key("client") {
+                if idx == load_idx {
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            // unqualify map keys for Load
+            if let Some(Value::Mapping(map_pairs)) = config.get("map").cloned() {
+                let unqualified: Vec<(Vec<u8>, Value)> = map_pairs.into_iter()
+                    .map(|(qk, v)| {
+                        let field = qk.iter().rposition(|&b| b == b'.')
+                            .map_or(qk.clone(), |p| qk[p+1..].to_vec());
+                        (field, v)
+                    })
+                    .collect();
+                config.insert("map".to_string(), Value::Mapping(unqualified));
+            }
+
+            // a plain `connection: "file.path"` names the key holding the connection mapping
+            if let Some(Value::Scalar(path)) = config.get("connection").cloned() {
+                let connection = self.resolve_connection_path(&String::from_utf8_lossy(&path))?;
+                config.insert("connection".to_string(), connection);
+            } else if let Some(path) = connection_path
+                && let Some(connection @ Value::Mapping(_)) = config.remove("connection")
+            {
+                let connection = self.materialize_connection(&path, connection)?;
+                config.insert("connection".to_string(), connection);
+            }
+
+            match self.load.handle(&config) {
+                Ok(loaded) => return Ok(Some(loaded)),
+                Err(LoadError::ClientNotConfigured) if idx == load_idx && next.is_none() => {
+                    let client = codec::client_decode(self.manifest.get_client(idx))
+                        .map(|name| String::from_utf8_lossy(name).into_owned())
+                        .unwrap_or_default();
+                    return Err(StateError::ClientNotConfigured(client));
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        match errors.len() {
+            0 => Ok(None),
+            1 if let Some(e) = config_error => Err(e),
+            1 => Err(StateError::LoadFailed(errors.remove(0))),
+            _ => Err(StateError::LoadFailed(LoadError::AllFailed(errors))),
+        }
+    }
+
+    /// Drops `key` from the chain of keys being resolved by `get`.
+    fn leave_key(&mut self, key: &str) {
+        if let Some(pos) = self.called_keys.iter().rposition(|k| k == key) {
+            self.called_keys.remove(pos);
+        }
+    }
+
+    /// Resolves a `_load.connection` given as a state path into its connection mapping.
+    fn resolve_connection_path(&mut self, path: &str) -> Result<Value, StateError> {
+        let unresolved = || StateError::LoadFailed(LoadError::ConfigMissing(
+            format!("connection '{}' did not resolve to a mapping", path)
+        ));
+        match self.get(path) {
+            Ok(Some(connection @ Value::Mapping(_))) => self.materialize_connection(path, connection),
+            Ok(_) | Err(StateError::KeyNotFound(_)) | Err(StateError::ManifestLoadFailed(_)) => Err(unresolved()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes `value` as field `path`'s last segment into the cached Mapping of its parent
+    /// node, so a parent read after a `_load.client: State` leaf sees the field. Nothing
+    /// happens when the parent is not cached as a Mapping; `fill_state_client_fields`
+    /// covers a parent read later.
+    fn merge_into_parent(&mut self, file: &str, path: &str, value: Value) {
+        let Some((parent, field)) = path.rsplit_once('.') else { return };
+        let Some(sv_idx) = self.manifest.find(file, parent).and_then(|idx| self.find_state_value(idx)) else { return };
+        if let Value::Mapping(fields) = &mut self.state_vals[sv_idx] {
+            match fields.iter_mut().find(|(k, _)| k.as_slice() == field.as_bytes()) {
+                Some((_, v)) => *v = value,
+                None => fields.push((field.as_bytes().to_vec(), value)),
+            }
+        }
+    }
+
+    /// Adds `key_idx`'s `_load.client: State` children, resolved, to a Mapping read for
+    /// it from the store or a load: the parent-read-later half of `merge_into_parent`.
+    /// Fields already in the Mapping win; a child that resolves to nothing is left out.
+    fn fill_state_client_fields(&mut self, file: &str, path: &str, key_idx: u16, value: Value) -> Value {
+        let Value::Mapping(mut fields) = value else { return value };
+        for (name, _) in self.manifest.field_children(key_idx) {
+            if fields.iter().any(|(k, _)| k.as_slice() == name.as_bytes()) {
+                continue;
+            }
+            let child = format!("{}.{}", path, name);
+            let is_state_client = self.manifest.get_meta(file, &child).load
+                .is_some_and(|load_idx| self.manifest.get_client(load_idx) == fixed_bits::CLIENT_STATE);
+            if is_state_client && let Ok(Some(v)) = self.get(&format!("{}.{}", file, child)) {
+                fields.push((name.into_bytes(), v));
+            }
+        }
+        Value::Mapping(fields)
+    }
+
+    /// Completes a connection mapping read from the node at `path` with that node's static
+    /// fields, so a value served from the store or cache without them (e.g. a partial `set`)
+    /// reaches the adapter whole.
+    fn materialize_connection(&mut self, path: &str, connection: Value) -> Result<Value, StateError> {
+        let (_, _, key_idx) = self.locate(path)?;
+        self.merge_static_fields(key_idx, connection)
+    }
+
+    /// Adds the owner's static manifest fields to a loaded Mapping, placeholders resolved.
+    /// Loaded fields win; a field whose placeholder is missing is left out.
+    fn merge_static_fields(&mut self, key_idx: u16, loaded: Value) -> Result<Value, StateError> {
+        let Value::Mapping(mut fields) = loaded else { return Ok(loaded) };
+        for (name, value) in self.manifest.static_fields(key_idx) {
+            if !fields.iter().any(|(k, _)| k.as_slice() == name.as_bytes())
+                && let Some(value) = self.resolve_static(value)?
+            {
+                fields.push((name.into_bytes(), Value::Scalar(value.into_bytes())));
+            }
+        }
+        Ok(Value::Mapping(fields))
+    }
+
     /// Writes `value` to the _store backend for `key`.
     ///
     /// # Examples
@@ -373,8 +1080,20 @@ impl State {
     /// assert!(state.set("connection.common", Value::Scalar(b"data".to_vec()), None).unwrap());
     /// ```
     pub fn set(&mut self, key: &str, value: Value, ttl: Option<u64>) -> Result<bool, StateError> {
+        let span = OpSpan::enter("set", key, self.called_keys.len());
+        self.touch(key);
+        let log = self.oplog.take();
+        let op = log.as_ref().map(|_| Op::Set { key: key.to_string(), value: value.clone(), ttl });
+        let result = self.set_inner(key, value, ttl);
+        self.oplog = log.map(|mut l| { l.extend(op); l });
+        span.exit(outcome_of(&result));
+        result
+    }
+
+    fn set_inner(&mut self, key: &str, value: Value, ttl: Option<u64>) -> Result<bool, StateError> {
         crate::fn_log!("State", "set", key);
-        let (file, path) = Self::split_key(key);
+        let key = &self.normalize_key(key);
+        let (file, path) = split_key(key);
         let file = file.to_string();
         let path = path.to_string();
 
@@ -390,17 +1109,18 @@ impl State {
         let meta = self.manifest.get_meta(&file, &path);
 
         if let Some(store_idx) = meta.store {
-            match self.resolve_config(store_idx)? {
+            match self.resolve_store_config(store_idx)? {
                 Some(config) => {
+                    if self.dry_run.is_some() {
+                        self.record_dry_run("set", store_idx, &config, Some(value.clone()));
+                        self.cache_put(key_idx, value);
+                        return Ok(true);
+                    }
+                    self.journal_write(&config);
                     return match self.store.set(&config, value.clone(), ttl) {
                         Ok(ok) => {
                             if ok {
-                                if let Some(sv_idx) = self.find_state_value(key_idx) {
-                                    self.state_vals[sv_idx] = value;
-                                } else {
-                                    self.state_keys.push(key_idx);
-                                    self.state_vals.push(value);
-                                }
+                                self.cache_put(key_idx, value);
                             }
                             Ok(ok)
                         }
@@ -413,6 +1133,92 @@ impl State {
         Ok(false)
     }
 
+    /// Writes `value` only when `key` has no value yet, returning `Ok(false)` otherwise.
+    /// A cached value, `Value::Null` included, counts as present. KVS stores go through `KVSClient::set_nx`;
+    /// other stores check then write, which is not atomic across processes.
+    pub fn set_if_absent(&mut self, key: &str, value: Value, ttl: Option<u64>) -> Result<bool, StateError> {
+        crate::fn_log!("State", "set_if_absent", key);
+        self.touch(key);
+        let (file, path, key_idx) = self.locate(key)?;
+        if self.find_state_value(key_idx).is_some() {
+            return Ok(false);
+        }
+        let Some(store_idx) = self.manifest.get_meta(&file, &path).store else { return Ok(false) };
+        let Some(config) = self.resolve_store_config(store_idx)? else { return Ok(false) };
+        if self.dry_run.is_some() {
+            if self.store.exists(&config) {
+                return Ok(false);
+            }
+            self.record_dry_run("set", store_idx, &config, Some(value.clone()));
+            self.cache_put(key_idx, value);
+            return Ok(true);
+        }
+        self.journal_write(&config);
+        let ok = self.store.set_nx(&config, value.clone(), ttl).map_err(StateError::StoreFailed)?;
+        if ok {
+            self.cache_put(key_idx, value);
+        }
+        Ok(ok)
+    }
+
+    /// Writes `new` only when the `_store` value for `key` equals `expected` (`None`: absent),
+    /// returning whether the swap happened. The store is read, not the instance cache.
+    /// KVS stores go through `KVSClient::cas`, whose default is not atomic; other stores
+    /// compare then write. `Ok(false)` when `key` has no `_store`; a failed swap drops
+    /// the cached value so the next `get` reads the one that won.
+    pub fn compare_and_set(&mut self, key: &str, expected: Option<Value>, new: Value, ttl: Option<u64>) -> Result<bool, StateError> {
+        crate::fn_log!("State", "compare_and_set", key);
+        self.touch(key);
+        let (file, path, key_idx) = self.locate(key)?;
+        let Some(store_idx) = self.manifest.get_meta(&file, &path).store else { return Ok(false) };
+        let Some(config) = self.resolve_store_config(store_idx)? else { return Ok(false) };
+        let ok = if self.dry_run.is_some() {
+            let ok = self.store.try_get(&config).map_err(StateError::StoreFailed)? == expected;
+            if ok {
+                self.record_dry_run("set", store_idx, &config, Some(new.clone()));
+            }
+            ok
+        } else {
+            // a successful swap means the store held `expected`, so that is the undo value
+            let prior = self.journal.is_some().then(|| expected.clone());
+            let ok = self.store.compare_and_set(&config, expected, new.clone(), ttl).map_err(StateError::StoreFailed)?;
+            if ok && let (Some(prior), Some(journal)) = (prior, self.journal.as_mut()) {
+                journal.push((config.clone(), prior));
+            }
+            ok
+        };
+        if ok {
+            self.cache_put(key_idx, new);
+        } else {
+            self.cache_drop(key_idx);
+        }
+        Ok(ok)
+    }
+
+    fn cache_drop(&mut self, key_idx: u16) {
+        if let Some(sv_idx) = self.find_state_value(key_idx) {
+            self.state_keys[sv_idx] = 0;
+            self.state_vals[sv_idx] = Value::Null;
+        }
+    }
+
+    fn cache_put(&mut self, key_idx: u16, value: Value) {
+        if let Some(sv_idx) = self.find_state_value(key_idx) {
+            self.stamp_cached(key_idx);
+            self.state_vals[sv_idx] = value;
+        } else {
+            self.cache_push(key_idx, value);
+        }
+    }
+
+    /// Caches a freshly resolved value for `key_idx`, which has no entry yet, and
+    /// starts its `_state.cache_ttl` clock now that the value is in hand.
+    fn cache_push(&mut self, key_idx: u16, value: Value) {
+        self.stamp_cached(key_idx);
+        self.state_keys.push(key_idx);
+        self.state_vals.push(value);
+    }
+
     /// Removes the value for `key` from the _store backend.
     ///
     /// # Examples
@@ -437,8 +1243,19 @@ impl State {
     /// assert!(state.get("connection.common").is_err() || state.get("connection.common").unwrap().is_none());
     /// ```
     pub fn delete(&mut self, key: &str) -> Result<bool, StateError> {
+        let span = OpSpan::enter("delete", key, self.called_keys.len());
+        self.touch(key);
+        let log = self.oplog.take();
+        let result = self.delete_inner(key);
+        self.oplog = log.map(|mut l| { l.push(Op::Delete { key: key.to_string() }); l });
+        span.exit(outcome_of(&result));
+        result
+    }
+
+    fn delete_inner(&mut self, key: &str) -> Result<bool, StateError> {
         crate::fn_log!("State", "delete", key);
-        let (file, path) = Self::split_key(key);
+        let key = &self.normalize_key(key);
+        let (file, path) = split_key(key);
         let file = file.to_string();
         let path = path.to_string();
 
@@ -454,8 +1271,17 @@ impl State {
         let meta = self.manifest.get_meta(&file, &path);
 
         if let Some(store_idx) = meta.store {
-            match self.resolve_config(store_idx)? {
+            match self.resolve_store_config(store_idx)? {
                 Some(config) => {
+                    if self.dry_run.is_some() {
+                        self.record_dry_run("delete", store_idx, &config, None);
+                        if let Some(sv_idx) = self.find_state_value(key_idx) {
+                            self.state_keys[sv_idx] = 0;
+                            self.state_vals[sv_idx] = Value::Null;
+                        }
+                        return Ok(true);
+                    }
+                    self.journal_write(&config);
                     return match self.store.delete(&config) {
                         Ok(ok) => {
                             if ok {
@@ -475,6 +1301,45 @@ impl State {
         Ok(false)
     }
 
+    /// Deletes every key starting with `prefix` from the `_store` backend of `key`
+    /// (InMemory or KVS, via their `scan`), e.g. `"user:42:"` on logout. `_store.key`
+    /// is not resolved. The cached value of `key` is dropped when anything was deleted.
+    /// Returns the number of keys deleted; under `with_dry_run` each match is recorded instead.
+    pub fn delete_prefix(&mut self, key: &str, prefix: &str) -> Result<usize, StateError> {
+        crate::fn_log!("State", "delete_prefix", key);
+        let (file, path, key_idx) = self.locate(key)?;
+        let Some(store_idx) = self.manifest.get_meta(&file, &path).store else { return Ok(0) };
+        let mut config: HashMap<String, Value> = self.manifest.build_config(store_idx)
+            .into_iter().flatten()
+            .filter(|(k, _)| k == "client" || k == "client_name")
+            .filter_map(|(k, cv)| match cv {
+                ConfigValue::Client(c) => Some((k, Value::Scalar(c.to_le_bytes().to_vec()))),
+                ConfigValue::Str(name) => Some((k, Value::Scalar(name.into_bytes()))),
+                _ => None,
+            })
+            .collect();
+        self.check_store_client(&config)?;
+
+        let mut deleted = 0;
+        for store_key in self.store.scan(&config, prefix).map_err(StateError::StoreFailed)? {
+            config.insert("key".to_string(), Value::Scalar(store_key.into_bytes()));
+            if self.dry_run.is_some() {
+                self.record_dry_run("delete", store_idx, &config, None);
+                deleted += 1;
+            } else {
+                self.journal_write(&config);
+                if self.store.delete(&config).map_err(StateError::StoreFailed)? {
+                    deleted += 1;
+                }
+            }
+        }
+        if let (true, Some(sv_idx)) = (deleted > 0, self.find_state_value(key_idx)) {
+            self.state_keys[sv_idx] = 0;
+            self.state_vals[sv_idx] = Value::Null;
+        }
+        Ok(deleted)
+    }
+
     /// Returns `true` if a value exists for `key` in state cache or _store.
     /// Does not trigger _load.
     ///
@@ -500,7 +1365,9 @@ impl State {
     /// ```
     pub fn exists(&mut self, key: &str) -> Result<bool, StateError> {
         crate::fn_log!("State", "exists", key);
-        let (file, path) = Self::split_key(key);
+        self.touch(key);
+        let key = &self.normalize_key(key);
+        let (file, path) = split_key(key);
         let file = file.to_string();
         let path = path.to_string();
 
@@ -513,41 +1380,903 @@ impl State {
             None => return Err(StateError::KeyNotFound(key.to_string())),
         };
 
-        if let Some(sv_idx) = self.find_state_value(key_idx) {
-            return Ok(!matches!(self.state_vals.get(sv_idx), Some(Value::Null) | None));
+        if self.find_fresh_state_value(key_idx).is_some() {
+            return Ok(true);
         }
 
         let meta = self.manifest.get_meta(&file, &path);
-        if let Some(store_idx) = meta.store {
-            if let Some(config) = self.resolve_config(store_idx)? {
-                return Ok(self.store.get(&config).is_some());
+        if let Some(store_idx) = meta.store
+            && let Some(config) = self.resolve_store_config(store_idx)?
+        {
+            if self.store.exists(&config) {
+                return Ok(true);
             }
         }
+        self.record_missing(key);
         Ok(false)
     }
-}
 
-fn yaml_to_parse_value(v: serde_yaml_ng::Value) -> ParseValue {
-    match v {
-        serde_yaml_ng::Value::Mapping(m) => ParseValue::Mapping(
-            m.into_iter()
-                .filter_map(|(k, v)| {
-                    let key = match k {
-                        serde_yaml_ng::Value::String(s) => s.into_bytes(),
-                        _ => return None,
-                    };
-                    Some((key, yaml_to_parse_value(v)))
-                })
+    /// Seconds until the `_store` value of `key` expires, resolving `_store.key` like
+    /// `exists`. Only KVS stores report one (`KVSClient::ttl`); `Ok(None)` for other
+    /// clients, values without expiry, and keys without `_store`. Does not trigger _load.
+    pub fn ttl(&mut self, key: &str) -> Result<Option<i64>, StateError> {
+        crate::fn_log!("State", "ttl", key);
+        let (file, path, _) = self.locate(key)?;
+        let Some(store_idx) = self.manifest.get_meta(&file, &path).store else { return Ok(None) };
+        Ok(self.resolve_store_config(store_idx)?.and_then(|config| self.store.ttl(&config)))
+    }
+
+    /// Like `exists`, but for a key below its `_store` owner (e.g. `cache.user.id` stored
+    /// with `cache.user`) checks that the field itself is present and non-null.
+    /// Uses the client's `has_field` when supported; otherwise reads the owner value
+    /// (from cache or store) once and walks it, so the whole value is still fetched.
+    /// Opaque scalar owners (e.g. raw KVS bytes) have no fields. Does not trigger _load.
+    pub fn exists_deep(&mut self, key: &str) -> Result<bool, StateError> {
+        crate::fn_log!("State", "exists_deep", key);
+        let (file, path, _) = self.locate(key)?;
+        let meta = self.manifest.get_meta(&file, &path);
+        let store_idx = match meta.store {
+            Some(idx) => idx,
+            None => return self.exists(key),
+        };
+
+        let segments: Vec<&str> = if path.is_empty() { vec![] } else { path.split('.').collect() };
+        let owner_len = self.store_owner_len(&file, &segments, store_idx);
+        if owner_len == segments.len() {
+            return self.exists(key);
+        }
+        let owner_path = segments[..owner_len].join(".");
+        let field = &segments[owner_len..];
+
+        if let Some(sv_idx) = self.manifest.find(&file, &owner_path).and_then(|idx| self.find_state_value(idx)) {
+            return Ok(value_has_field(&self.state_vals[sv_idx], field));
+        }
+
+        let config = match self.resolve_store_config(store_idx)? {
+            Some(c) => c,
+            None => return Ok(false),
+        };
+        if let Some(found) = self.store.has_field(&config, &field.join(".")) {
+            return Ok(found);
+        }
+        Ok(self.store.get(&config).is_some_and(|owner| value_has_field(&owner, field)))
+    }
+
+    /// `get`, then fills in the declared child fields that have their own `_load` or
+    /// `_store` (e.g. a `client: State` leaf) by `get`ting each, recursively, so the
+    /// returned Mapping is complete. Children without their own meta come from the
+    /// owner's value. A non-Mapping value is returned as is.
+    pub fn materialize(&mut self, key: &str) -> Result<Option<Value>, StateError> {
+        crate::fn_log!("State", "materialize", key);
+        let (file, path, key_idx) = self.locate(key)?;
+        let value = self.get(key)?;
+        self.materialize_children(&file, &path, key_idx, value)
+    }
+
+    fn materialize_children(&mut self, file: &str, path: &str, key_idx: u16, value: Option<Value>) -> Result<Option<Value>, StateError> {
+        let children = self.manifest.field_children(key_idx);
+        if children.is_empty() {
+            return Ok(value);
+        }
+        let mut fields = match value {
+            Some(Value::Mapping(fields)) => fields,
+            None => vec![],
+            other => return Ok(other),
+        };
+        let meta = self.manifest.get_meta(file, path);
+        for (name, child_idx) in children {
+            let child_path = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+            let child_meta = self.manifest.get_meta(file, &child_path);
+            let child = if child_meta.load != meta.load || child_meta.store != meta.store {
+                self.get(&format!("{}.{}", file, child_path))?
+            } else {
+                fields.iter().find(|(k, _)| k.as_slice() == name.as_bytes()).map(|(_, v)| v.clone())
+            };
+            let Some(child) = self.materialize_children(file, &child_path, child_idx, child)? else { continue };
+            match fields.iter_mut().find(|(k, _)| k.as_slice() == name.as_bytes()) {
+                Some((_, v)) => *v = child,
+                None => fields.push((name.into_bytes(), child)),
+            }
+        }
+        Ok(if fields.is_empty() { None } else { Some(Value::Mapping(fields)) })
+    }
+
+    /// `get`, then reads a `Scalar` as UTF-8 text. Other values, and bytes that are
+    /// not UTF-8, give `Ok(None)`.
+    pub fn get_str(&mut self, key: &str) -> Result<Option<String>, StateError> {
+        Ok(self.get(key)?.and_then(|v| match v {
+            Value::Scalar(b) => String::from_utf8(b).ok(),
+            _ => None,
+        }))
+    }
+
+    /// `get_str`, then parses the whole text as an `i64` (e.g. `"-42"`, so Env-loaded
+    /// values work). Surrounding whitespace or a fraction gives `Ok(None)`.
+    pub fn get_i64(&mut self, key: &str) -> Result<Option<i64>, StateError> {
+        Ok(self.get_str(key)?.and_then(|s| s.parse().ok()))
+    }
+
+    /// `get_str`, then parses the whole text as a finite `f64` (`"1"`, `"0.5"`, `"1e3"`).
+    pub fn get_f64(&mut self, key: &str) -> Result<Option<f64>, StateError> {
+        Ok(self.get_str(key)?
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|f| f.is_finite()))
+    }
+
+    /// `get_str`, then accepts exactly `"true"` or `"false"` (the text YAML and most
+    /// adapters produce).
+    pub fn get_bool(&mut self, key: &str) -> Result<Option<bool>, StateError> {
+        Ok(self.get_str(key)?.and_then(|s| match s.as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }))
+    }
+
+    /// Diagnostic read of the `_store` backend for `key`: returns exactly what the store
+    /// holds, bypassing the instance cache, `_load` and static-field merging. Nothing is
+    /// cached. `Ok(None)` when the key has no `_store` or the store has no value.
+    pub fn get_raw(&mut self, key: &str) -> Result<Option<Value>, StateError> {
+        crate::fn_log!("State", "get_raw", key);
+        let (file, path, _) = self.locate(key)?;
+        let Some(store_idx) = self.manifest.get_meta(&file, &path).store else { return Ok(None) };
+        let Some(config) = self.resolve_store_config(store_idx)? else { return Ok(None) };
+        match self.store.try_get(&config) {
+            Ok(value) => Ok(value),
+            Err(StoreError::Unavailable(msg)) => Err(StateError::StoreUnavailable(msg)),
+            Err(e) => Err(StateError::StoreFailed(e)),
+        }
+    }
+
+    /// Number of leading `segments` naming the node that declares `store_idx`
+    /// (the value's `_store` owner); `segments.len()` when the node itself owns it.
+    fn store_owner_len(&self, file: &str, segments: &[&str], store_idx: u16) -> usize {
+        (0..segments.len())
+            .find(|&n| self.manifest.get_meta(file, &segments[..n].join(".")).store == Some(store_idx))
+            .unwrap_or(segments.len())
+    }
+
+    /// Drops the cached value for `key` so the next `get` reads `_store` (then `_load`)
+    /// again. No store client is called. With `with_owner`, the `_store` owner of `key`
+    /// (e.g. `cache.user` for `cache.user.id`) is dropped as well.
+    /// Returns whether any cached value was dropped.
+    pub fn invalidate(&mut self, key: &str, with_owner: bool) -> Result<bool, StateError> {
+        crate::fn_log!("State", "invalidate", key);
+        let (file, path, key_idx) = self.locate(key)?;
+        let mut targets = vec![key_idx];
+        if let (true, Some(store_idx)) = (with_owner, self.manifest.get_meta(&file, &path).store) {
+            let segments: Vec<&str> = if path.is_empty() { vec![] } else { path.split('.').collect() };
+            let owner_path = segments[..self.store_owner_len(&file, &segments, store_idx)].join(".");
+            targets.extend(self.manifest.find(&file, &owner_path));
+        }
+
+        let mut dropped = false;
+        for idx in targets {
+            if let Some(sv_idx) = self.find_state_value(idx) {
+                self.state_keys[sv_idx] = 0;
+                self.state_vals[sv_idx] = Value::Null;
+                dropped = true;
+            }
+            if let (Some(frozen), Some(qualified)) = (self.frozen.as_mut(), self.manifest.key_path(idx)) {
+                frozen.remove(&qualified);
+            }
+        }
+        Ok(dropped)
+    }
+
+    /// Overrides the manifest's static value of the leaf `key` in memory (nothing is
+    /// written to files). The cached values of the leaf and its parent are dropped, so the parent's next
+    /// `_load` merges `value`; a value already in the parent's `_store` is returned as-is.
+    /// Returns false when `key` is not a static leaf.
+    pub fn set_static(&mut self, key: &str, value: &str) -> Result<bool, StateError> {
+        crate::fn_log!("State", "set_static", key);
+        let (_, _, key_idx) = self.locate(key)?;
+        if !self.manifest.set_static(key_idx, value) {
+            return Ok(false);
+        }
+        self.cache_drop(key_idx);
+        self.invalidate_static_owner(key)?;
+        Ok(true)
+    }
+
+    /// Reverts a `set_static` override to the manifest's value. Returns whether one existed.
+    pub fn clear_static(&mut self, key: &str) -> Result<bool, StateError> {
+        crate::fn_log!("State", "clear_static", key);
+        let (_, _, key_idx) = self.locate(key)?;
+        if !self.manifest.clear_static(key_idx) {
+            return Ok(false);
+        }
+        self.cache_drop(key_idx);
+        self.invalidate_static_owner(key)?;
+        Ok(true)
+    }
+
+    fn invalidate_static_owner(&mut self, key: &str) -> Result<(), StateError> {
+        let key = self.normalize_key(key);
+        match key.rsplit_once('.') {
+            Some((parent, _)) if parent.contains('.') => self.invalidate(parent, false).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like `get`, but ignores the instance cache: reads `_store` (then `_load` on a miss)
+    /// and replaces the cached value with the result.
+    pub fn get_fresh(&mut self, key: &str) -> Result<Option<Value>, StateError> {
+        crate::fn_log!("State", "get_fresh", key);
+        let (_, _, key_idx) = self.locate(key)?;
+        if let Some(sv_idx) = self.find_state_value(key_idx) {
+            self.state_keys[sv_idx] = 0;
+            self.state_vals[sv_idx] = Value::Null;
+        }
+        if let Some(frozen) = self.frozen.as_mut() {
+            frozen.remove(key);
+        }
+        self.get(key)
+    }
+
+    /// Drops the slots left behind by `delete`/`get_fresh` from the instance cache.
+    /// Entries are looked up by key_idx, so the remaining values stay findable.
+    /// Returns the number of slots reclaimed.
+    pub fn compact_cache(&mut self) -> usize {
+        let before = self.state_keys.len();
+        let mut i = 1;
+        while i < self.state_keys.len() {
+            if self.state_keys[i] == 0 {
+                self.state_keys.swap_remove(i);
+                self.state_vals.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        before - self.state_keys.len()
+    }
+
+    /// Returns the value for `key` if present (cache → _store → _load); otherwise calls `f`,
+    /// writes its result through `set` and returns it. `f` is not called when a load succeeds.
+    /// A `_load` that finds nothing counts as a miss; other errors are returned as-is.
+    pub fn get_or_set<F: FnOnce() -> Value>(&mut self, key: &str, f: F, ttl: Option<u64>) -> Result<Value, StateError> {
+        crate::fn_log!("State", "get_or_set", key);
+        match self.get(key) {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) | Err(StateError::LoadFailed(LoadError::NotFound(_))) => {}
+            Err(e) => return Err(e),
+        }
+        let value = f();
+        self.set(key, value.clone(), ttl)?;
+        Ok(value)
+    }
+
+    /// Runs `f` with reads frozen: the first successful `get` of each key inside `f` is
+    /// kept, and later `get`s of that key return it even if the value was changed by a
+    /// `set`/`delete` or in the backing store meanwhile. Nested calls share the outer snapshot.
+    /// The snapshot is dropped even if `f` panics.
+    pub fn read_snapshot<R>(&mut self, f: impl FnOnce(&mut State) -> R) -> R {
+        if self.frozen.is_some() {
+            return f(self);
+        }
+        self.frozen = Some(HashMap::new());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(self)));
+        self.frozen = None;
+        match result {
+            Ok(r) => r,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+
+    /// Runs `f`, undoing its writes when it returns `Err` or panics: the instance cache
+    /// is restored and every `_store` key written by `set`/`set_if_absent`/`delete`/
+    /// `delete_prefix`/`compare_and_set` or by a `get` that wrote a loaded value through
+    /// gets its prior value back (or is deleted), newest first. Restored
+    /// values are written without a TTL. Compensation is best effort and not isolated from
+    /// other writers. Nested calls join the outer transaction.
+    pub fn transaction<F, T>(&mut self, f: F) -> Result<T, StateError>
+    where
+        F: FnOnce(&mut State) -> Result<T, StateError>,
+    {
+        if self.journal.is_some() {
+            return f(self);
+        }
+        let keys = self.state_keys.clone();
+        let vals = self.state_vals.clone();
+        let cached_at = self.cached_at.clone();
+        self.journal = Some(Vec::new());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(self)));
+        let journal = self.journal.take().unwrap_or_default();
+        let result = match result {
+            Ok(Ok(value)) => return Ok(value),
+            other => other,
+        };
+
+        crate::fn_log!("State", "transaction", "rollback");
+        for (config, prior) in journal.into_iter().rev() {
+            let _ = match prior {
+                Some(value) => self.store.set(&config, value, None),
+                None => self.store.delete(&config),
+            };
+        }
+        self.state_keys = keys;
+        self.state_vals = vals;
+        self.cached_at = cached_at;
+        match result {
+            Ok(r) => r,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+
+    /// Records the current `_store` value behind `config` when inside `transaction`.
+    fn journal_write(&mut self, config: &HashMap<String, Value>) {
+        if self.journal.is_some() {
+            let prior = self.store.get(config);
+            if let Some(journal) = self.journal.as_mut() {
+                journal.push((config.clone(), prior));
+            }
+        }
+    }
+
+    /// Runs `get` for each key so later lookups are served from the instance cache.
+    /// Keys already cached are skipped; errors and misses are ignored. Returns the
+    /// number of keys newly cached.
+    pub fn prefetch(&mut self, keys: &[&str]) -> usize {
+        let mut count = 0;
+        for key in keys {
+            let Ok((_, _, key_idx)) = self.locate(key) else { continue };
+            if self.find_fresh_state_value(key_idx).is_some() {
+                continue;
+            }
+            if let Ok(Some(_)) = self.get(key) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Returns the `get` outcome counters accumulated since creation or `reset_metrics`.
+    pub fn metrics(&self) -> StateMetrics {
+        self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = StateMetrics::default();
+    }
+
+    /// Returns every key passed to `get`/`set`/`delete`/`exists` since creation or
+    /// `clear_touched`, deduplicated in order of first touch. Includes keys read while
+    /// resolving placeholders.
+    pub fn touched_keys(&self) -> &[String] {
+        &self.touched_keys
+    }
+
+    pub fn clear_touched(&mut self) {
+        self.touched_keys.clear();
+    }
+
+    /// Returns every key for which `get` returned `Ok(None)` or `exists` returned `Ok(false)`
+    /// since creation or `clear_missing_keys`, deduplicated in order. Includes placeholder
+    /// lookups that missed; errors (e.g. `KeyNotFound`) are not recorded.
+    pub fn missing_keys(&self) -> &[String] {
+        &self.missing_keys
+    }
+
+    pub fn clear_missing_keys(&mut self) {
+        self.missing_keys.clear();
+    }
+
+    fn record_missing(&mut self, key: &str) {
+        let key = self.normalize_key(key);
+        if !self.missing_keys.contains(&key) {
+            self.missing_keys.push(key);
+        }
+    }
+
+    fn touch(&mut self, key: &str) {
+        let key = self.normalize_key(key);
+        if !self.touched_keys.contains(&key) {
+            self.touched_keys.push(key);
+        }
+    }
+
+    /// Starts recording every public `get`/`set`/`delete` call (not the lookups they
+    /// trigger internally). See `oplog()` and `crate::oplog::replay`.
+    pub fn enable_oplog(&mut self) {
+        if self.oplog.is_none() {
+            self.oplog = Some(Vec::new());
+        }
+    }
+
+    /// When enabled, `set`/`delete` update the instance cache but skip the `_store`
+    /// client; each skipped write is recorded in `dry_run_log()`. `get`/`exists` are unchanged.
+    pub fn with_dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = if enabled { Some(Vec::new()) } else { None };
+        self
+    }
+
+    /// Returns the writes skipped since `with_dry_run(true)`; empty if not enabled.
+    pub fn dry_run_log(&self) -> &[DryRunWrite] {
+        self.dry_run.as_deref().unwrap_or(&[])
+    }
+
+    fn record_dry_run(&mut self, op: &str, store_idx: u16, config: &HashMap<String, Value>, value: Option<Value>) {
+        let store_key = match config.get("key").or_else(|| config.get("url")) {
+            Some(Value::Scalar(b)) => String::from_utf8_lossy(b).into_owned(),
+            _ => String::new(),
+        };
+        let client = codec::client_decode(self.manifest.get_client(store_idx))
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .unwrap_or_default();
+        if let Some(log) = self.dry_run.as_mut() {
+            log.push(DryRunWrite { op: op.to_string(), store_key, client, value });
+        }
+    }
+
+    /// Returns the operations recorded since `enable_oplog`; empty if not enabled.
+    pub fn oplog(&self) -> &[Op] {
+        self.oplog.as_deref().unwrap_or(&[])
+    }
+
+    /// Loads manifest `file` and returns advisory findings for `_load`/`_store`
+    /// combinations that `get` can never use (see `Manifest::lint`). Empty when clean.
+    pub fn lint_manifest(&mut self, file: &str) -> Result<Vec<String>, StateError> {
+        self.load_manifest(file).map_err(|e| StateError::ManifestLoadFailed(e.to_string()))?;
+        Ok(self.manifest.lint(file))
+    }
+
+    /// Shows the absolute path a placeholder written under `context_key` resolves to
+    /// (e.g. `"${org_id}"` under `cache.user.tenant_id` → `"cache.user.org_id"`), for
+    /// debugging placeholders that miss. See `Manifest::resolve_key`.
+    pub fn resolve_key(&mut self, context_key: &str, placeholder: &str) -> Result<String, StateError> {
+        let (file, path, _) = self.locate(context_key)?;
+        let context = if path.is_empty() { file } else { format!("{}.{}", file, path) };
+        Ok(self.manifest.resolve_key(&context, placeholder))
+    }
+
+    /// Builds a JSON Schema (as a `Value::Mapping`) from the `_state.type` declarations
+    /// under `key` (a file or `file.path`). Nodes with field children become `object`s
+    /// with `properties`; `integer`/`string`/`float`/`boolean` map to `integer`/`string`/
+    /// `number`/`boolean`, `datetime` to a `date-time` string; untyped leaves accept
+    /// anything. Meta keys are skipped and no property is `required`.
+    pub fn json_schema(&mut self, key: &str) -> Result<Value, StateError> {
+        let (_, _, key_idx) = self.locate(key)?;
+        Ok(self.schema_of(key_idx))
+    }
+
+    fn schema_of(&self, key_idx: u16) -> Value {
+        let s = |v: &str| Value::Scalar(v.as_bytes().to_vec());
+        let children = self.manifest.field_children(key_idx);
+        if !children.is_empty() {
+            let properties = children.into_iter()
+                .map(|(name, idx)| (name.into_bytes(), self.schema_of(idx)))
+                .collect();
+            return Value::Mapping(vec![
+                (b"type".to_vec(), s("object")),
+                (b"properties".to_vec(), Value::Mapping(properties)),
+            ]);
+        }
+        match self.manifest.get_type(key_idx) {
+            Some(b"integer") => Value::Mapping(vec![(b"type".to_vec(), s("integer"))]),
+            Some(b"float") => Value::Mapping(vec![(b"type".to_vec(), s("number"))]),
+            Some(b"boolean") => Value::Mapping(vec![(b"type".to_vec(), s("boolean"))]),
+            Some(b"datetime") => Value::Mapping(vec![
+                (b"type".to_vec(), s("string")),
+                (b"format".to_vec(), s("date-time")),
+            ]),
+            Some(_) => Value::Mapping(vec![(b"type".to_vec(), s("string"))]),
+            None => Value::Mapping(vec![]),
+        }
+    }
+
+    /// Returns the `_state.type` in effect for `key` (e.g. `"integer"`): its own, else the
+    /// nearest ancestor's. `None` when none is declared. Reads the manifest only; no store or load client is called.
+    pub fn get_type(&mut self, key: &str) -> Result<Option<String>, StateError> {
+        let (_, _, key_idx) = self.locate(key)?;
+        Ok(self.manifest.get_type(key_idx).map(|t| String::from_utf8_lossy(t).into_owned()))
+    }
+
+    /// Installs `load` in place of the current Load (e.g. a fresh DB client after a
+    /// failover) and returns the old one. The instance cache and parsed manifests are
+    /// kept. Clients, transforms and the warning handler all come from `load`. State
+    /// calls are synchronous, so no load is in flight while this runs.
+    pub fn replace_load(&mut self, load: Load) -> Load {
+        std::mem::replace(&mut self.load, load)
+    }
+
+    /// Returns the `_store.client` name (e.g. `"KVS"`, `"InMemory"`) in effect for `key`,
+    /// after meta inheritance. Returns `None` if the key has no `_store`.
+    /// Reads the manifest only; no store or load client is called.
+    pub fn store_client_of(&mut self, key: &str) -> Result<Option<String>, StateError> {
+        let (file, path, _) = self.locate(key)?;
+        let meta = self.manifest.get_meta(&file, &path);
+        Ok(meta.store
+            .and_then(|idx| codec::client_decode(self.manifest.get_client(idx)))
+            .map(|name| String::from_utf8_lossy(name).into_owned()))
+    }
+
+    /// Returns the `_load`, `_store` or `_state` block in effect for `key` (after meta
+    /// inheritance) as a `Mapping`, without resolving anything: placeholders are rendered
+    /// qualified (`${cache.user.org_id}`), `map` keys as stored, and a `_load` fallback
+    /// chain as nested `fallback` mappings. `Ok(None)` when `key` has no such block or
+    /// `block` is not one of the three.
+    pub fn meta_block(&mut self, key: &str, block: &str) -> Result<Option<Value>, StateError> {
+        let (file, path, _) = self.locate(key)?;
+        let meta = self.manifest.get_meta(&file, &path);
+        Ok(match block {
+            "_load" => meta.load.map(|idx| self.render_meta(idx, true)),
+            "_store" => meta.store.map(|idx| self.render_meta(idx, false)),
+            "_state" => meta.state.map(|idx| Value::Mapping(
+                self.manifest.state_type(idx)
+                    .map(|t| (b"type".to_vec(), Value::Scalar(t.to_vec())))
+                    .into_iter()
+                    .collect()
+            )),
+            _ => None,
+        })
+    }
+
+    fn render_meta(&self, meta_idx: u16, with_fallback: bool) -> Value {
+        let (open, close) = self.delimiters();
+        fn render(cv: ConfigValue, open: &str, close: &str) -> Value {
+            let s = |v: String| Value::Scalar(v.into_bytes());
+            match cv {
+                ConfigValue::Str(v) => s(v),
+                ConfigValue::Placeholder(p) => s(format!("{}{}{}", open, p, close)),
+                ConfigValue::Map(pairs) => Value::Mapping(pairs.into_iter().map(|(k, v)| (k.into_bytes(), s(v))).collect()),
+                ConfigValue::Client(c) => s(codec::client_decode(c).map(|n| String::from_utf8_lossy(n).into_owned()).unwrap_or_default()),
+                ConfigValue::Params(params) => Value::Mapping(params.into_iter().map(|(k, v)| (k.into_bytes(), render(v, open, close))).collect()),
+            }
+        }
+        let mut entries = self.manifest.build_config(meta_idx).unwrap_or_default();
+        let custom_name = entries.iter().position(|(k, _)| k == "client_name")
+            .map(|i| render(entries.remove(i).1, &open, &close));
+        let mut fields: Vec<(Vec<u8>, Value)> = entries.into_iter()
+            .map(|(k, cv)| {
+                let value = match (k.as_str(), &custom_name) {
+                    ("client", Some(name)) => name.clone(),
+                    _ => render(cv, &open, &close),
+                };
+                (k.into_bytes(), value)
+            })
+            .collect();
+        if with_fallback && let Some(next) = self.manifest.load_fallback(meta_idx) {
+            fields.push((b"fallback".to_vec(), self.render_meta(next, true)));
+        }
+        Value::Mapping(fields)
+    }
+
+    /// Returns a copy of every value cached in this instance, as a `Mapping` keyed by
+    /// qualified key (`file.path`), sorted by key. No load or store client is called.
+    pub fn snapshot(&self) -> Value {
+        let mut entries: Vec<(Vec<u8>, Value)> = self.state_keys.iter()
+            .zip(self.state_vals.iter())
+            .filter(|(k, _)| **k != 0)
+            .filter_map(|(k, v)| self.manifest.key_path(*k).map(|p| (p.into_bytes(), v.clone())))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Value::Mapping(entries)
+    }
+
+    /// Renders `snapshot()` as YAML for debugging.
+    pub fn snapshot_pretty(&self) -> String {
+        serde_yaml_ng::to_string(&value_to_yaml(self.snapshot())).unwrap_or_default()
+    }
+
+    /// Stable 64-bit hash of the cached values (as in `snapshot()`), optionally limited
+    /// to `prefix` and the keys below it. Equal content gives equal fingerprints
+    /// across instances and runs, so it can serve as an ETag. Mapping fields are
+    /// hashed in key order at every depth, so their stored order does not matter.
+    pub fn fingerprint(&self, prefix: Option<&str>) -> u64 {
+        let mut h = Fnv1a::new();
+        if let Value::Mapping(entries) = self.snapshot() {
+            for (path, value) in &entries {
+                if let Some(p) = prefix {
+                    let p = p.as_bytes();
+                    let under = path.starts_with(p) && (path.len() == p.len() || path[p.len()] == b'.');
+                    if !under {
+                        continue;
+                    }
+                }
+                h.bytes(path);
+                h.value(value);
+            }
+        }
+        h.0
+    }
+
+    /// Loads the manifest file for `key` and returns (file, path, key_idx).
+    fn locate(&mut self, key: &str) -> Result<(String, String, u16), StateError> {
+        let key = &self.normalize_key(key);
+        let (file, path) = split_key(key);
+        let file = file.to_string();
+        let path = path.to_string();
+
+        if let Err(e) = self.load_manifest(&file) {
+            return Err(StateError::ManifestLoadFailed(e.to_string()));
+        }
+
+        match self.manifest.find(&file, &path) {
+            Some(idx) => Ok((file, path, idx)),
+            None => Err(StateError::KeyNotFound(key.to_string())),
+        }
+    }
+}
+
+/// FNV-1a over a length-prefixed encoding of `Value`, used by `State::fingerprint`.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn bytes(&mut self, b: &[u8]) {
+        self.write(&(b.len() as u64).to_le_bytes());
+        self.write(b);
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.write(&[0]),
+            Value::Scalar(b) => {
+                self.write(&[1]);
+                self.bytes(b);
+            }
+            Value::Sequence(items) => {
+                self.write(&[2]);
+                self.write(&(items.len() as u64).to_le_bytes());
+                for item in items {
+                    self.value(item);
+                }
+            }
+            Value::Mapping(fields) => {
+                self.write(&[3]);
+                self.write(&(fields.len() as u64).to_le_bytes());
+                // field order carries no meaning, so hash in key order at every depth
+                let mut sorted: Vec<&(Vec<u8>, Value)> = fields.iter().collect();
+                sorted.sort_by(|a, b| a.0.cmp(&b.0));
+                for (k, v) in sorted {
+                    self.bytes(k);
+                    self.value(v);
+                }
+            }
+        }
+    }
+}
+
+fn parse_yaml_source(content: &[u8]) -> Result<serde_yaml_ng::Value, ManifestError> {
+    let content_str = std::str::from_utf8(content)
+        .map_err(|e| ManifestError::ParseError(format!("UTF-8 error: {}", e)))?;
+
+    // Multi-document files (`---`) are deep-merged in order; later documents win.
+    let mut merged = serde_yaml_ng::Value::Null;
+    for document in serde_yaml_ng::Deserializer::from_str(content_str) {
+        let doc = <serde_yaml_ng::Value as serde::Deserialize>::deserialize(document)
+            .map_err(|e| ManifestError::ParseError(format!("YAML parse error: {}", e)))?;
+        match merged {
+            serde_yaml_ng::Value::Null => merged = doc,
+            _ => merge_yaml(&mut merged, doc),
+        }
+    }
+    Ok(merged)
+}
+
+/// Stops the polling thread started by `State::watch_manifests` when dropped.
+#[cfg(feature = "watch")]
+pub struct ManifestWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "watch")]
+impl Drop for ManifestWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Modification time and length of each `.yml`/`.yaml` file in `dir`.
+#[cfg(feature = "watch")]
+fn manifest_mtimes(dir: &std::path::Path) -> Vec<(PathBuf, Option<std::time::SystemTime>, u64)> {
+    let mut files: Vec<_> = std::fs::read_dir(dir).into_iter().flatten().flatten()
+        .map(|entry| entry.path())
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yml" | "yaml")))
+        .map(|p| {
+            let meta = std::fs::metadata(&p).ok();
+            let modified = meta.as_ref().and_then(|m| m.modified().ok());
+            let len = meta.map_or(0, |m| m.len());
+            (p, modified, len)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Deep-merges `overlay` into `base`: mappings merge key by key, anything else is replaced.
+fn merge_yaml(base: &mut serde_yaml_ng::Value, overlay: serde_yaml_ng::Value) {
+    match (base, overlay) {
+        (serde_yaml_ng::Value::Mapping(base_map), serde_yaml_ng::Value::Mapping(overlay_map)) => {
+            for (k, v) in overlay_map {
+                match base_map.get_mut(&k) {
+                    Some(existing) => merge_yaml(existing, v),
+                    None => { base_map.insert(k, v); }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Replaces `${env:NAME}` tokens in every string of `yaml` (keys are not touched),
+/// reading each `NAME` through `lookup`.
+fn interpolate_env(
+    yaml: &mut serde_yaml_ng::Value,
+    mode: EnvInterpolation,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), ManifestError> {
+    match yaml {
+        serde_yaml_ng::Value::String(s) if s.contains("${env:") => {
+            let mut result = String::new();
+            let mut remaining = s.as_str();
+            while let Some(start) = remaining.find("${env:") {
+                result.push_str(&remaining[..start]);
+                let token = &remaining[start..];
+                let end = match token.find('}') {
+                    Some(e) => e,
+                    None => break,
+                };
+                let name = &token[6..end];
+                match lookup(name) {
+                    Some(v) => result.push_str(&v),
+                    None if mode == EnvInterpolation::KeepMissing => result.push_str(&token[..=end]),
+                    None => return Err(ManifestError::ParseError(
+                        format!("environment variable '{}' is not set", name)
+                    )),
+                }
+                remaining = &token[end + 1..];
+            }
+            result.push_str(remaining);
+            *s = result;
+        }
+        serde_yaml_ng::Value::Mapping(m) => {
+            for (_, v) in m.iter_mut() {
+                interpolate_env(v, mode, lookup)?;
+            }
+        }
+        serde_yaml_ng::Value::Sequence(items) => {
+            for v in items {
+                interpolate_env(v, mode, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_manifest_yaml(file: &str, yaml_root: serde_yaml_ng::Value, manifest: &mut Manifest) -> Result<(), ManifestError> {
+    check_yaml_depth(&yaml_root, manifest.options.max_depth)?;
+    let pm = parse(
+        file,
+        yaml_to_parse_value(yaml_root)?,
+        Pools {
+            dynamic: &mut manifest.dynamic,
+            keys: &mut manifest.keys,
+            values: &mut manifest.values,
+            path_map: &mut manifest.path_map,
+            children_map: &mut manifest.children_map,
+        },
+        &manifest.options,
+    ).map_err(ManifestError::ParseError)?;
+
+    manifest.insert(file.to_string(), pm);
+    Ok(())
+}
+
+fn value_to_yaml(v: Value) -> serde_yaml_ng::Value {
+    match v {
+        Value::Null => serde_yaml_ng::Value::Null,
+        Value::Scalar(b) => serde_yaml_ng::Value::String(String::from_utf8_lossy(&b).into_owned()),
+        Value::Sequence(items) => serde_yaml_ng::Value::Sequence(items.into_iter().map(value_to_yaml).collect()),
+        Value::Mapping(fields) => serde_yaml_ng::Value::Mapping(
+            fields.into_iter()
+                .map(|(k, v)| (serde_yaml_ng::Value::String(String::from_utf8_lossy(&k).into_owned()), value_to_yaml(v)))
                 .collect(),
         ),
+    }
+}
+
+/// Returns true if `field` (path segments) leads to a non-null value inside `value`.
+fn value_has_field(value: &Value, field: &[&str]) -> bool {
+    let mut current = value;
+    for segment in field {
+        let Value::Mapping(fields) = current else { return false };
+        match fields.iter().find(|(k, _)| k.as_slice() == segment.as_bytes()) {
+            Some((_, v)) => current = v,
+            None => return false,
+        }
+    }
+    !matches!(current, Value::Null)
+}
+
+/// Like the parser's depth check, for the YAML tree before `yaml_to_parse_value`
+/// recurses into it. Iterative, so a tree grown through `_include` cannot overflow here.
+fn check_yaml_depth(root: &serde_yaml_ng::Value, max_depth: usize) -> Result<(), ManifestError> {
+    let mut stack = vec![(root, 1)];
+    while let Some((value, depth)) = stack.pop() {
+        let children: Vec<&serde_yaml_ng::Value> = match value {
+            serde_yaml_ng::Value::Mapping(m) => m.values().collect(),
+            serde_yaml_ng::Value::Sequence(items) => items.iter().collect(),
+            _ => continue,
+        };
+        if depth > max_depth {
+            return Err(ManifestError::ParseError(format!("nesting too deep (max {})", max_depth)));
+        }
+        stack.extend(children.into_iter().map(|v| (v, depth + 1)));
+    }
+    Ok(())
+}
+
+/// Converts a YAML node to the parser input. Numbers keep their YAML text form
+/// (u64 above i64::MAX and floats such as 0.1 stay exact); non-finite floats are rejected.
+fn yaml_to_parse_value(v: serde_yaml_ng::Value) -> Result<ParseValue, ManifestError> {
+    Ok(match v {
+        serde_yaml_ng::Value::Mapping(m) => {
+            let mut fields = Vec::with_capacity(m.len());
+            for (k, v) in m {
+                fields.push((yaml_key_bytes(k)?, yaml_to_parse_value(v)?));
+            }
+            ParseValue::Mapping(fields)
+        }
         serde_yaml_ng::Value::Sequence(s) => ParseValue::Sequence(
-            s.into_iter().map(yaml_to_parse_value).collect()
+            s.into_iter().map(yaml_to_parse_value).collect::<Result<_, _>>()?
         ),
         serde_yaml_ng::Value::String(s) => ParseValue::Scalar(s.into_bytes()),
-        serde_yaml_ng::Value::Number(n) => ParseValue::Scalar(n.to_string().into_bytes()),
+        serde_yaml_ng::Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                ParseValue::Scalar(u.to_string().into_bytes())
+            } else if let Some(i) = n.as_i64() {
+                ParseValue::Scalar(i.to_string().into_bytes())
+            } else {
+                match n.as_f64() {
+                    Some(f) if f.is_finite() => ParseValue::Scalar(n.to_string().into_bytes()),
+                    _ => return Err(ManifestError::ParseError(format!("non-finite number '{}'", n))),
+                }
+            }
+        }
         serde_yaml_ng::Value::Bool(b)   => ParseValue::Scalar(b.to_string().into_bytes()),
         serde_yaml_ng::Value::Null      => ParseValue::Null,
         _                               => ParseValue::Null,
+    })
+}
+
+/// Span outcome for `set`/`delete` results.
+fn outcome_of(result: &Result<bool, StateError>) -> &'static str {
+    match result {
+        Ok(true) => "ok",
+        Ok(false) => "noop",
+        Err(_) => "err",
+    }
+}
+
+/// Mapping keys as bytes. Numbers and bools are stringified as written (`200`, `true`)
+/// and a null key becomes `null`; sequence/mapping keys are rejected.
+fn yaml_key_bytes(k: serde_yaml_ng::Value) -> Result<Vec<u8>, ManifestError> {
+    match k {
+        serde_yaml_ng::Value::String(s) => Ok(s.into_bytes()),
+        serde_yaml_ng::Value::Null => Ok(b"null".to_vec()),
+        k @ (serde_yaml_ng::Value::Number(_) | serde_yaml_ng::Value::Bool(_)) => match yaml_to_parse_value(k)? {
+            ParseValue::Scalar(b) => Ok(b),
+            _ => Err(ManifestError::ParseError("unsupported mapping key".into())),
+        },
+        other => Err(ManifestError::ParseError(format!(
+            "unsupported mapping key {}", serde_yaml_ng::to_string(&other).unwrap_or_default().trim_end()
+        ))),
     }
 }
 
@@ -592,6 +2321,2149 @@ mod tests {
         fn delete(&self, _: &str, _: Option<&[(Vec<u8>, Vec<u8>)]>) -> bool { false }
     }
 
+    struct MapFile {
+        files: std::sync::Mutex<HashMap<String, Vec<u8>>>,
+    }
+    impl MapFile {
+        fn new(entries: &[(&str, &str)]) -> Self {
+            Self { files: std::sync::Mutex::new(
+                entries.iter().map(|(k, v)| (k.to_string(), v.as_bytes().to_vec())).collect()
+            ) }
+        }
+    }
+    impl FileClient for MapFile {
+        fn get(&self, key: &str) -> Option<Vec<u8>> { self.files.lock().unwrap().get(key).cloned() }
+        fn set(&self, key: &str, value: Vec<u8>) -> bool { self.files.lock().unwrap().insert(key.to_string(), value); true }
+        fn delete(&self, key: &str) -> bool { self.files.lock().unwrap().remove(key).is_some() }
+    }
+
+    struct MockInMemory {
+        data: std::sync::Mutex<HashMap<String, Value>>,
+    }
+    impl MockInMemory {
+        fn new() -> Self { Self { data: Default::default() } }
+    }
+    impl crate::ports::required::InMemoryClient for MockInMemory {
+        fn get(&self, key: &str) -> Option<Value> { self.data.lock().unwrap().get(key).cloned() }
+        fn set(&self, key: &str, value: Value) -> bool { self.data.lock().unwrap().insert(key.to_string(), value); true }
+        fn delete(&self, key: &str) -> bool { self.data.lock().unwrap().remove(key).is_some() }
+    }
+
+    const SESSION_YML: &str = "
+sso_user_id:
+  _store:
+    client: InMemory
+    key: \"user-key\"
+";
+
+    #[test]
+    fn test_precompiled_manifest_is_used() {
+        let source = State::new("m").with_manifest_file(MapFile::new(&[("m/session.yml", SESSION_YML)]));
+        let bin = source.precompile_manifest("session").unwrap();
+
+        let file = MapFile::new(&[]);
+        file.set("m/session.bin", bin);
+        let mut state = State::new("m")
+            .with_manifest_file(file)
+            .with_in_memory(Arc::new(MockInMemory::new()));
+        assert!(state.set("session.sso_user_id", Value::Scalar(b"7".to_vec()), None).unwrap());
+        assert_eq!(state.get("session.sso_user_id").unwrap(), Some(Value::Scalar(b"7".to_vec())));
+    }
+
+    #[test]
+    fn test_precompiled_manifest_for_other_file_is_rejected() {
+        let source = State::new("m").with_manifest_file(MapFile::new(&[("m/session.yml", SESSION_YML)]));
+        let bin = source.precompile_manifest("session").unwrap();
+
+        let file = MapFile::new(&[]);
+        file.set("m/cache.bin", bin);
+        let mut state = State::new("m").with_manifest_file(file);
+        assert!(matches!(state.get("cache.user"), Err(StateError::ManifestLoadFailed(_))));
+    }
+
+    #[test]
+    fn test_precompiled_manifest_refused_with_overlay_or_env_interpolation() {
+        let source = State::new("m").with_manifest_file(MapFile::new(&[("m/session.yml", SESSION_YML)]));
+        let bin = source.precompile_manifest("session").unwrap();
+
+        let file = MapFile::new(&[("m/session.yml", SESSION_YML)]);
+        file.set("m/session.bin", bin);
+        let mut state = State::new("m").with_manifest_file(file).with_manifest_overlay("prod");
+        match state.get("session.sso_user_id") {
+            Err(StateError::ManifestLoadFailed(msg)) => assert!(msg.contains("session.bin"), "{}", msg),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    const STORES_YML: &str = "
+user:
+  _store:
+    client: KVS
+    key: \"user\"
+  id:
+    _state:
+      type: integer
+conn:
+  _store:
+    client: InMemory
+    key: \"conn\"
+  host:
+plain:
+  value:
+";
+
+    fn stores_state() -> State {
+        State::new("m").with_manifest_file(MapFile::new(&[("m/stores.yml", STORES_YML)]))
+    }
+
+    #[test]
+    fn test_store_client_of() {
+        let mut state = stores_state();
+        assert_eq!(state.store_client_of("stores.user.id").unwrap(), Some("KVS".to_string()));
+        assert_eq!(state.store_client_of("stores.conn.host").unwrap(), Some("InMemory".to_string()));
+        assert_eq!(state.store_client_of("stores.plain.value").unwrap(), None);
+        assert!(matches!(state.store_client_of("stores.missing"), Err(StateError::KeyNotFound(_))));
+    }
+
+    #[test]
+    fn test_snapshot_lists_cached_keys() {
+        let file = MapFile::new(&[("m/session.yml", SESSION_YML), ("m/stores.yml", STORES_YML)]);
+        let mut state = State::new("m")
+            .with_manifest_file(file)
+            .with_in_memory(Arc::new(MockInMemory::new()));
+        assert_eq!(state.snapshot(), Value::Mapping(vec![]));
+
+        state.set("session.sso_user_id", Value::Scalar(b"7".to_vec()), None).unwrap();
+        state.set("stores.conn", Value::Mapping(vec![(b"host".to_vec(), Value::Scalar(b"db".to_vec()))]), None).unwrap();
+
+        let Value::Mapping(entries) = state.snapshot() else { panic!("expected mapping") };
+        let keys: Vec<&[u8]> = entries.iter().map(|(k, _)| k.as_slice()).collect();
+        assert_eq!(keys, vec![b"session.sso_user_id".as_slice(), b"stores.conn".as_slice()]);
+        assert_eq!(entries[0].1, Value::Scalar(b"7".to_vec()));
+        assert!(state.snapshot_pretty().contains("session.sso_user_id: '7'"));
+    }
+
+    #[test]
+    fn test_insert_manifest_source() {
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(Arc::new(MockInMemory::new()));
+        state.insert_manifest_source("stores", STORES_YML).unwrap();
+
+        assert_eq!(state.store_client_of("stores.conn.host").unwrap(), Some("InMemory".to_string()));
+        assert!(state.set("stores.conn", Value::Scalar(b"x".to_vec()), None).unwrap());
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"x".to_vec())));
+        assert!(matches!(
+            state.insert_manifest_source("broken", "a: [1"),
+            Err(ManifestError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_or_set_cache_hit() {
+        let mut state = stores_state().with_in_memory(Arc::new(MockInMemory::new()));
+        state.set("stores.conn", Value::Scalar(b"cached".to_vec()), None).unwrap();
+        let v = state.get_or_set("stores.conn", || panic!("must not compute"), None).unwrap();
+        assert_eq!(v, Value::Scalar(b"cached".to_vec()));
+    }
+
+    #[test]
+    fn test_get_or_set_store_hit() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"stored".to_vec()));
+        let mut state = stores_state().with_in_memory(client);
+        let v = state.get_or_set("stores.conn", || panic!("must not compute"), None).unwrap();
+        assert_eq!(v, Value::Scalar(b"stored".to_vec()));
+    }
+
+    #[test]
+    fn test_get_or_set_computes_on_miss() {
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client.clone());
+        let mut calls = 0;
+        let v = state.get_or_set("stores.conn", || { calls += 1; Value::Scalar(b"new".to_vec()) }, None).unwrap();
+        assert_eq!(v, Value::Scalar(b"new".to_vec()));
+        assert_eq!(calls, 1);
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&Value::Scalar(b"new".to_vec())));
+        let again = state.get_or_set("stores.conn", || panic!("must not compute"), None).unwrap();
+        assert_eq!(again, v);
+    }
+
+    #[test]
+    fn test_get_merges_static_fields_into_loaded() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Mapping(vec![
+            (b"name".to_vec(), Value::Scalar(b"alice".to_vec())),
+            (b"role".to_vec(), Value::Scalar(b"admin".to_vec())),
+        ]));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(client);
+        state.insert_manifest_source("app", "
+profile:
+  _load:
+    client: InMemory
+    key: \"profile-src\"
+  name:
+  role: \"member\"
+  plan: \"free\"
+").unwrap();
+
+        let Some(Value::Mapping(fields)) = state.get("app.profile").unwrap() else { panic!("expected mapping") };
+        let field = |k: &[u8]| fields.iter().find(|(f, _)| f.as_slice() == k).map(|(_, v)| v.clone());
+        assert_eq!(field(b"name"), Some(Value::Scalar(b"alice".to_vec())));
+        assert_eq!(field(b"role"), Some(Value::Scalar(b"admin".to_vec())));
+        assert_eq!(field(b"plan"), Some(Value::Scalar(b"free".to_vec())));
+    }
+
+    #[test]
+    fn test_merged_static_field_resolves_template() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Mapping(vec![(b"name".to_vec(), Value::Scalar(b"alice".to_vec()))]));
+        client.data.lock().unwrap().insert("region".to_string(), Value::Scalar(b"eu".to_vec()));
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[])).with_in_memory(client);
+        state.insert_manifest_source("app", "
+region:
+  _store:
+    client: InMemory
+    key: \"region\"
+profile:
+  _load:
+    client: InMemory
+    key: \"profile-src\"
+  name:
+  home: \"https://${app.region}.example.com\"
+").unwrap();
+
+        let profile = state.get("app.profile").unwrap().unwrap();
+        assert_eq!(field(&profile, b"home"), Some(Value::Scalar(b"https://eu.example.com".to_vec())));
+    }
+
+    const CONNECTION_YML: &str = "
+db:
+  _load:
+    client: InMemory
+    key: \"db-src\"
+  host: \"localhost\"
+  port: \"5432\"
+";
+
+    const CONNECTION_PROD_YML: &str = "
+db:
+  _load:
+    key: \"db-prod-src\"
+  host: \"prod-db\"
+";
+
+    fn connection_client() -> Arc<MockInMemory> {
+        let client = Arc::new(MockInMemory::new());
+        let row = |user: &str| Value::Mapping(vec![(b"user".to_vec(), Value::Scalar(user.as_bytes().to_vec()))]);
+        client.data.lock().unwrap().insert("db-src".to_string(), row("dev"));
+        client.data.lock().unwrap().insert("db-prod-src".to_string(), row("prod"));
+        client
+    }
+
+    fn field(value: &Value, name: &[u8]) -> Option<Value> {
+        let Value::Mapping(fields) = value else { return None };
+        fields.iter().find(|(k, _)| k.as_slice() == name).map(|(_, v)| v.clone())
+    }
+
+    #[test]
+    fn test_manifest_overlay_merges_over_base() {
+        let file = MapFile::new(&[("m/connection.yml", CONNECTION_YML), ("m/connection.prod.yml", CONNECTION_PROD_YML)]);
+        let mut state = State::new("m")
+            .with_manifest_file(file)
+            .with_manifest_overlay("prod")
+            .with_in_memory(connection_client());
+
+        let db = state.get("connection.db").unwrap().unwrap();
+        assert_eq!(field(&db, b"host"), Some(Value::Scalar(b"prod-db".to_vec())));
+        assert_eq!(field(&db, b"port"), Some(Value::Scalar(b"5432".to_vec())));
+        assert_eq!(field(&db, b"user"), Some(Value::Scalar(b"prod".to_vec())));
+    }
+
+    #[test]
+    fn test_manifest_overlay_keeps_meta_key_order() {
+        let base = "
+user:
+  _load:
+    client: Db
+    table: \"users\"
+    where: \"active=1\"
+";
+        let overlay = "
+user:
+  _load:
+    multi: true
+    table: \"accounts\"
+";
+        let order = || {
+            let file = MapFile::new(&[("m/app.yml", base), ("m/app.prod.yml", overlay)]);
+            let mut state = State::new("m").with_manifest_file(file).with_manifest_overlay("prod");
+            state.load_manifest("app").unwrap();
+            let load_idx = state.manifest.get_meta("app", "user").load.unwrap();
+            state.manifest.build_config(load_idx).unwrap().into_iter().map(|(k, _)| k).collect::<Vec<_>>()
+        };
+        // overridden keys keep the base position; new keys are appended
+        assert_eq!(order(), vec!["client", "table", "where", "multi"]);
+        assert_eq!(order(), order());
+    }
+
+    #[test]
+    fn test_manifest_overlay_absent_keeps_base() {
+        let file = MapFile::new(&[("m/connection.yml", CONNECTION_YML), ("m/connection.prod.yml", CONNECTION_PROD_YML)]);
+        let mut state = State::new("m")
+            .with_manifest_file(file)
+            .with_manifest_overlay("staging")
+            .with_in_memory(connection_client());
+
+        let db = state.get("connection.db").unwrap().unwrap();
+        assert_eq!(field(&db, b"host"), Some(Value::Scalar(b"localhost".to_vec())));
+        assert_eq!(field(&db, b"user"), Some(Value::Scalar(b"dev".to_vec())));
+    }
+
+    #[test]
+    fn test_key_normalizer_resolves_mixed_case_key() {
+        let mut state = stores_state()
+            .with_in_memory(Arc::new(MockInMemory::new()))
+            .with_key_normalizer(|k| k.to_lowercase());
+        assert!(state.set("Stores.Conn", Value::Scalar(b"x".to_vec()), None).unwrap());
+        assert_eq!(state.get("STORES.conn").unwrap(), Some(Value::Scalar(b"x".to_vec())));
+        assert!(state.exists("stores.CONN").unwrap());
+        assert_eq!(state.store_client_of("Stores.Conn.Host").unwrap(), Some("InMemory".to_string()));
+        assert!(state.delete("Stores.Conn").unwrap());
+        assert!(!state.exists("stores.conn").unwrap());
+    }
+
+    #[test]
+    fn test_collapse_key_drops_empty_segments_and_whitespace() {
+        assert_eq!(State::collapse_key("a..b"), "a.b");
+        assert_eq!(State::collapse_key(".a.b."), "a.b");
+        assert_eq!(State::collapse_key(" a . b "), "a.b");
+        assert_eq!(State::collapse_key(". . a"), "a");
+        assert_eq!(State::collapse_key(&State::collapse_key(" a .. b ")), "a.b");
+
+        let mut state = stores_state()
+            .with_in_memory(Arc::new(MockInMemory::new()))
+            .with_key_normalizer(State::collapse_key);
+        assert!(state.set("stores..conn.", Value::Scalar(b"x".to_vec()), None).unwrap());
+        assert_eq!(state.get(" stores . conn ").unwrap(), Some(Value::Scalar(b"x".to_vec())));
+    }
+
+    struct RowsDb {
+        rows: Vec<Value>,
+    }
+    impl DbClient for RowsDb {
+        fn get(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> Option<Vec<Value>> { Some(self.rows.clone()) }
+        fn set(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> bool { false }
+        fn delete(&self, _: &Value, _: &str, _: Option<&[u8]>) -> bool { false }
+    }
+
+    #[test]
+    fn test_get_db_multi_returns_mapped_rows() {
+        let row = |id: &str, org: &str| Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(id.as_bytes().to_vec())),
+            (b"sso_org_id".to_vec(), Value::Scalar(org.as_bytes().to_vec())),
+        ]);
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("main-conn".to_string(), Value::Mapping(vec![]));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(client)
+            .with_db(Arc::new(RowsDb { rows: vec![row("1", "10"), row("2", "20")] }));
+        state.insert_manifest_source("orders", "
+conn:
+  _store:
+    client: InMemory
+    key: \"main-conn\"
+list:
+  _load:
+    client: Db
+    connection: ${orders.conn}
+    table: \"orders\"
+    multi: true
+    map:
+      id: \"id\"
+      org_id: \"sso_org_id\"
+").unwrap();
+
+        let Some(Value::Sequence(rows)) = state.get("orders.list").unwrap() else { panic!("expected sequence") };
+        assert_eq!(rows.len(), 2);
+        assert_eq!(field(&rows[1], b"org_id"), Some(Value::Scalar(b"20".to_vec())));
+    }
+
+    #[test]
+    fn test_manifest_key_normalization_trim() {
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_manifest_key_normalization(NormalizeMode::Trim);
+        state.insert_manifest_source("conn", "\"host \":\n  _store:\n    client: KVS\n    key: \"h\"\n").unwrap();
+        assert_eq!(state.store_client_of("conn.host").unwrap(), Some("KVS".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_template_dotted_placeholders() {
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/session.yml", SESSION_YML)]))
+            .with_in_memory(Arc::new(MockInMemory::new()));
+        state.insert_manifest_source("a", "b:\n  c:\n    _store:\n      client: InMemory\n      key: \"abc\"\n").unwrap();
+        state.set("session.sso_user_id", Value::Scalar(b"7".to_vec()), None).unwrap();
+        state.set("a.b.c", Value::Scalar(b"deep".to_vec()), None).unwrap();
+
+        assert_eq!(
+            state.resolve_template("u:${session.sso_user_id}/${a.b.c}").unwrap(),
+            Some("u:7/deep".to_string())
+        );
+        assert_eq!(state.resolve_template("cost: $5").unwrap(), Some("cost: $5".to_string()));
+    }
+
+    #[test]
+    fn test_oplog_records_and_replays() {
+        let file = MapFile::new(&[("m/session.yml", SESSION_YML), ("m/stores.yml", STORES_YML)]);
+        let mut state = State::new("m")
+            .with_manifest_file(file)
+            .with_in_memory(Arc::new(MockInMemory::new()));
+        state.enable_oplog();
+        state.set("session.sso_user_id", Value::Scalar(b"7".to_vec()), None).unwrap();
+        state.set("stores.conn", Value::Scalar(b"c".to_vec()), None).unwrap();
+        state.get("session.sso_user_id").unwrap();
+        state.delete("stores.conn").unwrap();
+
+        let ops = Op::decode_all(&Op::encode_all(state.oplog())).unwrap();
+        assert_eq!(ops, vec![
+            Op::Set { key: "session.sso_user_id".into(), value: Value::Scalar(b"7".to_vec()), ttl: None },
+            Op::Set { key: "stores.conn".into(), value: Value::Scalar(b"c".to_vec()), ttl: None },
+            Op::Get { key: "session.sso_user_id".into() },
+            Op::Delete { key: "stores.conn".into() },
+        ]);
+
+        let file = MapFile::new(&[("m/session.yml", SESSION_YML), ("m/stores.yml", STORES_YML)]);
+        let mut fresh = State::new("m")
+            .with_manifest_file(file)
+            .with_in_memory(Arc::new(MockInMemory::new()));
+        crate::oplog::replay(&ops, &mut fresh);
+        assert_eq!(fresh.snapshot(), state.snapshot());
+        assert!(fresh.oplog().is_empty());
+    }
+
+    #[test]
+    fn test_yaml_numbers_keep_precision() {
+        let yaml = serde_yaml_ng::from_str("big: 18446744073709551615\nneg: -9223372036854775808\nfrac: 0.1\n").unwrap();
+        let ParseValue::Mapping(fields) = yaml_to_parse_value(yaml).unwrap() else { panic!("expected mapping") };
+        assert_eq!(fields, vec![
+            (b"big".to_vec(), ParseValue::Scalar(b"18446744073709551615".to_vec())),
+            (b"neg".to_vec(), ParseValue::Scalar(b"-9223372036854775808".to_vec())),
+            (b"frac".to_vec(), ParseValue::Scalar(b"0.1".to_vec())),
+        ]);
+    }
+
+    #[test]
+    fn test_yaml_non_finite_number_is_rejected() {
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[]));
+        assert!(matches!(
+            state.insert_manifest_source("nums", "ratio: .inf\n"),
+            Err(ManifestError::ParseError(_))
+        ));
+    }
+
+    const DEEP_YML: &str = "
+conn:
+  _store:
+    client: InMemory
+    key: \"conn\"
+  host:
+  port:
+  user:
+";
+
+    #[test]
+    fn test_exists_deep_checks_child_field() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Mapping(vec![
+            (b"host".to_vec(), Value::Scalar(b"db".to_vec())),
+            (b"port".to_vec(), Value::Null),
+        ]));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/deep.yml", DEEP_YML)]))
+            .with_in_memory(client);
+
+        assert!(state.exists("deep.conn.user").unwrap());
+        assert!(state.exists_deep("deep.conn.host").unwrap());
+        assert!(!state.exists_deep("deep.conn.port").unwrap());
+        assert!(!state.exists_deep("deep.conn.user").unwrap());
+        assert!(state.exists_deep("deep.conn").unwrap());
+
+        // cached owner is used without a store read
+        state.get("deep.conn").unwrap();
+        state.set("deep.conn", Value::Mapping(vec![(b"user".to_vec(), Value::Scalar(b"u".to_vec()))]), None).unwrap();
+        assert!(state.exists_deep("deep.conn.user").unwrap());
+        assert!(!state.exists_deep("deep.conn.host").unwrap());
+    }
+
+    struct HashStore;
+    impl crate::ports::required::KVSClient for HashStore {
+        fn get(&self, _: &str) -> Option<Vec<u8>> { panic!("must use has_field") }
+        fn set(&self, _: &str, _: Vec<u8>, _: Option<u64>) -> bool { false }
+        fn delete(&self, _: &str) -> bool { false }
+        fn has_field(&self, key: &str, field: &str) -> Option<bool> { Some(key == "user" && field == "id") }
+    }
+
+    #[test]
+    fn test_exists_deep_uses_client_field_hook() {
+        let mut state = stores_state().with_kvs(Arc::new(HashStore));
+        assert!(state.exists_deep("stores.user.id").unwrap());
+    }
+
+    #[test]
+    fn test_exists_uses_kvs_exists_without_get() {
+        struct ExistsKVS;
+        impl KVSClient for ExistsKVS {
+            fn get(&self, _: &str) -> Option<Vec<u8>> { panic!("must use exists") }
+            fn set(&self, _: &str, _: Vec<u8>, _: Option<u64>) -> bool { false }
+            fn delete(&self, _: &str) -> bool { false }
+            fn exists(&self, key: &str) -> bool { key == "user" }
+        }
+        let mut state = stores_state()
+            .with_kvs(Arc::new(ExistsKVS))
+            .with_kvs_codec(crate::store::KvsCodec::Binary);
+        assert!(state.exists("stores.user").unwrap());
+    }
+
+    #[test]
+    fn test_read_snapshot_keeps_first_reads() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("user-key".to_string(), Value::Scalar(b"1".to_vec()));
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"a".to_vec()));
+        let file = MapFile::new(&[("m/session.yml", SESSION_YML), ("m/stores.yml", STORES_YML)]);
+        let mut state = State::new("m").with_manifest_file(file).with_in_memory(client.clone());
+
+        let (first, second) = state.read_snapshot(|s| {
+            let first = (s.get("session.sso_user_id").unwrap(), s.get("stores.conn").unwrap());
+            s.set("session.sso_user_id", Value::Scalar(b"2".to_vec()), None).unwrap();
+            s.delete("stores.conn").unwrap();
+            let second = (s.get("session.sso_user_id").unwrap(), s.get("stores.conn").unwrap());
+            (first, second)
+        });
+        assert_eq!(first, (Some(Value::Scalar(b"1".to_vec())), Some(Value::Scalar(b"a".to_vec()))));
+        assert_eq!(first, second);
+        assert_eq!(state.get("session.sso_user_id").unwrap(), Some(Value::Scalar(b"2".to_vec())));
+    }
+
+    #[test]
+    fn test_read_snapshot_unfreezes_after_panic() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"a".to_vec()));
+        let mut state = stores_state().with_in_memory(client.clone());
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            state.read_snapshot(|s| {
+                s.get("stores.conn").unwrap();
+                panic!("boom");
+            })
+        }));
+        assert!(panicked.is_err());
+        assert!(state.frozen.is_none());
+        state.set("stores.conn", Value::Scalar(b"b".to_vec()), None).unwrap();
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"b".to_vec())));
+    }
+
+    #[test]
+    fn test_metrics_count_get_outcomes() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"c".to_vec()));
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Mapping(vec![]));
+        let file = MapFile::new(&[("m/session.yml", SESSION_YML), ("m/stores.yml", STORES_YML)]);
+        let mut state = State::new("m").with_manifest_file(file).with_in_memory(client);
+        state.insert_manifest_source("app", "profile:\n  _load:\n    client: InMemory\n    key: \"profile-src\"\n").unwrap();
+
+        state.set("session.sso_user_id", Value::Scalar(b"7".to_vec()), None).unwrap();
+        state.get("session.sso_user_id").unwrap();
+        state.get("stores.conn").unwrap();
+        state.get("app.profile").unwrap();
+        state.get("stores.plain.value").unwrap();
+        assert_eq!(state.metrics(), StateMetrics { cache_hits: 1, store_hits: 1, loads: 1, misses: 1 });
+
+        state.reset_metrics();
+        assert_eq!(state.metrics(), StateMetrics::default());
+    }
+
+    #[test]
+    fn test_get_fresh_bypasses_stale_cache() {
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client.clone());
+        state.set("stores.conn", Value::Scalar(b"old".to_vec()), None).unwrap();
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"new".to_vec()));
+
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+        assert_eq!(state.get_fresh("stores.conn").unwrap(), Some(Value::Scalar(b"new".to_vec())));
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"new".to_vec())));
+    }
+
+    #[test]
+    fn test_multi_document_manifest_is_merged() {
+        let source = "
+conn:
+  _store:
+    client: InMemory
+    key: \"conn\"
+  host: \"localhost\"
+  port: \"5432\"
+---
+conn:
+  host: \"db.internal\"
+extra:
+  _store:
+    client: KVS
+    key: \"extra\"
+";
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/multi.yml", source)]));
+        assert_eq!(state.store_client_of("multi.conn").unwrap(), Some("InMemory".to_string()));
+        assert_eq!(state.store_client_of("multi.extra").unwrap(), Some("KVS".to_string()));
+        let conn = state.manifest.find("multi", "conn").unwrap();
+        assert_eq!(state.manifest.static_fields(conn), vec![
+            ("host".to_string(), "db.internal".to_string()),
+            ("port".to_string(), "5432".to_string()),
+        ]);
+    }
+
+    struct ConnDb {
+        connection: std::sync::Mutex<Option<Value>>,
+    }
+    impl DbClient for ConnDb {
+        fn get(&self, connection: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> Option<Vec<Value>> {
+            *self.connection.lock().unwrap() = Some(connection.clone());
+            Some(vec![Value::Mapping(vec![])])
+        }
+        fn set(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> bool { false }
+        fn delete(&self, _: &Value, _: &str, _: Option<&[u8]>) -> bool { false }
+    }
+
+    fn connection_state(connection: &str) -> (State, Arc<ConnDb>) {
+        let db = Arc::new(ConnDb { connection: Default::default() });
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("tenant".to_string(), Value::Mapping(vec![
+            (b"host".to_vec(), Value::Scalar(b"tenant-db".to_vec())),
+        ]));
+        client.data.lock().unwrap().insert("name".to_string(), Value::Scalar(b"plain".to_vec()));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(client)
+            .with_db(db.clone());
+        state.insert_manifest_source("conns", "
+tenant:
+  _store:
+    client: InMemory
+    key: \"tenant\"
+name:
+  _store:
+    client: InMemory
+    key: \"name\"
+").unwrap();
+        state.insert_manifest_source("rows", &format!(
+            "item:\n  _load:\n    client: Db\n    connection: {}\n    table: \"items\"\n    map:\n      id: \"id\"\n", connection
+        )).unwrap();
+        (state, db)
+    }
+
+    #[test]
+    fn test_db_connection_placeholder_object() {
+        let (mut state, db) = connection_state("${conns.tenant}");
+        assert!(state.get("rows.item").unwrap().is_some());
+        assert!(matches!(db.connection.lock().unwrap().as_ref(), Some(Value::Mapping(f)) if !f.is_empty()));
+    }
+
+    #[test]
+    fn test_db_connection_state_path_is_resolved() {
+        let (mut state, db) = connection_state("\"conns.tenant\"");
+        assert!(state.get("rows.item").unwrap().is_some());
+        assert_eq!(
+            field(db.connection.lock().unwrap().as_ref().unwrap(), b"host"),
+            Some(Value::Scalar(b"tenant-db".to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_db_connection_unresolved_names_path() {
+        for connection in ["\"conns.name\"", "\"conns.missing\"", "${conns.name}"] {
+            let (mut state, db) = connection_state(connection);
+            match state.get("rows.item") {
+                Err(StateError::LoadFailed(LoadError::ConfigMissing(msg))) => {
+                    assert!(msg.contains("conns.") || msg.contains("plain"), "{}", msg);
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+            assert!(db.connection.lock().unwrap().is_none());
+        }
+    }
+
+    const ENV_YML: &str = "
+db:
+  _store:
+    client: KVS
+    key: \"db:${env:STATE_ENGINE_TEST_PREFIX}\"
+  host: \"${env:STATE_ENGINE_TEST_HOST}\"
+  name:
+";
+
+    #[test]
+    fn test_env_interpolation_set_and_missing() {
+        let lookup = |name: &str| (name == "STATE_ENGINE_TEST_HOST").then(|| "db.internal".to_string());
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/envs.yml", ENV_YML)]))
+            .with_env_interpolation(EnvInterpolation::KeepMissing)
+            .with_env_lookup(lookup);
+        let db = state.locate("envs.db").unwrap().2;
+        assert_eq!(state.manifest.static_fields(db), vec![("host".to_string(), "db.internal".to_string())]);
+        let meta = state.manifest.get_meta("envs", "db");
+        let key = state.manifest.build_config(meta.store.unwrap()).unwrap()
+            .into_iter().find(|(k, _)| k == "key").unwrap().1;
+        assert!(matches!(&key, ConfigValue::Str(s) if s.contains("env:STATE_ENGINE_TEST_PREFIX}")), "{:?}", key);
+
+        let mut strict = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/envs.yml", ENV_YML)]))
+            .with_env_interpolation(EnvInterpolation::ErrorOnMissing)
+            .with_env_lookup(lookup);
+        match strict.get("envs.db") {
+            Err(StateError::ManifestLoadFailed(msg)) => assert!(msg.contains("STATE_ENGINE_TEST_PREFIX")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_touched_keys_in_first_touch_order() {
+        let file = MapFile::new(&[("m/session.yml", SESSION_YML), ("m/stores.yml", STORES_YML)]);
+        let mut state = State::new("m").with_manifest_file(file).with_in_memory(Arc::new(MockInMemory::new()));
+        state.set("stores.conn", Value::Scalar(b"c".to_vec()), None).unwrap();
+        state.get("session.sso_user_id").unwrap();
+        state.exists("stores.conn").unwrap();
+        state.delete("session.sso_user_id").unwrap();
+        assert!(state.get("stores.missing").is_err());
+        assert_eq!(state.touched_keys(), &["stores.conn", "session.sso_user_id", "stores.missing"]);
+
+        state.clear_touched();
+        assert!(state.touched_keys().is_empty());
+    }
+
+    struct DownKVS;
+    impl KVSClient for DownKVS {
+        fn get(&self, _: &str) -> Option<Vec<u8>> { None }
+        fn try_get(&self, _: &str) -> Result<Option<Vec<u8>>, String> { Err("connection refused".into()) }
+        fn set(&self, _: &str, _: Vec<u8>, _: Option<u64>) -> bool { false }
+        fn delete(&self, _: &str) -> bool { false }
+    }
+
+    #[test]
+    fn test_get_reports_unavailable_store() {
+        let mut state = stores_state().with_kvs(Arc::new(DownKVS));
+        assert_eq!(state.get("stores.user"), Err(StateError::StoreUnavailable("connection refused".into())));
+
+        // an infallible adapter still reports a plain miss
+        let mut state = stores_state().with_kvs(Arc::new(StubKVS));
+        assert_eq!(state.get("stores.user"), Ok(None));
+    }
+
+    #[test]
+    fn test_compact_cache_keeps_live_entries() {
+        let yml = "
+a:
+  _store:
+    client: InMemory
+    key: \"a\"
+b:
+  _store:
+    client: InMemory
+    key: \"b\"
+c:
+  _store:
+    client: InMemory
+    key: \"c\"
+";
+        let client = Arc::new(MockInMemory::new());
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/abc.yml", yml)]))
+            .with_in_memory(client.clone());
+        for k in ["abc.a", "abc.b", "abc.c"] {
+            state.set(k, Value::Scalar(k.as_bytes().to_vec()), None).unwrap();
+        }
+        state.delete("abc.a").unwrap();
+        assert_eq!(state.compact_cache(), 1);
+        assert_eq!(state.compact_cache(), 0);
+
+        // served from the cache, not the client
+        client.data.lock().unwrap().clear();
+        assert_eq!(state.get("abc.b").unwrap(), Some(Value::Scalar(b"abc.b".to_vec())));
+        assert_eq!(state.get("abc.c").unwrap(), Some(Value::Scalar(b"abc.c".to_vec())));
+        assert_eq!(state.get("abc.a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_cached_content() {
+        let mut a = stores_state().with_in_memory(Arc::new(MockInMemory::new()));
+        let mut b = stores_state().with_in_memory(Arc::new(MockInMemory::new()));
+        a.set("stores.conn", Value::Scalar(b"x".to_vec()), None).unwrap();
+        b.set("stores.conn", Value::Scalar(b"x".to_vec()), None).unwrap();
+        assert_eq!(a.fingerprint(None), b.fingerprint(None));
+        assert_eq!(a.fingerprint(Some("stores.conn")), b.fingerprint(Some("stores.conn")));
+
+        b.set("stores.conn", Value::Scalar(b"y".to_vec()), None).unwrap();
+        assert_ne!(a.fingerprint(None), b.fingerprint(None));
+        assert_ne!(a.fingerprint(Some("stores")), b.fingerprint(Some("stores")));
+        // nothing cached under this prefix on either side
+        assert_eq!(a.fingerprint(Some("stores.user")), b.fingerprint(Some("stores.user")));
+        assert_eq!(a.fingerprint(Some("stores.co")), a.fingerprint(Some("other")));
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_field_order_at_every_depth() {
+        let s = |v: &str| Value::Scalar(v.as_bytes().to_vec());
+        let inner_ab = Value::Mapping(vec![(b"a".to_vec(), s("1")), (b"b".to_vec(), s("2"))]);
+        let inner_ba = Value::Mapping(vec![(b"b".to_vec(), s("2")), (b"a".to_vec(), s("1"))]);
+        let nested = |inner: Value, outer_first: bool| {
+            let fields = vec![(b"host".to_vec(), s("h")), (b"opts".to_vec(), Value::Mapping(vec![(b"tls".to_vec(), inner)]))];
+            Value::Mapping(if outer_first { fields } else { fields.into_iter().rev().collect() })
+        };
+
+        let mut a = stores_state().with_in_memory(Arc::new(MockInMemory::new()));
+        let mut b = stores_state().with_in_memory(Arc::new(MockInMemory::new()));
+        a.set("stores.conn", nested(inner_ab, true), None).unwrap();
+        b.set("stores.conn", nested(inner_ba, false), None).unwrap();
+        assert_eq!(a.fingerprint(None), b.fingerprint(None));
+
+        b.set("stores.conn", nested(Value::Mapping(vec![(b"a".to_vec(), s("1")), (b"b".to_vec(), s("3"))]), true), None).unwrap();
+        assert_ne!(a.fingerprint(None), b.fingerprint(None));
+    }
+
+    #[test]
+    fn test_get_type() {
+        let mut state = stores_state();
+        assert_eq!(state.get_type("stores.user.id").unwrap(), Some("integer".to_string()));
+        assert_eq!(state.get_type("stores.conn.host").unwrap(), None);
+        assert!(matches!(state.get_type("stores.missing"), Err(StateError::KeyNotFound(_))));
+    }
+
+    #[test]
+    fn test_custom_placeholder_delimiters() {
+        let yml = "
+sid:
+  _store:
+    client: InMemory
+    key: \"sid\"
+item:
+  _store:
+    client: InMemory
+    key: \"item:<<t.sid>>:${HOME}\"
+";
+        let client = Arc::new(MockInMemory::new());
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/t.yml", yml)]))
+            .with_placeholder_delimiters("<<", ">>").unwrap()
+            .with_in_memory(client.clone());
+        state.set("t.sid", Value::Scalar(b"7".to_vec()), None).unwrap();
+        state.set("t.item", Value::Scalar(b"x".to_vec()), None).unwrap();
+        assert_eq!(client.data.lock().unwrap().get("item:7:${HOME}"), Some(&Value::Scalar(b"x".to_vec())));
+    }
+
+    #[test]
+    fn test_with_placeholder_delimiters_rejects_empty() {
+        let empty = ManifestError::ParseError("placeholder delimiters must not be empty".into());
+        assert_eq!(State::new("m").with_placeholder_delimiters("", ">>").err(), Some(empty));
+        assert!(State::new("m").with_placeholder_delimiters("<<", "").is_err());
+    }
+
+    #[test]
+    fn test_empty_parse_option_delimiter_fails_manifest_load() {
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/t.yml", "item:\n  value: \"x\"\n")]));
+        state.manifest.options.placeholder_close = Vec::new();
+        match state.get("t.item.value") {
+            Err(StateError::ManifestLoadFailed(msg)) => assert!(msg.contains("placeholder delimiters must not be empty"), "{}", msg),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_yaml_non_string_keys_are_stringified() {
+        let yaml: serde_yaml_ng::Value = serde_yaml_ng::from_str("
+status:
+  200: \"OK\"
+  404: \"Not Found\"
+flags:
+  true: \"on\"
+  false: \"off\"
+").unwrap();
+        let ParseValue::Mapping(root) = yaml_to_parse_value(yaml).unwrap() else { panic!() };
+        let keys = |name: &[u8]| -> Vec<Vec<u8>> {
+            match &root.iter().find(|(k, _)| k == name).unwrap().1 {
+                ParseValue::Mapping(m) => m.iter().map(|(k, _)| k.clone()).collect(),
+                _ => panic!(),
+            }
+        };
+        assert_eq!(keys(b"status"), vec![b"200".to_vec(), b"404".to_vec()]);
+        assert_eq!(keys(b"flags"), vec![b"true".to_vec(), b"false".to_vec()]);
+
+        let bad: serde_yaml_ng::Value = serde_yaml_ng::from_str("? [a, b]\n: x\n").unwrap();
+        assert!(matches!(yaml_to_parse_value(bad), Err(ManifestError::ParseError(m)) if m.contains("unsupported mapping key")));
+    }
+
+    #[test]
+    fn test_replace_load_keeps_cache() {
+        struct FixedEnv(&'static [u8]);
+        impl EnvClient for FixedEnv {
+            fn get(&self, _: &str) -> Option<Vec<u8>> { Some(self.0.to_vec()) }
+            fn set(&self, _: &str, _: Vec<u8>) -> bool { false }
+            fn delete(&self, _: &str) -> bool { false }
+        }
+        let yml = "
+a:
+  _load:
+    client: Env
+    map:
+      host: \"DB_HOST\"
+b:
+  _load:
+    client: Env
+    map:
+      host: \"DB_HOST\"
+";
+        let host = |v: Option<Value>| match v {
+            Some(Value::Mapping(m)) => m.into_iter().next().map(|(_, v)| v),
+            _ => None,
+        };
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/t.yml", yml)]))
+            .with_env(Arc::new(FixedEnv(b"old")));
+        assert_eq!(host(state.get("t.a").unwrap()), Some(Value::Scalar(b"old".to_vec())));
+
+        state.replace_load(Load::new().with_env(Arc::new(FixedEnv(b"new"))));
+        assert_eq!(host(state.get("t.b").unwrap()), Some(Value::Scalar(b"new".to_vec())));
+        assert_eq!(host(state.get("t.a").unwrap()), Some(Value::Scalar(b"old".to_vec())));
+    }
+
+    #[test]
+    fn test_set_if_absent() {
+        struct NxKVS(std::sync::Mutex<HashMap<String, Vec<u8>>>);
+        impl KVSClient for NxKVS {
+            fn get(&self, key: &str) -> Option<Vec<u8>> { self.0.lock().unwrap().get(key).cloned() }
+            fn set(&self, key: &str, value: Vec<u8>, _: Option<u64>) -> bool { self.0.lock().unwrap().insert(key.into(), value); true }
+            fn delete(&self, key: &str) -> bool { self.0.lock().unwrap().remove(key).is_some() }
+            fn set_nx(&self, key: &str, value: Vec<u8>, _: Option<u64>) -> bool {
+                let mut m = self.0.lock().unwrap();
+                if m.contains_key(key) { return false; }
+                m.insert(key.into(), value);
+                true
+            }
+        }
+        let kvs = Arc::new(NxKVS(Default::default()));
+        let mut state = stores_state().with_kvs(kvs.clone());
+        assert!(state.set_if_absent("stores.user", Value::Scalar(b"a".to_vec()), None).unwrap());
+        assert!(!state.set_if_absent("stores.user", Value::Scalar(b"b".to_vec()), None).unwrap());
+        assert_eq!(kvs.get("user"), Some(b"a".to_vec()));
+
+        // another process already holds the key
+        let mut other = stores_state().with_kvs(kvs.clone());
+        assert!(!other.set_if_absent("stores.user", Value::Scalar(b"c".to_vec()), None).unwrap());
+
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client.clone());
+        assert!(state.set_if_absent("stores.conn", Value::Scalar(b"x".to_vec()), None).unwrap());
+        assert!(!state.set_if_absent("stores.conn", Value::Scalar(b"y".to_vec()), None).unwrap());
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&Value::Scalar(b"x".to_vec())));
+
+        // a stored null is present
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client.clone());
+        assert!(state.set("stores.conn", Value::Null, None).unwrap());
+        assert!(!state.set_if_absent("stores.conn", Value::Scalar(b"y".to_vec()), None).unwrap());
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn test_include_merges_shared_block() {
+        let shared = "
+common:
+  _store:
+    client: InMemory
+    key: \"shared\"
+  host: \"localhost\"
+  port: \"5432\"
+";
+        let cache = "
+tenant:
+  _include: \"shared.common\"
+  port: \"6543\"
+";
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/shared.yml", shared), ("m/cache.yml", cache)]));
+        let idx = state.locate("cache.tenant").unwrap().2;
+        assert_eq!(state.manifest.static_fields(idx), vec![
+            ("host".to_string(), "localhost".to_string()),
+            ("port".to_string(), "6543".to_string()),
+        ]);
+        assert_eq!(state.store_client_of("cache.tenant").unwrap(), Some("InMemory".to_string()));
+    }
+
+    #[test]
+    fn test_include_cycle_is_rejected() {
+        let a = "
+x:
+  _include: \"b.y\"
+";
+        let b = "
+y:
+  _include: \"a.x\"
+";
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/a.yml", a), ("m/b.yml", b)]));
+        match state.get("a.x") {
+            Err(StateError::ManifestLoadFailed(msg)) => assert!(msg.contains("cycle"), "{}", msg),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_http_load_posts_resolved_body() {
+        use crate::ports::required::HttpClient;
+        type Call = (String, String, Option<Value>);
+        struct PostHttp(std::sync::Mutex<Vec<Call>>);
+        impl HttpClient for PostHttp {
+            fn get(&self, _: &str, _: Option<&[(Vec<u8>, Vec<u8>)]>) -> Option<Value> { None }
+            fn set(&self, _: &str, _: Value, _: Option<&[(Vec<u8>, Vec<u8>)]>) -> bool { false }
+            fn delete(&self, _: &str, _: Option<&[(Vec<u8>, Vec<u8>)]>) -> bool { false }
+            fn request(&self, method: &str, url: &str, _: Option<&[(Vec<u8>, Vec<u8>)]>, body: Option<&Value>) -> Option<Value> {
+                self.0.lock().unwrap().push((method.into(), url.into(), body.cloned()));
+                Some(Value::Scalar(b"ok".to_vec()))
+            }
+        }
+        let yml = "
+sid:
+  _store:
+    client: InMemory
+    key: \"sid\"
+profile:
+  _load:
+    client: HTTP
+    url: \"https://api.example.com/profile\"
+    method: \"POST\"
+    body:
+      user_id: ${t.sid}
+      scope: \"full\"
+";
+        let http = Arc::new(PostHttp(Default::default()));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/t.yml", yml)]))
+            .with_in_memory(Arc::new(MockInMemory::new()))
+            .with_http(http.clone());
+        state.set("t.sid", Value::Scalar(b"42".to_vec()), None).unwrap();
+        assert_eq!(state.get("t.profile").unwrap(), Some(Value::Scalar(b"ok".to_vec())));
+
+        let calls = http.0.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!((calls[0].0.as_str(), calls[0].1.as_str()), ("POST", "https://api.example.com/profile"));
+        assert_eq!(calls[0].2, Some(Value::Mapping(vec![
+            (b"user_id".to_vec(), Value::Scalar(b"42".to_vec())),
+            (b"scope".to_vec(), Value::Scalar(b"full".to_vec())),
+        ])));
+    }
+
+    #[test]
+    fn test_dry_run_skips_store_writes() {
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state()
+            .with_in_memory(client.clone())
+            .with_dry_run(true);
+        let host = Value::Mapping(vec![(b"host".to_vec(), Value::Scalar(b"db".to_vec()))]);
+
+        assert!(state.set("stores.conn", host.clone(), None).unwrap());
+        assert_eq!(state.get("stores.conn").unwrap(), Some(host.clone()));
+        assert!(state.delete("stores.conn").unwrap());
+
+        assert!(client.data.lock().unwrap().is_empty());
+        assert_eq!(state.dry_run_log(), &[
+            DryRunWrite { op: "set".into(), store_key: "conn".into(), client: "InMemory".into(), value: Some(host) },
+            DryRunWrite { op: "delete".into(), store_key: "conn".into(), client: "InMemory".into(), value: None },
+        ]);
+    }
+
+    #[test]
+    fn test_dry_run_covers_conditional_and_load_writes() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("src".into(), Value::Scalar(b"loaded".to_vec()));
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[])).with_in_memory(client.clone()).with_dry_run(true);
+        state.insert_manifest_source("app", "
+flag:
+  _store:
+    client: InMemory
+    key: \"flag\"
+profile:
+  _store:
+    client: InMemory
+    key: \"profile\"
+  _load:
+    client: InMemory
+    key: \"src\"
+").unwrap();
+        let (a, b) = (Value::Scalar(b"a".to_vec()), Value::Scalar(b"b".to_vec()));
+
+        assert!(state.set_if_absent("app.flag", a.clone(), None).unwrap());
+        assert!(!state.set_if_absent("app.flag", b.clone(), None).unwrap());
+        assert!(!state.compare_and_set("app.flag", Some(a.clone()), b.clone(), None).unwrap());
+        assert!(state.compare_and_set("app.flag", None, b.clone(), None).unwrap());
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"loaded".to_vec())));
+
+        assert_eq!(client.data.lock().unwrap().len(), 1);
+        let written: Vec<_> = state.dry_run_log().iter().map(|w| (w.op.as_str(), w.store_key.as_str(), w.value.clone())).collect();
+        assert_eq!(written, [
+            ("set", "flag", Some(a)),
+            ("set", "flag", Some(b)),
+            ("set", "profile", Some(Value::Scalar(b"loaded".to_vec()))),
+        ]);
+    }
+
+    #[test]
+    fn test_prefetch_warms_cache() {
+        struct FixedEnv;
+        impl EnvClient for FixedEnv {
+            fn get(&self, _: &str) -> Option<Vec<u8>> { Some(b"v".to_vec()) }
+            fn set(&self, _: &str, _: Vec<u8>) -> bool { false }
+            fn delete(&self, _: &str) -> bool { false }
+        }
+        let mut state = State::new("./examples/manifest")
+            .with_in_memory(Arc::new(MockInMemory::new()))
+            .with_env(Arc::new(FixedEnv));
+
+        assert_eq!(state.prefetch(&["connection.common", "connection.missing"]), 1);
+        assert_eq!(state.prefetch(&["connection.common"]), 0);
+
+        state.reset_metrics();
+        assert!(state.get("connection.common").unwrap().is_some());
+        assert_eq!(state.metrics().cache_hits, 1);
+    }
+
+    #[test]
+    fn test_kvs_codec_stores_mappings() {
+        #[derive(Default)]
+        struct MemKVS(std::sync::Mutex<HashMap<String, Vec<u8>>>);
+        impl KVSClient for MemKVS {
+            fn get(&self, key: &str) -> Option<Vec<u8>> { self.0.lock().unwrap().get(key).cloned() }
+            fn set(&self, key: &str, value: Vec<u8>, _: Option<u64>) -> bool { self.0.lock().unwrap().insert(key.into(), value); true }
+            fn delete(&self, key: &str) -> bool { self.0.lock().unwrap().remove(key).is_some() }
+        }
+        let kvs = Arc::new(MemKVS::default());
+        let mut state = stores_state()
+            .with_kvs(kvs.clone())
+            .with_kvs_codec(crate::store::KvsCodec::Binary);
+        let user = Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"7".to_vec()))]);
+        state.set("stores.user", user.clone(), None).unwrap();
+
+        let mut fresh = stores_state()
+            .with_kvs(kvs)
+            .with_kvs_codec(crate::store::KvsCodec::Binary);
+        assert_eq!(fresh.get("stores.user").unwrap(), Some(user));
+    }
+
+    #[test]
+    fn test_recursion_errors() {
+        let yml = "
+a:
+  _load:
+    client: State
+    key: \"${b}\"
+b:
+  _load:
+    client: State
+    key: \"${a}\"
+";
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/loop.yml", yml)]));
+        assert_eq!(
+            state.get("loop.a"),
+            Err(StateError::CircularReference("loop.a -> loop.b -> loop.a".into()))
+        );
+        // the chain is unwound after the error
+        assert_eq!(
+            state.get("loop.b"),
+            Err(StateError::CircularReference("loop.b -> loop.a -> loop.b".into()))
+        );
+
+        let deep: String = (0..25)
+            .map(|i| format!("k{}:\n  _load:\n    client: State\n    key: \"${{k{}}}\"\n", i, i + 1))
+            .collect();
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/deep.yml", &deep)]));
+        let chain: Vec<String> = (0..=20).map(|i| format!("deep.k{}", i)).collect();
+        assert_eq!(state.get("deep.k0"), Err(StateError::RecursionLimitExceeded(chain.join(" -> "))));
+        assert!(state.get("deep.k0").unwrap_err().to_string()
+            .starts_with("RecursionLimitExceeded: max recursion resolving: deep.k0 -> deep.k1 -> "));
+    }
+
+    #[test]
+    fn test_typed_getters() {
+        let yml: String = ["port", "ratio", "debug", "label", "conn"].iter()
+            .map(|k| format!("{k}:\n  _store:\n    client: InMemory\n    key: \"cfg:{k}\"\n"))
+            .collect();
+        // Env and InMemory adapters hand back text, so numbers arrive as strings
+        let client = Arc::new(MockInMemory::new());
+        for (k, v) in [("port", "5432"), ("ratio", "0.25"), ("debug", "true"), ("label", "app")] {
+            client.data.lock().unwrap().insert(format!("cfg:{}", k), Value::Scalar(v.as_bytes().to_vec()));
+        }
+        client.data.lock().unwrap().insert("cfg:conn".into(), Value::Mapping(vec![]));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/cfg.yml", &yml)]))
+            .with_in_memory(client);
+
+        assert_eq!(state.get_str("cfg.label").unwrap(), Some("app".to_string()));
+        assert_eq!(state.get_i64("cfg.port").unwrap(), Some(5432));
+        assert_eq!(state.get_f64("cfg.ratio").unwrap(), Some(0.25));
+        assert_eq!(state.get_f64("cfg.port").unwrap(), Some(5432.0));
+        assert_eq!(state.get_bool("cfg.debug").unwrap(), Some(true));
+
+        // type mismatches
+        assert_eq!(state.get_i64("cfg.ratio").unwrap(), None);
+        assert_eq!(state.get_i64("cfg.label").unwrap(), None);
+        assert_eq!(state.get_bool("cfg.port").unwrap(), None);
+        assert_eq!(state.get_str("cfg.conn").unwrap(), None);
+        assert!(state.get_str("cfg.missing").is_err());
+    }
+
+    #[test]
+    fn test_lint_manifest() {
+        let yml = "
+alias:
+  _store:
+    client: InMemory
+    key: \"alias\"
+  _load:
+    client: State
+    key: \"${stores.conn}\"
+";
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/lint.yml", yml), ("m/stores.yml", STORES_YML)]));
+        assert_eq!(state.lint_manifest("lint").unwrap(), vec!["lint.alias: _store is never read because _load.client is State".to_string()]);
+        assert!(state.lint_manifest("stores").unwrap().is_empty());
+        assert!(matches!(state.lint_manifest("nope"), Err(StateError::ManifestLoadFailed(_))));
+    }
+
+    #[test]
+    fn test_store_ttl_from_placeholder() {
+        struct TtlKVS(std::sync::Mutex<Option<u64>>);
+        impl KVSClient for TtlKVS {
+            fn get(&self, _: &str) -> Option<Vec<u8>> { None }
+            fn set(&self, _: &str, _: Vec<u8>, ttl: Option<u64>) -> bool { *self.0.lock().unwrap() = ttl; true }
+            fn delete(&self, _: &str) -> bool { false }
+        }
+        let yml = "
+session_ttl:
+  _store:
+    client: InMemory
+    key: \"session_ttl\"
+session:
+  _store:
+    client: KVS
+    key: \"session\"
+    ttl: ${session_ttl}
+";
+        let kvs = Arc::new(TtlKVS(std::sync::Mutex::new(None)));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/config.yml", yml)]))
+            .with_in_memory(Arc::new(MockInMemory::new()))
+            .with_kvs(kvs.clone());
+        state.set("config.session_ttl", Value::Scalar(b"14400".to_vec()), None).unwrap();
+        state.set("config.session", Value::Scalar(b"s".to_vec()), None).unwrap();
+        assert_eq!(*kvs.0.lock().unwrap(), Some(14400));
+    }
+
+    #[test]
+    fn test_invalidate_refetches_from_store() {
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client.clone());
+        state.set("stores.conn", Value::Scalar(b"old".to_vec()), None).unwrap();
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"new".to_vec()));
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+
+        assert!(state.invalidate("stores.conn", false).unwrap());
+        assert!(!state.invalidate("stores.conn", false).unwrap());
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"new".to_vec())));
+        assert_eq!(client.data.lock().unwrap().len(), 1);
+
+        // a child key drops its owner only on request
+        assert!(!state.invalidate("stores.conn.host", false).unwrap());
+        assert!(state.invalidate("stores.conn.host", true).unwrap());
+        assert!(matches!(state.invalidate("stores.missing", false), Err(StateError::KeyNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_distinguishes_null_from_missing() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Null);
+        let mut state = stores_state().with_in_memory(client.clone());
+
+        // from the store, then from the cache
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Null));
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Null));
+        assert!(state.exists("stores.conn").unwrap());
+
+        state.delete("stores.conn").unwrap();
+        assert_eq!(state.get("stores.conn").unwrap(), None);
+        assert!(!state.exists("stores.conn").unwrap());
+
+        state.set("stores.conn", Value::Null, None).unwrap();
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Null));
+    }
+
+    #[test]
+    fn test_load_fallback_chain() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("main-conn".to_string(), Value::Mapping(vec![]));
+        let row = Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"1".to_vec()))]);
+        let yml = "
+conn:
+  _store:
+    client: InMemory
+    key: \"main-conn\"
+user:
+  _load:
+    client: KVS
+    key: \"user:1\"
+    fallback:
+      client: Db
+      connection: ${users.conn}
+      table: \"users\"
+      map:
+        id: \"id\"
+";
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/users.yml", yml)]))
+            .with_in_memory(client.clone())
+            .with_kvs(Arc::new(StubKVS))
+            .with_db(Arc::new(RowsDb { rows: vec![row.clone()] }));
+        assert_eq!(state.get("users.user").unwrap(), Some(row));
+
+        let mut failing = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/users.yml", yml)]))
+            .with_in_memory(client)
+            .with_kvs(Arc::new(StubKVS))
+            .with_db(Arc::new(StubDb));
+        assert_eq!(failing.get("users.user"), Err(StateError::LoadFailed(LoadError::AllFailed(vec![
+            LoadError::NotFound("user:1".into()),
+            LoadError::NotFound("users".into()),
+        ]))));
+    }
+
+    #[test]
+    fn test_load_fallback_skips_unresolvable_block() {
+        let yml = "
+user:
+  _load:
+    client: InMemory
+    key: \"${users.missing}\"
+    fallback:
+      client: InMemory
+      key: \"user-row\"
+lone:
+  _load:
+    client: InMemory
+    key: \"${users.missing}\"
+";
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("user-row".to_string(), Value::Scalar(b"1".to_vec()));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/users.yml", yml)]))
+            .with_in_memory(client);
+        assert_eq!(state.get("users.user").unwrap(), Some(Value::Scalar(b"1".to_vec())));
+        assert_eq!(state.get("users.lone"), Err(StateError::KeyNotFound("users.missing".into())));
+    }
+
+    #[test]
+    fn test_strict_meta() {
+        let yml = "
+user:
+  _stoer:
+    client: KVS
+  id:
+";
+        let mut lenient = State::new("m").with_manifest_file(MapFile::new(&[("m/typo.yml", yml)]));
+        assert_eq!(lenient.get("typo.user.id").unwrap(), None);
+
+        let mut strict = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/typo.yml", yml)]))
+            .with_strict_meta(true);
+        assert_eq!(
+            strict.get("typo.user.id"),
+            Err(StateError::ManifestLoadFailed("ParseError: unknown meta key '_stoer' at typo.user._stoer".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_raw_reads_store_verbatim() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Mapping(vec![(b"name".to_vec(), Value::Scalar(b"a".to_vec()))]));
+        let yml = "
+profile:
+  _store:
+    client: InMemory
+    key: \"profile\"
+  _load:
+    client: InMemory
+    key: \"profile-src\"
+  kind: \"user\"
+";
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/app.yml", yml)]))
+            .with_in_memory(client.clone());
+        assert_eq!(state.get_raw("app.profile").unwrap(), None);
+
+        let merged = state.get("app.profile").unwrap().unwrap();
+        assert_eq!(state.get_raw("app.profile").unwrap(), Some(merged));
+
+        let external = Value::Mapping(vec![(b"name".to_vec(), Value::Scalar(b"b".to_vec()))]);
+        client.data.lock().unwrap().insert("profile".to_string(), external.clone());
+        assert_eq!(state.get_raw("app.profile").unwrap(), Some(external));
+        assert_ne!(state.get("app.profile").unwrap(), state.get_raw("app.profile").unwrap());
+    }
+
+    #[test]
+    fn test_set_static_reaches_next_load() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Mapping(vec![
+            (b"name".to_vec(), Value::Scalar(b"alice".to_vec())),
+        ]));
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(client);
+        state.insert_manifest_source("app", "
+profile:
+  _load:
+    client: InMemory
+    key: \"profile-src\"
+  name:
+  plan: \"free\"
+").unwrap();
+        let plan = |state: &mut State| {
+            let Some(Value::Mapping(fields)) = state.get("app.profile").unwrap() else { panic!("expected mapping") };
+            fields.into_iter().find(|(k, _)| k == b"plan").map(|(_, v)| v)
+        };
+
+        assert_eq!(plan(&mut state), Some(Value::Scalar(b"free".to_vec())));
+        assert!(state.set_static("app.profile.plan", "pro").unwrap());
+        assert_eq!(plan(&mut state), Some(Value::Scalar(b"pro".to_vec())));
+        assert!(!state.set_static("app.profile", "x").unwrap());
+        assert!(state.clear_static("app.profile.plan").unwrap());
+        assert_eq!(plan(&mut state), Some(Value::Scalar(b"free".to_vec())));
+        assert!(!state.clear_static("app.profile.plan").unwrap());
+    }
+
+    #[test]
+    fn test_register_store_dispatches_custom_client() {
+        struct Sqlite(Arc<std::sync::Mutex<HashMap<String, Value>>>);
+        impl StoreBackend for Sqlite {
+            fn get(&self, config: &HashMap<String, Value>) -> Option<Value> {
+                let Some(Value::Scalar(k)) = config.get("key") else { return None };
+                self.0.lock().unwrap().get(&String::from_utf8_lossy(k).into_owned()).cloned()
+            }
+            fn set(&self, config: &HashMap<String, Value>, value: Value, _ttl: Option<u64>) -> bool {
+                let Some(Value::Scalar(k)) = config.get("key") else { return false };
+                self.0.lock().unwrap().insert(String::from_utf8_lossy(k).into_owned(), value);
+                true
+            }
+            fn delete(&self, config: &HashMap<String, Value>) -> bool {
+                let Some(Value::Scalar(k)) = config.get("key") else { return false };
+                self.0.lock().unwrap().remove(&String::from_utf8_lossy(k).into_owned()).is_some()
+            }
+        }
+
+        let yml = "
+prefs:
+  _store:
+    client: Sqlite
+    key: \"prefs\"
+";
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/app.yml", yml)]));
+        assert_eq!(state.set("app.prefs", Value::Scalar(b"dark".to_vec()), None), Err(StateError::StoreClientNotConfigured("Sqlite".into())));
+
+        let rows = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        state.register_store("Sqlite", Box::new(Sqlite(rows.clone())));
+        assert!(state.set("app.prefs", Value::Scalar(b"dark".to_vec()), None).unwrap());
+        assert_eq!(rows.lock().unwrap().get("prefs"), Some(&Value::Scalar(b"dark".to_vec())));
+        state.invalidate("app.prefs", false).unwrap();
+        assert_eq!(state.get("app.prefs").unwrap(), Some(Value::Scalar(b"dark".to_vec())));
+        assert!(state.delete("app.prefs").unwrap());
+        assert!(rows.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reload_manifest_reads_file_again() {
+        struct Shared(Arc<MapFile>);
+        impl FileClient for Shared {
+            fn get(&self, key: &str) -> Option<Vec<u8>> { self.0.get(key) }
+            fn set(&self, key: &str, value: Vec<u8>) -> bool { self.0.set(key, value) }
+            fn delete(&self, key: &str) -> bool { self.0.delete(key) }
+        }
+        let file = Arc::new(MapFile::new(&[("m/app.yml", "a:\n")]));
+        let mut state = State::new("m").with_manifest_file(Shared(file.clone()));
+        assert_eq!(state.get("app.a").unwrap(), None);
+        file.set("m/app.yml", b"b:\n".to_vec());
+        assert!(matches!(state.get("app.b"), Err(StateError::KeyNotFound(_))));
+
+        assert!(state.reload_manifest("app"));
+        assert_eq!(state.get("app.b").unwrap(), None);
+        assert!(matches!(state.get("app.a"), Err(StateError::KeyNotFound(_))));
+        assert!(!state.reload_manifest("other"));
+    }
+
+    #[test]
+    fn test_reload_manifest_compacts_pools() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".into(), Value::Scalar(b"c".to_vec()));
+        let file = MapFile::new(&[("m/app.yml", "label: \"at ${stores.conn}\"\n"), ("m/stores.yml", STORES_YML)]);
+        let mut state = State::new("m").with_manifest_file(file).with_in_memory(client.clone());
+        // app is parsed first, so removing it shifts every stores record
+        assert_eq!(state.get("app.label").unwrap(), Some(Value::Scalar(b"at c".to_vec())));
+        assert!(state.set_static("stores.plain.value", "pinned").unwrap());
+
+        let sizes = (state.manifest.keys.len(), state.manifest.values.len(), state.manifest.dynamic.len());
+        for _ in 0..5 {
+            assert!(state.reload_manifest("app"));
+            assert_eq!(state.get("app.label").unwrap(), Some(Value::Scalar(b"at c".to_vec())));
+        }
+        assert_eq!((state.manifest.keys.len(), state.manifest.values.len(), state.manifest.dynamic.len()), sizes);
+
+        // entries of the file that stayed follow their keys' new indices
+        client.data.lock().unwrap().insert("conn".into(), Value::Scalar(b"changed".to_vec()));
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"c".to_vec())));
+        assert_eq!(state.get("stores.plain.value").unwrap(), Some(Value::Scalar(b"pinned".to_vec())));
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_watch_manifests_reloads_changed_file() {
+        let dir = std::env::temp_dir().join(format!("state-engine-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.yml"), "a:\n").unwrap();
+        let mut state = State::new(dir.to_str().unwrap());
+        let _watcher = state.watch_manifests(std::time::Duration::from_millis(10));
+        assert!(matches!(state.get("app.b"), Err(StateError::KeyNotFound(_))));
+
+        std::fs::write(dir.join("app.yml"), "a:\nb:\n").unwrap();
+        let seen = (0..200).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            state.get("app.b").is_ok()
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(seen);
+    }
+
+    #[test]
+    fn test_delete_prefix_removes_matching_keys() {
+        #[derive(Default)]
+        struct ScanKVS(std::sync::Mutex<HashMap<String, Vec<u8>>>);
+        impl KVSClient for ScanKVS {
+            fn get(&self, key: &str) -> Option<Vec<u8>> { self.0.lock().unwrap().get(key).cloned() }
+            fn set(&self, key: &str, value: Vec<u8>, _: Option<u64>) -> bool { self.0.lock().unwrap().insert(key.into(), value); true }
+            fn delete(&self, key: &str) -> bool { self.0.lock().unwrap().remove(key).is_some() }
+            fn scan(&self, prefix: &str) -> Vec<String> {
+                self.0.lock().unwrap().keys().filter(|k| k.starts_with(prefix)).cloned().collect()
+            }
+        }
+        let kvs = Arc::new(ScanKVS::default());
+        for k in ["user:1:profile", "user:1:prefs", "user:2:profile"] {
+            kvs.set(k, b"v".to_vec(), None);
+        }
+        let mut state = stores_state().with_kvs(kvs.clone());
+
+        assert_eq!(state.delete_prefix("stores.user", "user:1:").unwrap(), 2);
+        assert_eq!(kvs.0.lock().unwrap().keys().collect::<Vec<_>>(), vec!["user:2:profile"]);
+        assert_eq!(state.delete_prefix("stores.user", "user:1:").unwrap(), 0);
+        assert_eq!(state.delete_prefix("stores.plain", "user:").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_transaction_commits_on_ok() {
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client.clone());
+        let out = state.transaction(|s| {
+            s.set("stores.conn", Value::Scalar(b"a".to_vec()), None)?;
+            Ok(7)
+        }).unwrap();
+        assert_eq!(out, 7);
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&Value::Scalar(b"a".to_vec())));
+        assert!(state.journal.is_none());
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_on_err_and_panic() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"old".to_vec()));
+        let mut state = stores_state().with_in_memory(client.clone());
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+
+        let result: Result<(), _> = state.transaction(|s| {
+            s.set("stores.conn", Value::Scalar(b"new".to_vec()), None)?;
+            s.delete("stores.conn")?;
+            s.set("stores.conn", Value::Scalar(b"newer".to_vec()), None)?;
+            Err(StateError::KeyNotFound("abort".into()))
+        });
+        assert!(matches!(result, Err(StateError::KeyNotFound(_))));
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&Value::Scalar(b"old".to_vec())));
+        assert_eq!(state.get("stores.conn").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+
+        client.data.lock().unwrap().clear();
+        state.invalidate("stores.conn", false).unwrap();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _: Result<(), _> = state.transaction(|s| {
+                s.set("stores.conn", Value::Scalar(b"new".to_vec()), None)?;
+                panic!("boom");
+            });
+        }));
+        assert!(panicked.is_err());
+        assert!(client.data.lock().unwrap().get("conn").is_none());
+        assert_eq!(state.get("stores.conn").unwrap(), None);
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_load_write_through() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Scalar(b"v".to_vec()));
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[])).with_in_memory(client.clone());
+        state.insert_manifest_source("app", "profile:\n  _store:\n    client: InMemory\n    key: \"profile\"\n  _load:\n    client: InMemory\n    key: \"profile-src\"\n").unwrap();
+
+        let result: Result<(), _> = state.transaction(|s| {
+            assert_eq!(s.get("app.profile")?, Some(Value::Scalar(b"v".to_vec())));
+            assert!(client.data.lock().unwrap().contains_key("profile"));
+            Err(StateError::KeyNotFound("abort".into()))
+        });
+        assert!(result.is_err());
+        assert!(!client.data.lock().unwrap().contains_key("profile"));
+    }
+
+    #[test]
+    fn test_transaction_journals_only_successful_compare_and_set() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".to_string(), Value::Scalar(b"old".to_vec()));
+        let mut state = stores_state().with_in_memory(client.clone());
+        let (a, b) = (Value::Scalar(b"a".to_vec()), Value::Scalar(b"b".to_vec()));
+
+        // a lost swap leaves nothing to undo, so a concurrent write survives rollback
+        let result: Result<(), _> = state.transaction(|s| {
+            assert!(!s.compare_and_set("stores.conn", Some(a.clone()), b.clone(), None)?);
+            client.data.lock().unwrap().insert("conn".to_string(), a.clone());
+            Err(StateError::KeyNotFound("abort".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&a));
+
+        // a won swap is undone
+        let result: Result<(), _> = state.transaction(|s| {
+            assert!(s.compare_and_set("stores.conn", Some(a.clone()), b.clone(), None)?);
+            Err(StateError::KeyNotFound("abort".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&a));
+    }
+
+    #[test]
+    fn test_transaction_rollback_restores_cache_timestamps() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Scalar(b"old".to_vec()));
+        let start = std::time::Instant::now();
+        let elapsed = Arc::new(std::sync::Mutex::new(std::time::Duration::ZERO));
+        let clock = elapsed.clone();
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(client.clone())
+            .with_clock(move || start + *clock.lock().unwrap());
+        state.insert_manifest_source("app", "profile:\n  _state:\n    cache_ttl: 0.05\n  _load:\n    client: InMemory\n    key: \"profile-src\"\n").unwrap();
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+
+        // the reload inside the aborted transaction re-stamps the entry; rollback puts
+        // back both the old value and its original (now expired) timestamp
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Scalar(b"new".to_vec()));
+        *elapsed.lock().unwrap() = std::time::Duration::from_millis(60);
+        let result: Result<(), _> = state.transaction(|s| {
+            assert_eq!(s.get("app.profile")?, Some(Value::Scalar(b"new".to_vec())));
+            Err(StateError::KeyNotFound("abort".into()))
+        });
+        assert!(result.is_err());
+        *elapsed.lock().unwrap() = std::time::Duration::from_millis(70);
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"new".to_vec())));
+    }
+
+    #[test]
+    fn test_resolve_key_previews_qualification() {
+        let mut state = State::new("./examples/manifest");
+        assert_eq!(state.resolve_key("cache.user.tenant_id", "${org_id}").unwrap(), "cache.user.org_id");
+        assert_eq!(state.resolve_key("cache.user", "session.sso_user_id").unwrap(), "session.sso_user_id");
+        assert!(matches!(state.resolve_key("cache.nope", "x"), Err(StateError::KeyNotFound(_))));
+    }
+
+    #[test]
+    fn test_json_schema_from_state_types() {
+        let mut state = State::new("./examples/manifest");
+        let schema = state.json_schema("cache.user").unwrap();
+        let field = |v: &Value, k: &[u8]| match v {
+            Value::Mapping(m) => m.iter().find(|(f, _)| f.as_slice() == k).map(|(_, v)| v.clone()),
+            _ => None,
+        };
+        let s = |v: &str| Some(Value::Scalar(v.as_bytes().to_vec()));
+        assert_eq!(field(&schema, b"type"), s("object"));
+        let props = field(&schema, b"properties").unwrap();
+        assert_eq!(field(&field(&props, b"id").unwrap(), b"type"), s("integer"));
+        assert_eq!(field(&field(&props, b"tenant_id").unwrap(), b"type"), s("integer"));
+
+        let file = state.json_schema("cache").unwrap();
+        let tenant = field(&field(&file, b"properties").unwrap(), b"tenant").unwrap();
+        let name = field(&field(&tenant, b"properties").unwrap(), b"name").unwrap();
+        assert_eq!(field(&name, b"type"), s("string"));
+        assert_eq!(state.json_schema("cache.user.id").unwrap(), Value::Mapping(vec![(b"type".to_vec(), Value::Scalar(b"integer".to_vec()))]));
+    }
+
+    #[test]
+    fn test_get_names_unconfigured_load_client() {
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/app.yml", "
+rows:
+  _load:
+    client: Db
+    table: \"users\"
+    map:
+      id: \"id\"
+")]));
+        assert_eq!(state.get("app.rows"), Err(StateError::ClientNotConfigured("Db".into())));
+
+        let mut state = state.with_db(Arc::new(StubDb));
+        assert!(matches!(state.get("app.rows"), Err(StateError::LoadFailed(LoadError::ConfigMissing(_)))));
+    }
+
+    #[test]
+    fn test_get_static_only_leaf() {
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/consts.yml", "db:\n  driver: postgres\n  port:\n")]));
+        assert_eq!(state.get("consts.db.driver").unwrap(), Some(Value::Scalar(b"postgres".to_vec())));
+        assert_eq!(state.get("consts.db.port").unwrap(), None);
+        assert_eq!(state.get("consts.db.driver").unwrap(), Some(Value::Scalar(b"postgres".to_vec())));
+        assert_eq!(state.metrics().cache_hits, 1);
+    }
+
+    #[test]
+    fn test_get_static_only_leaf_resolves_template_and_follows_set_static() {
+        let consts = "db:\n  driver: postgres\n  name: app\n  label: \"${consts.db.name} on ${consts.db.driver}\"\n";
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/consts.yml", consts)]));
+        assert_eq!(state.get("consts.db.label").unwrap(), Some(Value::Scalar(b"app on postgres".to_vec())));
+
+        assert_eq!(state.get("consts.db.driver").unwrap(), Some(Value::Scalar(b"postgres".to_vec())));
+        assert!(state.set_static("consts.db.driver", "mysql").unwrap());
+        assert_eq!(state.get("consts.db.driver").unwrap(), Some(Value::Scalar(b"mysql".to_vec())));
+        assert!(state.clear_static("consts.db.driver").unwrap());
+        assert_eq!(state.get("consts.db.driver").unwrap(), Some(Value::Scalar(b"postgres".to_vec())));
+    }
+
+    #[test]
+    fn test_compare_and_set() {
+        struct MapKVS(std::sync::Mutex<HashMap<String, Vec<u8>>>);
+        impl KVSClient for MapKVS {
+            fn get(&self, key: &str) -> Option<Vec<u8>> { self.0.lock().unwrap().get(key).cloned() }
+            fn set(&self, key: &str, value: Vec<u8>, _: Option<u64>) -> bool { self.0.lock().unwrap().insert(key.into(), value); true }
+            fn delete(&self, key: &str) -> bool { self.0.lock().unwrap().remove(key).is_some() }
+        }
+        let kvs = Arc::new(MapKVS(Default::default()));
+        let mut state = stores_state().with_kvs(kvs.clone());
+        let (a, b) = (Value::Scalar(b"a".to_vec()), Value::Scalar(b"b".to_vec()));
+
+        // absent expected
+        assert!(state.compare_and_set("stores.user", None, a.clone(), None).unwrap());
+        assert!(!state.compare_and_set("stores.user", None, b.clone(), None).unwrap());
+        assert_eq!(kvs.get("user"), Some(b"a".to_vec()));
+
+        // mismatched, then matching expected
+        assert!(!state.compare_and_set("stores.user", Some(b.clone()), b.clone(), None).unwrap());
+        assert_eq!(state.get("stores.user").unwrap(), Some(a.clone()));
+        assert!(state.compare_and_set("stores.user", Some(a.clone()), b.clone(), None).unwrap());
+        assert_eq!(state.get("stores.user").unwrap(), Some(b.clone()));
+
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client.clone());
+        assert!(state.compare_and_set("stores.conn", None, a.clone(), None).unwrap());
+        assert!(!state.compare_and_set("stores.conn", Some(b.clone()), b.clone(), None).unwrap());
+        assert!(state.compare_and_set("stores.conn", Some(a.clone()), b.clone(), None).unwrap());
+        assert_eq!(client.data.lock().unwrap().get("conn"), Some(&b));
+        assert!(!state.compare_and_set("stores.plain", None, b.clone(), None).unwrap());
+
+        // a lost swap drops the stale cached value
+        let c = Value::Scalar(b"c".to_vec());
+        assert_eq!(state.get("stores.conn").unwrap(), Some(b.clone()));
+        client.data.lock().unwrap().insert("conn".into(), c.clone());
+        assert!(!state.compare_and_set("stores.conn", Some(a.clone()), a, None).unwrap());
+        assert_eq!(state.get("stores.conn").unwrap(), Some(c));
+    }
+
+    #[test]
+    fn test_get_materializes_loaded_connection() {
+        struct ConnDb(std::sync::Mutex<Vec<Value>>);
+        impl DbClient for ConnDb {
+            fn get(&self, connection: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> Option<Vec<Value>> {
+                self.0.lock().unwrap().push(connection.clone());
+                Some(vec![Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"1".to_vec()))])])
+            }
+            fn set(&self, _: &Value, _: &str, _: &[(Vec<u8>, Vec<u8>)], _: Option<&[u8]>) -> bool { false }
+            fn delete(&self, _: &Value, _: &str, _: Option<&[u8]>) -> bool { false }
+        }
+        const CONNS_YML: &str = "
+tenant:
+  _store:
+    client: InMemory
+    key: \"tenant-conn\"
+  _load:
+    client: InMemory
+    key: \"tenant-src\"
+  driver: \"postgres\"
+  host:
+";
+        const APP_YML: &str = "
+user:
+  _load:
+    client: Db
+    connection: ${conns.tenant}
+    table: \"users\"
+    map:
+      id: \"id\"
+  id:
+";
+        let host = |h: &[u8]| Value::Mapping(vec![(b"host".to_vec(), Value::Scalar(h.to_vec()))]);
+        let full = |h: &[u8]| Value::Mapping(vec![
+            (b"host".to_vec(), Value::Scalar(h.to_vec())),
+            (b"driver".to_vec(), Value::Scalar(b"postgres".to_vec())),
+        ]);
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("tenant-src".to_string(), host(b"h1"));
+        let db = Arc::new(ConnDb(Default::default()));
+        let file = MapFile::new(&[("m/conns.yml", CONNS_YML), ("m/app.yml", APP_YML)]);
+        let mut state = State::new("m").with_manifest_file(file).with_in_memory(client.clone()).with_db(db.clone());
+
+        // conns.tenant runs its own _load first
+        assert!(state.get("app.user").unwrap().is_some());
+        assert_eq!(db.0.lock().unwrap().pop(), Some(full(b"h1")));
+
+        // a partial value already in the connection's _store still reaches Db whole
+        client.data.lock().unwrap().insert("tenant-conn".to_string(), host(b"h2"));
+        let file = MapFile::new(&[("m/conns.yml", CONNS_YML), ("m/app.yml", APP_YML)]);
+        let mut state = State::new("m").with_manifest_file(file).with_in_memory(client).with_db(db.clone());
+        assert!(state.get("app.user").unwrap().is_some());
+        assert_eq!(db.0.lock().unwrap().pop(), Some(full(b"h2")));
+    }
+
+    #[test]
+    fn test_deep_manifest_fails_cleanly() {
+        let deep = |depth: usize| {
+            let mut src = String::new();
+            for i in 0..depth {
+                src.push_str(&" ".repeat(i * 2));
+                src.push_str("k:\n");
+            }
+            src
+        };
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[])).with_max_manifest_depth(16);
+        assert!(state.insert_manifest_source("ok", &deep(16)).is_ok());
+        assert!(matches!(state.insert_manifest_source("deep", &deep(17)),
+            Err(ManifestError::ParseError(m)) if m == "nesting too deep (max 16)"));
+
+        // beyond the YAML reader's own limit, still an error rather than a crash
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[]));
+        assert!(matches!(state.insert_manifest_source("deep", &deep(1000)), Err(ManifestError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_missing_keys() {
+        let client = Arc::new(MockInMemory::new());
+        let mut state = stores_state().with_in_memory(client);
+        assert_eq!(state.get("stores.conn").unwrap(), None);
+        assert_eq!(state.get("stores.plain.value").unwrap(), None);
+        assert_eq!(state.get("stores.conn").unwrap(), None);
+        assert!(state.get("stores.missing").is_err());
+        assert_eq!(state.missing_keys(), ["stores.conn", "stores.plain.value"]);
+
+        state.clear_missing_keys();
+        state.set("stores.conn", Value::Scalar(b"c".to_vec()), None).unwrap();
+        assert!(state.exists("stores.conn").unwrap());
+        assert!(!state.exists("stores.plain.value").unwrap());
+        assert_eq!(state.missing_keys(), ["stores.plain.value"]);
+    }
+
+    #[test]
+    fn test_template_embeds_numbers_verbatim() {
+        const NUMS_YML: &str = "
+ids:
+  int: 42
+  float: 3.0
+  exp: 1.5e3
+  neg: -7
+  text: \"3.10\"
+a:
+  _store:
+    client: InMemory
+    key: \"a:${nums.ids.int}:${nums.ids.float}\"
+b:
+  _store:
+    client: InMemory
+    key: \"b:${nums.ids.exp}:${nums.ids.neg}:${nums.ids.text}\"
+";
+        let client = Arc::new(MockInMemory::new());
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/nums.yml", NUMS_YML)])).with_in_memory(client.clone());
+        assert!(state.set("nums.a", Value::Scalar(b"x".to_vec()), None).unwrap());
+        assert!(state.set("nums.b", Value::Scalar(b"x".to_vec()), None).unwrap());
+        let mut keys: Vec<String> = client.data.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["a:42:3.0", "b:1500.0:-7:3.10"]);
+
+        // a loaded scalar keeps its own text
+        client.data.lock().unwrap().clear();
+        state.insert_manifest_source("app", "n:\n  _store:\n    client: InMemory\n    key: \"n\"\nrow:\n  _store:\n    client: InMemory\n    key: \"row:${app.n}\"\n").unwrap();
+        state.set("app.n", Value::Scalar(b"3.50".to_vec()), None).unwrap();
+        state.set("app.row", Value::Scalar(b"x".to_vec()), None).unwrap();
+        assert!(client.data.lock().unwrap().contains_key("row:3.50"));
+    }
+
+    #[test]
+    fn test_meta_block() {
+        let mut state = State::new("./examples/manifest");
+        let s = |v: &str| Value::Scalar(v.as_bytes().to_vec());
+        let Some(Value::Mapping(load)) = state.meta_block("cache.user", "_load").unwrap() else { panic!("expected _load") };
+        let get = |k: &str| load.iter().find(|(n, _)| n == k.as_bytes()).map(|(_, v)| v.clone());
+        assert_eq!(get("client"), Some(s("Db")));
+        assert_eq!(get("connection"), Some(s("${connection.tenant}")));
+        assert_eq!(get("table"), Some(s("users")));
+
+        // inherited from the owner
+        let store = state.meta_block("cache.user.id", "_store").unwrap();
+        assert_eq!(store, state.meta_block("cache.user", "_store").unwrap());
+        assert_eq!(state.meta_block("cache.user.id", "_state").unwrap(),
+            Some(Value::Mapping(vec![(b"type".to_vec(), s("integer"))])));
+        assert_eq!(state.meta_block("cache.user", "_state").unwrap(), None);
+        assert_eq!(state.meta_block("cache.user", "_stoer").unwrap(), None);
+        assert!(matches!(state.meta_block("cache.nope", "_load"), Err(StateError::KeyNotFound(_))));
+    }
+
+    #[test]
+    fn test_extension_priority() {
+        let files = || MapFile::new(&[
+            ("m/a.yml", "k:\n  v: \"yml\"\n"),
+            ("m/a.yaml", "k:\n  v: \"yaml\"\n"),
+            ("m/b.json", "{\"k\": {\"v\": \"json\"}}"),
+        ]);
+        let v = |s: &str| Some(Value::Scalar(s.as_bytes().to_vec()));
+
+        let mut strict = State::new("m").with_manifest_file(files());
+        assert!(matches!(strict.get("a.k.v"), Err(StateError::ManifestLoadFailed(m)) if m.contains("AmbiguousFile")));
+
+        let warnings = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        let mut state = State::new("m")
+            .with_manifest_file(files())
+            .with_extension_priority(&["yaml", "yml", "json"])
+            .with_warning_handler(Arc::new(move |m: &str| sink.lock().unwrap().push(m.to_string())));
+        assert_eq!(state.get("a.k.v").unwrap(), v("yaml"));
+        assert_eq!(state.get("b.k.v").unwrap(), v("json"));
+        assert_eq!(*warnings.lock().unwrap(), ["manifest 'a.yaml' used; ignoring 'a.yml'"]);
+        assert!(matches!(state.get("c.k"), Err(StateError::ManifestLoadFailed(m)) if m.contains("'c.yaml' or 'c.yml' or 'c.json'")));
+    }
+
+    #[test]
+    fn test_state_client_leaf_merges_into_parent() {
+        const APP_YML: &str = "
+org_id:
+  _store:
+    client: InMemory
+    key: \"org\"
+user:
+  _store:
+    client: InMemory
+    key: \"user\"
+  id:
+  tenant_id:
+    _load:
+      client: State
+      key: \"${app.org_id}\"
+";
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("org".into(), Value::Scalar(b"5".to_vec()));
+        client.data.lock().unwrap().insert("user".into(), Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"1".to_vec()))]));
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/app.yml", APP_YML)])).with_in_memory(client.clone());
+
+        state.get("app.user").unwrap();
+        assert_eq!(state.get("app.user.tenant_id").unwrap(), Some(Value::Scalar(b"5".to_vec())));
+        assert_eq!(state.get("app.user").unwrap(), Some(Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(b"1".to_vec())),
+            (b"tenant_id".to_vec(), Value::Scalar(b"5".to_vec())),
+        ])));
+        // only the instance cache is touched
+        assert_eq!(client.data.lock().unwrap().get("user"), Some(&Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"1".to_vec()))])));
+
+        // leaf first: the parent picks the field up when it is read later
+        let mut state = State::new("m").with_manifest_file(MapFile::new(&[("m/app.yml", APP_YML)])).with_in_memory(client.clone());
+        assert_eq!(state.get("app.user.tenant_id").unwrap(), Some(Value::Scalar(b"5".to_vec())));
+        assert_eq!(state.get("app.user").unwrap(), Some(Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(b"1".to_vec())),
+            (b"tenant_id".to_vec(), Value::Scalar(b"5".to_vec())),
+        ])));
+        assert_eq!(client.data.lock().unwrap().get("user"), Some(&Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"1".to_vec()))])));
+    }
+
+    #[test]
+    fn test_ttl() {
+        type Entry = (Vec<u8>, Option<u64>);
+        struct TtlKVS(std::sync::Mutex<HashMap<String, Entry>>);
+        impl KVSClient for TtlKVS {
+            fn get(&self, key: &str) -> Option<Vec<u8>> { self.0.lock().unwrap().get(key).map(|(v, _)| v.clone()) }
+            fn set(&self, key: &str, value: Vec<u8>, ttl: Option<u64>) -> bool { self.0.lock().unwrap().insert(key.into(), (value, ttl)); true }
+            fn delete(&self, key: &str) -> bool { self.0.lock().unwrap().remove(key).is_some() }
+            fn ttl(&self, key: &str) -> Option<i64> { self.0.lock().unwrap().get(key)?.1.map(|t| t as i64) }
+        }
+        let kvs = Arc::new(TtlKVS(Default::default()));
+        let mut state = stores_state().with_kvs(kvs).with_in_memory(Arc::new(MockInMemory::new()));
+        assert_eq!(state.ttl("stores.user").unwrap(), None);
+        state.set("stores.user", Value::Scalar(b"u".to_vec()), Some(60)).unwrap();
+        assert_eq!(state.ttl("stores.user").unwrap(), Some(60));
+        assert_eq!(state.ttl("stores.user.id").unwrap(), Some(60));
+
+        state.set("stores.conn", Value::Scalar(b"c".to_vec()), Some(60)).unwrap();
+        assert_eq!(state.ttl("stores.conn").unwrap(), None);
+        assert_eq!(state.ttl("stores.plain.value").unwrap(), None);
+    }
+
+    #[test]
+    fn test_cache_ttl_expires_instance_cache_entry() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Scalar(b"old".to_vec()));
+        let start = std::time::Instant::now();
+        let elapsed = Arc::new(std::sync::Mutex::new(std::time::Duration::ZERO));
+        let clock = elapsed.clone();
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(client.clone())
+            .with_clock(move || start + *clock.lock().unwrap());
+        state.insert_manifest_source("app", "profile:\n  _state:\n    cache_ttl: 0.05\n  _load:\n    client: InMemory\n    key: \"profile-src\"\n").unwrap();
+
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Scalar(b"new".to_vec()));
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+
+        *elapsed.lock().unwrap() = std::time::Duration::from_millis(49);
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+        *elapsed.lock().unwrap() = std::time::Duration::from_millis(50);
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"new".to_vec())));
+    }
+
+    #[test]
+    fn test_cache_ttl_starts_when_a_slow_load_returns() {
+        // a load that takes 40ms on the injected clock
+        struct SlowInMemory(Arc<MockInMemory>, Arc<std::sync::Mutex<std::time::Duration>>);
+        impl crate::ports::required::InMemoryClient for SlowInMemory {
+            fn get(&self, key: &str) -> Option<Value> {
+                *self.1.lock().unwrap() += std::time::Duration::from_millis(40);
+                self.0.get(key)
+            }
+            fn set(&self, key: &str, value: Value) -> bool { self.0.set(key, value) }
+            fn delete(&self, key: &str) -> bool { self.0.delete(key) }
+        }
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Scalar(b"old".to_vec()));
+        let start = std::time::Instant::now();
+        let elapsed = Arc::new(std::sync::Mutex::new(std::time::Duration::ZERO));
+        let clock = elapsed.clone();
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(Arc::new(SlowInMemory(client.clone(), elapsed.clone())))
+            .with_clock(move || start + *clock.lock().unwrap());
+        state.insert_manifest_source("app", "profile:\n  _state:\n    cache_ttl: 0.05\n  _load:\n    client: InMemory\n    key: \"profile-src\"\n\
+missing:\n  _state:\n    cache_ttl: 0.05\n  _load:\n    client: InMemory\n    key: \"nothing\"\n").unwrap();
+
+        // stamped at 40ms, when the value arrived, so still fresh at 80ms
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+        client.data.lock().unwrap().insert("profile-src".to_string(), Value::Scalar(b"new".to_vec()));
+        *elapsed.lock().unwrap() = std::time::Duration::from_millis(80);
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"old".to_vec())));
+        *elapsed.lock().unwrap() = std::time::Duration::from_millis(90);
+        assert_eq!(state.get("app.profile").unwrap(), Some(Value::Scalar(b"new".to_vec())));
+
+        // a miss caches nothing, so it leaves no timestamp behind
+        let missing = state.manifest.find("app", "missing").unwrap();
+        assert!(state.get("app.missing").is_err());
+        assert!(!state.cached_at.contains_key(&missing));
+    }
+
+    #[test]
+    fn test_unknown_store_client_is_an_error() {
+        let warnings = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let sink = warnings.clone();
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/app.yml", "prefs:\n  _store:\n    client: KSV\n    key: \"prefs\"\n")]))
+            .with_warning_handler(Arc::new(move |m: &str| sink.lock().unwrap().push(m.to_string())));
+
+        assert_eq!(state.get("app.prefs"), Err(StateError::StoreClientNotConfigured("KSV".into())));
+        assert_eq!(state.exists("app.prefs"), Err(StateError::StoreClientNotConfigured("KSV".into())));
+        assert!(warnings.lock().unwrap().iter().all(|w| w.contains("KSV")));
+        assert_eq!(warnings.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_store_client_fails_every_store_operation() {
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[("m/app.yml", "prefs:\n  _store:\n    client: KSV\n    key: \"prefs\"\n")]));
+        let v = || Value::Scalar(b"v".to_vec());
+        let unknown = || StateError::StoreClientNotConfigured("KSV".into());
+
+        assert_eq!(state.set("app.prefs", v(), None), Err(unknown()));
+        assert_eq!(state.set_if_absent("app.prefs", v(), None), Err(unknown()));
+        assert_eq!(state.compare_and_set("app.prefs", None, v(), None), Err(unknown()));
+        assert_eq!(state.delete("app.prefs"), Err(unknown()));
+        assert_eq!(state.delete_prefix("app.prefs", "pre"), Err(unknown()));
+        assert_eq!(state.ttl("app.prefs"), Err(unknown()));
+        assert_eq!(state.exists_deep("app.prefs"), Err(unknown()));
+        assert_eq!(state.get_raw("app.prefs"), Err(unknown()));
+    }
+
+    #[test]
+    fn test_materialize_fills_individually_loaded_children() {
+        let client = Arc::new(MockInMemory::new());
+        client.data.lock().unwrap().insert("conn".into(), Value::Mapping(vec![]));
+        client.data.lock().unwrap().insert("org".into(), Value::Scalar(b"5".to_vec()));
+        client.data.lock().unwrap().insert("user-src".into(), Value::Mapping(vec![(b"id".to_vec(), Value::Scalar(b"1".to_vec()))]));
+        let profile = Value::Mapping(vec![(b"bio".to_vec(), Value::Scalar(b"hi".to_vec()))]);
+        let mut state = State::new("m")
+            .with_manifest_file(MapFile::new(&[]))
+            .with_in_memory(client)
+            .with_db(Arc::new(RowsDb { rows: vec![profile] }));
+        state.insert_manifest_source("app", "
+conn:
+  _store:
+    client: InMemory
+    key: \"conn\"
+org_id:
+  _store:
+    client: InMemory
+    key: \"org\"
+user:
+  _load:
+    client: InMemory
+    key: \"user-src\"
+  id:
+  profile:
+    _load:
+      client: Db
+      connection: ${app.conn}
+      table: \"profiles\"
+      map:
+        bio: \"bio\"
+  tenant_id:
+    _load:
+      client: State
+      key: \"${app.org_id}\"
+").unwrap();
+
+        let Some(user) = state.materialize("app.user").unwrap() else { panic!("expected user") };
+        assert_eq!(field(&user, b"id"), Some(Value::Scalar(b"1".to_vec())));
+        assert_eq!(field(&user, b"profile"), Some(Value::Mapping(vec![(b"bio".to_vec(), Value::Scalar(b"hi".to_vec()))])));
+        assert_eq!(field(&user, b"tenant_id"), Some(Value::Scalar(b"5".to_vec())));
+        assert_eq!(state.materialize("app.org_id").unwrap(), Some(Value::Scalar(b"5".to_vec())));
+    }
+
+    #[test]
+    fn test_state_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<State>();
+    }
+
     #[test]
     fn test_with_clients_build() {
         let _ = State::new("./examples/manifest").with_kvs(Arc::new(StubKVS));
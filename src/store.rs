@@ -1,14 +1,29 @@
-use crate::ports::required::{InMemoryClient, KVSClient, HttpClient, FileClient};
+use crate::ports::required::{InMemoryClient, KVSClient, HttpClient, FileClient, StoreBackend};
 use crate::ports::provided::{StoreError, Value};
 use crate::core::fixed_bits;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// How values are turned into bytes for `KVSClient`.
+///
+/// Instances reading the same key must use the same codec; nothing in the stored
+/// bytes says which one wrote them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KvsCodec {
+    /// Scalar bytes are stored as-is; other values are stored empty.
+    #[default]
+    Raw,
+    /// Tagged binary layout (same as `Op::encode_all`); round-trips every `Value`.
+    Binary,
+}
+
 pub struct Store {
     in_memory: Option<Arc<dyn InMemoryClient>>,
     kvs: Option<Arc<dyn KVSClient>>,
     http: Option<Arc<dyn HttpClient>>,
     file: Option<Arc<dyn FileClient>>,
+    kvs_codec: KvsCodec,
+    backends: HashMap<String, Box<dyn StoreBackend>>,
 }
 
 impl Store {
@@ -18,6 +33,8 @@ impl Store {
             kvs: None,
             http: None,
             file: None,
+            kvs_codec: KvsCodec::Raw,
+            backends: HashMap::new(),
         }
     }
 
@@ -31,6 +48,11 @@ impl Store {
         self
     }
 
+    pub fn with_kvs_codec(mut self, codec: KvsCodec) -> Self {
+        self.kvs_codec = codec;
+        self
+    }
+
     pub fn with_http(mut self, client: Arc<dyn HttpClient>) -> Self {
         self.http = Some(client);
         self
@@ -41,6 +63,27 @@ impl Store {
         self
     }
 
+    /// Registers `backend` for `_store.client: {name}`. Built-in client names never
+    /// reach the registry.
+    pub fn register(&mut self, name: &str, backend: Box<dyn StoreBackend>) {
+        self.backends.insert(name.to_string(), backend);
+    }
+
+    fn backend(&self, store_config: &HashMap<String, Value>) -> Option<&dyn StoreBackend> {
+        let name = scalar_str(store_config, "client_name")?;
+        self.backends.get(name).map(|b| b.as_ref())
+    }
+
+    /// The `_store.client` name when it is neither built-in nor registered with
+    /// `register`, e.g. a typo such as `KSV`.
+    pub fn unknown_client<'c>(&self, store_config: &'c HashMap<String, Value>) -> Option<&'c str> {
+        if client_id(store_config) != Some(fixed_bits::CLIENT_CUSTOM) {
+            return None;
+        }
+        let name = scalar_str(store_config, "client_name")?;
+        (!self.backends.contains_key(name)).then_some(name)
+    }
+
     pub fn get(&self, store_config: &HashMap<String, Value>) -> Option<Value> {
         let client = client_id(store_config)?;
 
@@ -53,7 +96,7 @@ impl Store {
             fixed_bits::CLIENT_KVS => {
                 let kvs = self.kvs.as_deref()?;
                 let key = scalar_str(store_config, "key")?;
-                kvs.get(key).map(Value::Scalar)
+                self.kvs_decode(kvs.get(key)?).ok()
             }
             fixed_bits::CLIENT_HTTP => {
                 let http = self.http.as_deref()?;
@@ -66,10 +109,91 @@ impl Store {
                 let key = scalar_str(store_config, "key")?;
                 file.get(key).map(Value::Scalar)
             }
+            fixed_bits::CLIENT_CUSTOM => self.backend(store_config)?.get(store_config),
+            _ => None,
+        }
+    }
+
+    /// Like `get`, but a KVS backend failure is returned as `StoreError::Unavailable`
+    /// instead of being indistinguishable from a miss.
+    pub fn try_get(&self, store_config: &HashMap<String, Value>) -> Result<Option<Value>, StoreError> {
+        if client_id(store_config) != Some(fixed_bits::CLIENT_KVS) {
+            return Ok(self.get(store_config));
+        }
+        let (Some(kvs), Some(key)) = (self.kvs.as_deref(), scalar_str(store_config, "key")) else {
+            return Ok(None);
+        };
+        match kvs.try_get(key).map_err(StoreError::Unavailable)? {
+            Some(bytes) => self.kvs_decode(bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn kvs_encode(&self, value: Value) -> Vec<u8> {
+        match self.kvs_codec {
+            KvsCodec::Raw => value_to_bytes(value),
+            KvsCodec::Binary => crate::oplog::encode_value(&value),
+        }
+    }
+
+    fn kvs_decode(&self, bytes: Vec<u8>) -> Result<Value, StoreError> {
+        match self.kvs_codec {
+            KvsCodec::Raw => Ok(Value::Scalar(bytes)),
+            KvsCodec::Binary => crate::oplog::decode_value(&bytes).map_err(StoreError::SerializeError),
+        }
+    }
+
+    /// Whether the store has a value for the key. KVS asks `KVSClient::exists` without
+    /// decoding; other clients read the value.
+    pub fn exists(&self, store_config: &HashMap<String, Value>) -> bool {
+        if client_id(store_config) == Some(fixed_bits::CLIENT_KVS) {
+            return match (self.kvs.as_deref(), scalar_str(store_config, "key")) {
+                (Some(kvs), Some(key)) => kvs.exists(key),
+                _ => false,
+            };
+        }
+        self.get(store_config).is_some()
+    }
+
+    /// Seconds until the stored value expires. KVS only; other clients have no TTL.
+    pub fn ttl(&self, store_config: &HashMap<String, Value>) -> Option<i64> {
+        if client_id(store_config) != Some(fixed_bits::CLIENT_KVS) {
+            return None;
+        }
+        self.kvs.as_deref()?.ttl(scalar_str(store_config, "key")?)
+    }
+
+    /// Asks the backend whether `field` exists inside the stored value.
+    /// Returns `None` when the client has no field-level support.
+    pub fn has_field(&self, store_config: &HashMap<String, Value>, field: &str) -> Option<bool> {
+        let key = scalar_str(store_config, "key")?;
+        match client_id(store_config)? {
+            fixed_bits::CLIENT_IN_MEMORY => self.in_memory.as_deref()?.has_field(key, field),
+            fixed_bits::CLIENT_KVS => self.kvs.as_deref()?.has_field(key, field),
             _ => None,
         }
     }
 
+    /// Lists the backend's keys starting with `prefix`. InMemory and KVS only.
+    pub fn scan(&self, store_config: &HashMap<String, Value>, prefix: &str) -> Result<Vec<String>, StoreError> {
+        let client = client_id(store_config)
+            .ok_or(StoreError::ConfigMissing("client".into()))?;
+
+        match client {
+            fixed_bits::CLIENT_IN_MEMORY => {
+                let in_memory = self.in_memory.as_deref()
+                    .ok_or(StoreError::ClientNotConfigured)?;
+                Ok(in_memory.scan(prefix))
+            }
+            fixed_bits::CLIENT_KVS => {
+                let kvs = self.kvs.as_deref()
+                    .ok_or(StoreError::ClientNotConfigured)?;
+                Ok(kvs.scan(prefix))
+            }
+            _ => Err(StoreError::UnsupportedClient(client)),
+        }
+    }
+
     pub fn set(
         &self,
         store_config: &HashMap<String, Value>,
@@ -92,7 +216,7 @@ impl Store {
                     .ok_or(StoreError::ClientNotConfigured)?;
                 let key = scalar_str(store_config, "key")
                     .ok_or(StoreError::ConfigMissing("key".into()))?;
-                let bytes = value_to_bytes(value);
+                let bytes = self.kvs_encode(value);
                 let final_ttl = ttl.or_else(|| scalar_u64(store_config, "ttl"));
                 Ok(kvs.set(key, bytes, final_ttl))
             }
@@ -112,10 +236,62 @@ impl Store {
                 let bytes = value_to_bytes(value);
                 Ok(file.set(key, bytes))
             }
+            fixed_bits::CLIENT_CUSTOM => {
+                let backend = self.backend(store_config)
+                    .ok_or(StoreError::ClientNotConfigured)?;
+                let final_ttl = ttl.or_else(|| scalar_u64(store_config, "ttl"));
+                Ok(backend.set(store_config, value, final_ttl))
+            }
             _ => Err(StoreError::UnsupportedClient(client)),
         }
     }
 
+    /// Writes `value` only if the store has no value for the key. KVS uses
+    /// `KVSClient::set_nx`; other clients check with `get` first (not atomic).
+    pub fn set_nx(
+        &self,
+        store_config: &HashMap<String, Value>,
+        value: Value,
+        ttl: Option<u64>,
+    ) -> Result<bool, StoreError> {
+        if client_id(store_config) == Some(fixed_bits::CLIENT_KVS) {
+            let kvs = self.kvs.as_deref()
+                .ok_or(StoreError::ClientNotConfigured)?;
+            let key = scalar_str(store_config, "key")
+                .ok_or(StoreError::ConfigMissing("key".into()))?;
+            let final_ttl = ttl.or_else(|| scalar_u64(store_config, "ttl"));
+            return Ok(kvs.set_nx(key, self.kvs_encode(value), final_ttl));
+        }
+        if self.get(store_config).is_some() {
+            return Ok(false);
+        }
+        self.set(store_config, value, ttl)
+    }
+
+    /// Writes `value` only if the stored value equals `expected` (`None`: absent).
+    /// KVS compares encoded bytes through `KVSClient::cas`; other clients read then write.
+    pub fn compare_and_set(
+        &self,
+        store_config: &HashMap<String, Value>,
+        expected: Option<Value>,
+        value: Value,
+        ttl: Option<u64>,
+    ) -> Result<bool, StoreError> {
+        if client_id(store_config) == Some(fixed_bits::CLIENT_KVS) {
+            let kvs = self.kvs.as_deref()
+                .ok_or(StoreError::ClientNotConfigured)?;
+            let key = scalar_str(store_config, "key")
+                .ok_or(StoreError::ConfigMissing("key".into()))?;
+            let final_ttl = ttl.or_else(|| scalar_u64(store_config, "ttl"));
+            let expected = expected.map(|v| self.kvs_encode(v));
+            return Ok(kvs.cas(key, expected.as_deref(), self.kvs_encode(value), final_ttl));
+        }
+        if self.try_get(store_config)? != expected {
+            return Ok(false);
+        }
+        self.set(store_config, value, ttl)
+    }
+
     pub fn delete(&self, store_config: &HashMap<String, Value>) -> Result<bool, StoreError> {
         let client = client_id(store_config)
             .ok_or(StoreError::ConfigMissing("client".into()))?;
@@ -150,6 +326,11 @@ impl Store {
                     .ok_or(StoreError::ConfigMissing("key".into()))?;
                 Ok(file.delete(key))
             }
+            fixed_bits::CLIENT_CUSTOM => {
+                let backend = self.backend(store_config)
+                    .ok_or(StoreError::ClientNotConfigured)?;
+                Ok(backend.delete(store_config))
+            }
             _ => Err(StoreError::UnsupportedClient(client)),
         }
     }
@@ -169,9 +350,13 @@ fn scalar_str<'a>(config: &'a HashMap<String, Value>, key: &str) -> Option<&'a s
     }
 }
 
+/// Reads decimal text first (manifest literals and resolved placeholders such as
+/// `"14400"`), then an 8-byte little-endian integer.
 fn scalar_u64(config: &HashMap<String, Value>, key: &str) -> Option<u64> {
     match config.get(key) {
-        Some(Value::Scalar(b)) => b.as_slice().try_into().ok().map(u64::from_le_bytes),
+        Some(Value::Scalar(b)) => std::str::from_utf8(b).ok()
+            .and_then(|s| s.trim().parse().ok())
+            .or_else(|| b.as_slice().try_into().ok().map(u64::from_le_bytes)),
         _ => None,
     }
 }
@@ -337,6 +522,24 @@ mod tests {
         c
     }
 
+    #[test]
+    fn test_store_kvs_try_get_unavailable() {
+        struct DownKVS;
+        impl KVSClient for DownKVS {
+            fn get(&self, _: &str) -> Option<Vec<u8>> { None }
+            fn try_get(&self, _: &str) -> Result<Option<Vec<u8>>, String> { Err("timeout".into()) }
+            fn set(&self, _: &str, _: Vec<u8>, _: Option<u64>) -> bool { false }
+            fn delete(&self, _: &str) -> bool { false }
+        }
+        let store = Store::new().with_kvs(Arc::new(DownKVS));
+        let config = kvs_config("k");
+        assert_eq!(store.try_get(&config), Err(StoreError::Unavailable("timeout".into())));
+        assert_eq!(store.get(&config), None);
+
+        let store = Store::new().with_kvs(Arc::new(MockKVS::new()));
+        assert_eq!(store.try_get(&config), Ok(None));
+    }
+
     #[test]
     fn test_store_kvs_set_and_get() {
         let client = Arc::new(MockKVS::new());
@@ -347,6 +550,48 @@ mod tests {
         assert_eq!(store.get(&config).unwrap(), Value::Scalar(b"hello".to_vec()));
     }
 
+    #[test]
+    fn test_store_kvs_codec_round_trip() {
+        let nested = Value::Mapping(vec![
+            (b"id".to_vec(), Value::Scalar(b"42".to_vec())),
+            (b"tags".to_vec(), Value::Sequence(vec![Value::Scalar(b"a".to_vec()), Value::Null])),
+            (b"org".to_vec(), Value::Mapping(vec![(b"name".to_vec(), Value::Scalar(vec![0, 255]))])),
+        ]);
+        let config = kvs_config("k");
+
+        let store = Store::new().with_kvs(Arc::new(MockKVS::new()));
+        assert!(store.set(&config, Value::Scalar(b"raw".to_vec()), None).unwrap());
+        assert_eq!(store.get(&config), Some(Value::Scalar(b"raw".to_vec())));
+
+        let client = Arc::new(MockKVS::new());
+        let store = Store::new().with_kvs(client.clone()).with_kvs_codec(KvsCodec::Binary);
+        for value in [Value::Null, Value::Scalar(b"raw".to_vec()), nested] {
+            assert!(store.set(&config, value.clone(), None).unwrap());
+            assert_eq!(store.get(&config), Some(value.clone()));
+            assert_eq!(store.try_get(&config), Ok(Some(value)));
+        }
+
+        client.store.lock().unwrap().insert("k".to_string(), b"not encoded".to_vec());
+        assert_eq!(store.get(&config), None);
+        assert!(matches!(store.try_get(&config), Err(StoreError::SerializeError(_))));
+    }
+
+    #[test]
+    fn test_store_kvs_keeps_large_integer_exact() {
+        // beyond i64::MAX; scalars are text bytes, so no numeric conversion can round it
+        let id = Value::Scalar(b"10000000000000000001".to_vec());
+        let config = kvs_config("k");
+        for codec in [KvsCodec::Raw, KvsCodec::Binary] {
+            let client = Arc::new(MockKVS::new());
+            let store = Store::new().with_kvs(client.clone()).with_kvs_codec(codec);
+            assert!(store.set(&config, id.clone(), None).unwrap());
+            assert_eq!(store.get(&config), Some(id.clone()));
+            if codec == KvsCodec::Raw {
+                assert_eq!(client.get("k"), Some(b"10000000000000000001".to_vec()));
+            }
+        }
+    }
+
     #[test]
     fn test_store_kvs_set_uses_ttl_from_config() {
         let client = Arc::new(MockKVS::new());
@@ -356,6 +601,17 @@ mod tests {
         assert!(store.set(&config, Value::Scalar(b"1".to_vec()), None).unwrap());
     }
 
+    #[test]
+    fn test_scalar_u64_reads_text_and_le_bytes() {
+        let mut config = HashMap::new();
+        config.insert("ttl".to_string(), Value::Scalar(b"14400".to_vec()));
+        assert_eq!(scalar_u64(&config, "ttl"), Some(14400));
+        config.insert("ttl".to_string(), Value::Scalar(3600u64.to_le_bytes().to_vec()));
+        assert_eq!(scalar_u64(&config, "ttl"), Some(3600));
+        config.insert("ttl".to_string(), Value::Scalar(b"soon".to_vec()));
+        assert_eq!(scalar_u64(&config, "ttl"), None);
+    }
+
     #[test]
     fn test_store_kvs_delete() {
         let client = Arc::new(MockKVS::new());